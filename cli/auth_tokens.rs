@@ -0,0 +1,141 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use deno_core::url::Url;
+use std::fmt;
+
+/// The credentials Deno sends when it downloads a module from a host.
+#[derive(Clone, PartialEq)]
+pub enum AuthTokenData {
+  Bearer(String),
+  Basic { username: String, password: String },
+}
+
+#[derive(Clone, PartialEq)]
+pub struct AuthToken {
+  host: String,
+  token: AuthTokenData,
+}
+
+impl AuthToken {
+  /// Returns the value of the `Authorization` request header.
+  pub fn header_value(&self) -> String {
+    match &self.token {
+      AuthTokenData::Bearer(token) => format!("Bearer {}", token),
+      AuthTokenData::Basic { username, password } => format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", username, password))
+      ),
+    }
+  }
+}
+
+// Credentials must not end up in logs or error messages.
+impl fmt::Debug for AuthToken {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let kind = match self.token {
+      AuthTokenData::Bearer(_) => "Bearer",
+      AuthTokenData::Basic { .. } => "Basic",
+    };
+    write!(
+      f,
+      "AuthToken {{ host: {:?}, token: {} [redacted] }}",
+      self.host, kind
+    )
+  }
+}
+
+/// Credentials for the hosts that serve private modules, configured with the
+/// `DENO_AUTH_TOKENS` environment variable as a semicolon separated list of
+/// `token@host` (a bearer token) and `username:password@host` (basic
+/// authentication) entries. The host may include a port.
+#[derive(Clone, Debug, Default)]
+pub struct AuthTokens(Vec<AuthToken>);
+
+impl AuthTokens {
+  pub fn new(maybe_tokens_str: Option<String>) -> Self {
+    let mut tokens = Vec::new();
+    if let Some(tokens_str) = maybe_tokens_str {
+      for token_str in tokens_str.split(';') {
+        let token_str = token_str.trim();
+        if token_str.is_empty() {
+          continue;
+        }
+        // Tokens may contain "@", the host can't.
+        match token_str.rfind('@') {
+          Some(index) if index > 0 && index < token_str.len() - 1 => {
+            let credentials = &token_str[..index];
+            let host = token_str[index + 1..].to_lowercase();
+            let token = match credentials.find(':') {
+              Some(colon) => AuthTokenData::Basic {
+                username: credentials[..colon].to_string(),
+                password: credentials[colon + 1..].to_string(),
+              },
+              None => AuthTokenData::Bearer(credentials.to_string()),
+            };
+            tokens.push(AuthToken { host, token });
+          }
+          _ => error!("Badly formed auth token discarded."),
+        }
+      }
+    }
+    Self(tokens)
+  }
+
+  /// Returns the credentials for a URL. Credentials configured for a host also
+  /// apply to its subdomains, and those that include a port only apply to that
+  /// port.
+  pub fn get(&self, url: &Url) -> Option<AuthToken> {
+    let host = url.host_str()?.to_lowercase();
+    let host_and_port = match url.port() {
+      Some(port) => format!("{}:{}", host, port),
+      None => host,
+    };
+    self
+      .0
+      .iter()
+      .find(|token| {
+        host_and_port == token.host
+          || host_and_port.ends_with(&format!(".{}", token.host))
+      })
+      .cloned()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_auth_tokens() {
+    let auth_tokens = AuthTokens::new(Some(
+      "abc123@deno.land;user:p@ss:word@localhost:4545;bad;".to_string(),
+    ));
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    assert_eq!(
+      auth_tokens.get(&url).unwrap().header_value(),
+      "Bearer abc123"
+    );
+    let url = Url::parse("https://www.deno.land/x/mod.ts").unwrap();
+    assert_eq!(
+      auth_tokens.get(&url).unwrap().header_value(),
+      "Bearer abc123"
+    );
+    let url = Url::parse("https://evildeno.land/x/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url).is_none());
+    let url = Url::parse("http://localhost:4545/mod.ts").unwrap();
+    assert_eq!(
+      auth_tokens.get(&url).unwrap().header_value(),
+      format!("Basic {}", base64::encode("user:p@ss:word"))
+    );
+    let url = Url::parse("http://localhost:4546/mod.ts").unwrap();
+    assert!(auth_tokens.get(&url).is_none());
+  }
+
+  #[test]
+  fn test_auth_token_debug_is_redacted() {
+    let auth_tokens = AuthTokens::new(Some("abc123@deno.land".to_string()));
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    let debug = format!("{:?}", auth_tokens.get(&url).unwrap());
+    assert!(!debug.contains("abc123"));
+  }
+}
@@ -0,0 +1,214 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Inspection and cleanup of the caches in `$DENO_DIR`, used by `deno dir`.
+
+use crate::colors;
+use crate::disk_cache::DiskCache;
+use crate::http_cache::HttpCache;
+use crate::info::human_size;
+use deno_core::error::AnyError;
+use deno_core::url::Url;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// The extensions of the files the compiler output of a module is stored in.
+const EMIT_EXTENSIONS: &[&str] = &["js", "js.map", "meta", "buildinfo"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedModule {
+  pub url: String,
+  pub filename: PathBuf,
+  pub size: u64,
+  /// Seconds since the UNIX epoch at which the module was fetched.
+  pub fetched_at: Option<u64>,
+}
+
+/// Returns the remote modules in the cache, sorted by URL.
+pub fn list(http_cache: &HttpCache) -> Vec<CachedModule> {
+  http_cache
+    .entries()
+    .into_iter()
+    .map(|(filename, metadata)| {
+      let file_metadata = fs::metadata(&filename).ok();
+      let size = file_metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+      // Entries written before the fetch time was recorded fall back to the
+      // modification time of the cached file.
+      let fetched_at = metadata.fetched_at.or_else(|| {
+        file_metadata
+          .and_then(|m| m.modified().ok())
+          .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+          .map(|d| d.as_secs())
+      });
+      CachedModule {
+        url: metadata.url,
+        filename,
+        size,
+        fetched_at,
+      }
+    })
+    .collect()
+}
+
+/// Formats seconds since the UNIX epoch as a UTC date and time.
+fn format_timestamp(secs: u64) -> String {
+  let days = (secs / 86_400) as i64;
+  let time = secs % 86_400;
+  // Converts days since the epoch to a civil date, see
+  // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+    year,
+    month,
+    day,
+    time / 3600,
+    time % 3600 / 60,
+    time % 60
+  )
+}
+
+impl fmt::Display for CachedModule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let fetched_at = self
+      .fetched_at
+      .map(format_timestamp)
+      .unwrap_or_else(|| "unknown".to_string());
+    write!(
+      f,
+      "{} {}",
+      self.url,
+      colors::gray(&format!(
+        "({}, fetched {})",
+        human_size(self.size as f64),
+        fetched_at
+      ))
+    )
+  }
+}
+
+/// Adds the targets of the redirects cached for `urls` to the set, so that
+/// pruning keeps both ends of a redirect.
+pub fn with_redirects(
+  http_cache: &HttpCache,
+  urls: impl IntoIterator<Item = Url>,
+) -> HashSet<Url> {
+  let mut reachable = HashSet::new();
+  for url in urls {
+    let mut url = url;
+    while reachable.insert(url.clone()) {
+      let location = match http_cache.get_metadata(&url) {
+        Ok(metadata) => metadata.headers.get("location").cloned(),
+        Err(_) => None,
+      };
+      match location.and_then(|l| url.join(&l).ok()) {
+        Some(redirect_url) => url = redirect_url,
+        None => break,
+      }
+    }
+  }
+  reachable
+}
+
+/// Removes the remote modules that are not in `reachable` from the cache,
+/// together with their compiler output. Returns the URLs of the removed
+/// modules; nothing is removed when `dry_run` is set.
+pub fn prune(
+  http_cache: &HttpCache,
+  gen_cache: &DiskCache,
+  reachable: &HashSet<Url>,
+  dry_run: bool,
+) -> Result<Vec<String>, AnyError> {
+  let mut removed = Vec::new();
+  for (_, metadata) in http_cache.entries() {
+    let url = match Url::parse(&metadata.url) {
+      Ok(url) => url,
+      Err(_) => continue,
+    };
+    if reachable.contains(&url) {
+      continue;
+    }
+    if !dry_run {
+      http_cache.remove(&url)?;
+      clear_emit(gen_cache, &url)?;
+    }
+    removed.push(metadata.url);
+  }
+  Ok(removed)
+}
+
+/// Removes the compiler output of a module. Returns the number of files that
+/// were removed.
+pub fn clear_emit(gen_cache: &DiskCache, url: &Url) -> Result<usize, AnyError> {
+  let mut count = 0;
+  for extension in EMIT_EXTENSIONS {
+    let filename = gen_cache.get_cache_filename_with_extension(url, extension);
+    match gen_cache.remove(&filename) {
+      Ok(()) => count += 1,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+      Err(e) => return Err(e.into()),
+    }
+  }
+  Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_format_timestamp() {
+    assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+    assert_eq!(format_timestamp(1_603_024_245), "2020-10-18 12:30:45 UTC");
+  }
+
+  #[test]
+  fn test_prune() {
+    let dir = TempDir::new().unwrap();
+    let http_cache = HttpCache::new(&dir.path().join("deps"));
+    let gen_cache = DiskCache::new(&dir.path().join("gen"));
+    let used = Url::parse("https://deno.land/x/used.ts").unwrap();
+    let target = Url::parse("https://deno.land/x/target.ts").unwrap();
+    let unused = Url::parse("https://deno.land/x/unused.ts").unwrap();
+
+    let mut headers = HashMap::new();
+    headers.insert("location".to_string(), "/x/target.ts".to_string());
+    http_cache.set(&used, headers, b"").unwrap();
+    http_cache.set(&target, HashMap::new(), b"1").unwrap();
+    http_cache.set(&unused, HashMap::new(), b"2").unwrap();
+    let emit = gen_cache.get_cache_filename_with_extension(&unused, "js");
+    gen_cache.set(&emit, b"2").unwrap();
+
+    let reachable = with_redirects(&http_cache, vec![used.clone()]);
+    assert_eq!(reachable.len(), 2);
+    assert!(reachable.contains(&target));
+
+    let removed = prune(&http_cache, &gen_cache, &reachable, true).unwrap();
+    assert_eq!(removed, vec![unused.to_string()]);
+    assert_eq!(list(&http_cache).len(), 3);
+
+    let removed = prune(&http_cache, &gen_cache, &reachable, false).unwrap();
+    assert_eq!(removed, vec![unused.to_string()]);
+    let urls: Vec<String> =
+      list(&http_cache).into_iter().map(|m| m.url).collect();
+    assert_eq!(urls, vec![target.to_string(), used.to_string()]);
+    assert!(gen_cache.get(&emit).is_err());
+    assert_eq!(clear_emit(&gen_cache, &unused).unwrap(), 0);
+  }
+}
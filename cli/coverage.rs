@@ -1,13 +1,28 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use crate::colors;
+use crate::flags::CoverageThreshold;
+use crate::fs as deno_fs;
 use crate::inspector::DenoInspector;
 use crate::inspector::InspectorSession;
+use crate::source_maps::SourceMapGetter;
+use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::serde_json;
 use deno_core::serde_json::json;
 use deno_core::url::Url;
+use regex::Regex;
 use serde::Deserialize;
+use serde::Serialize;
+use sourcemap::SourceMap;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use uuid::Uuid;
 
 pub struct CoverageCollector {
   session: Box<InspectorSession>,
@@ -80,7 +95,7 @@ impl CoverageCollector {
   }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CoverageRange {
   pub start_offset: usize,
@@ -88,7 +103,7 @@ pub struct CoverageRange {
   pub count: usize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FunctionCoverage {
   pub function_name: String,
@@ -96,7 +111,7 @@ pub struct FunctionCoverage {
   pub is_block_coverage: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScriptCoverage {
   pub script_id: String,
@@ -104,7 +119,7 @@ pub struct ScriptCoverage {
   pub functions: Vec<FunctionCoverage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Coverage {
   pub script_coverage: ScriptCoverage,
@@ -124,14 +139,114 @@ pub struct GetScriptSourceResult {
   pub bytecode: Option<String>,
 }
 
+/// The number of covered items out of all instrumented items of one kind.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CoverageRatio {
+  pub hit: usize,
+  pub found: usize,
+}
+
+impl CoverageRatio {
+  pub fn new(hit: usize, found: usize) -> Self {
+    Self { hit, found }
+  }
+
+  /// Returns the covered percentage, treating nothing to cover as fully
+  /// covered.
+  pub fn percent(&self) -> f32 {
+    if self.found == 0 {
+      100.0
+    } else {
+      self.hit as f32 / self.found as f32 * 100.0
+    }
+  }
+
+  fn add(&mut self, other: &CoverageRatio) {
+    self.hit += other.hit;
+    self.found += other.found;
+  }
+}
+
+impl fmt::Display for CoverageRatio {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:.3}% ({}/{})", self.percent(), self.hit, self.found)
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CoverageSummary {
+  pub lines: CoverageRatio,
+  pub functions: CoverageRatio,
+  pub branches: CoverageRatio,
+}
+
+impl CoverageSummary {
+  fn add(&mut self, other: &CoverageSummary) {
+    self.lines.add(&other.lines);
+    self.functions.add(&other.functions);
+    self.branches.add(&other.branches);
+  }
+
+  /// Returns an error naming every kind of coverage that falls below its
+  /// threshold.
+  pub fn check_threshold(
+    &self,
+    threshold: &CoverageThreshold,
+  ) -> Result<(), AnyError> {
+    let checks = vec![
+      ("lines", threshold.lines, self.lines),
+      ("functions", threshold.functions, self.functions),
+      ("branches", threshold.branches, self.branches),
+    ];
+
+    let mut misses = Vec::new();
+    for (kind, maybe_minimum, ratio) in checks {
+      if let Some(minimum) = maybe_minimum {
+        if ratio.percent() < minimum {
+          misses.push(format!(
+            "{} {:.3}% is below {}%",
+            kind,
+            ratio.percent(),
+            minimum
+          ));
+        }
+      }
+    }
+
+    if misses.is_empty() {
+      Ok(())
+    } else {
+      Err(generic_error(format!(
+        "Coverage threshold not met: {}",
+        misses.join(", ")
+      )))
+    }
+  }
+}
+
+fn colorize_ratio(ratio: &CoverageRatio) -> String {
+  let text = ratio.to_string();
+  let percent = ratio.percent();
+  if percent >= 90.0 {
+    colors::green(&text).to_string()
+  } else if percent >= 75.0 {
+    colors::yellow(&text).to_string()
+  } else {
+    colors::red(&text).to_string()
+  }
+}
+
 pub struct PrettyCoverageReporter {
   quiet: bool,
+  summaries: Vec<(String, CoverageSummary)>,
 }
 
-// TODO(caspervonb) add support for lcov output (see geninfo(1) for format spec).
 impl PrettyCoverageReporter {
   pub fn new(quiet: bool) -> PrettyCoverageReporter {
-    PrettyCoverageReporter { quiet }
+    PrettyCoverageReporter {
+      quiet,
+      summaries: Vec::new(),
+    }
   }
 
   pub fn visit_coverage(&mut self, coverage: &Coverage) {
@@ -142,7 +257,7 @@ impl PrettyCoverageReporter {
 
     let mut line_start_offset = 0;
     for (index, line) in lines.iter().enumerate() {
-      let line_end_offset = line_start_offset + line.len();
+      let line_end_offset = line_start_offset + utf16_len(line);
 
       let mut count = 0;
       for function in &coverage.script_coverage.functions {
@@ -168,6 +283,15 @@ impl PrettyCoverageReporter {
       }
     }
 
+    self.summaries.push((
+      coverage.script_coverage.url.clone(),
+      CoverageSummary {
+        lines: CoverageRatio::new(covered_lines.len(), lines.len()),
+        functions: function_ratio(coverage),
+        branches: branch_ratio(coverage),
+      },
+    ));
+
     if !self.quiet {
       print!("cover {} ... ", coverage.script_coverage.url);
 
@@ -198,6 +322,638 @@ impl PrettyCoverageReporter {
       }
     }
   }
+
+  /// Returns the combined coverage of all visited scripts.
+  pub fn total(&self) -> CoverageSummary {
+    let mut total = CoverageSummary::default();
+    for (_, summary) in &self.summaries {
+      total.add(summary);
+    }
+    total
+  }
+
+  /// Prints a table with the line, function and branch coverage of every
+  /// visited script, followed by the totals.
+  pub fn print_summary(&self) {
+    if self.quiet || self.summaries.is_empty() {
+      return;
+    }
+
+    let total = self.total();
+    let mut rows: Vec<(&str, &CoverageSummary)> = self
+      .summaries
+      .iter()
+      .map(|(url, summary)| (url.as_str(), summary))
+      .collect();
+    rows.push(("total", &total));
+
+    let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap();
+    let column_width = rows
+      .iter()
+      .flat_map(|(_, s)| {
+        vec![
+          s.lines.to_string().len(),
+          s.functions.to_string().len(),
+          s.branches.to_string().len(),
+        ]
+      })
+      .max()
+      .unwrap()
+      .max("functions".len());
+
+    println!();
+    println!("cover summary:");
+    println!(
+      "{:name_width$} | {:column_width$} | {:column_width$} | {:column_width$}",
+      "file",
+      "lines",
+      "functions",
+      "branches",
+      name_width = name_width,
+      column_width = column_width
+    );
+    // Pad by hand, the ANSI escapes would throw off the format widths.
+    let pad = |ratio: &CoverageRatio| {
+      let padding = column_width - ratio.to_string().len();
+      format!("{}{}", colorize_ratio(ratio), " ".repeat(padding))
+    };
+    for (name, summary) in rows {
+      println!(
+        "{:name_width$} | {} | {} | {}",
+        name,
+        pad(&summary.lines),
+        pad(&summary.functions),
+        pad(&summary.branches),
+        name_width = name_width
+      );
+    }
+  }
+}
+
+/// Returns the length of `s` in UTF-16 code units, the unit V8 reports
+/// coverage offsets in.
+fn utf16_len(s: &str) -> usize {
+  s.encode_utf16().count()
+}
+
+/// The first function V8 reports for a script is its top-level code, which
+/// spans the whole source. It is not counted as a function.
+fn is_top_level(coverage: &Coverage, function: &FunctionCoverage) -> bool {
+  match function.ranges.first() {
+    Some(range) => {
+      range.start_offset == 0
+        && range.end_offset >= utf16_len(&coverage.script_source)
+    }
+    None => true,
+  }
+}
+
+/// Returns how many of the script's functions were called at least once.
+fn function_ratio(coverage: &Coverage) -> CoverageRatio {
+  let mut ratio = CoverageRatio::default();
+  for function in &coverage.script_coverage.functions {
+    if is_top_level(coverage, function) {
+      continue;
+    }
+    ratio.found += 1;
+    if function.ranges[0].count > 0 {
+      ratio.hit += 1;
+    }
+  }
+  ratio
+}
+
+/// Returns how many of the script's blocks were taken. Every range after the
+/// first one of a function is a block (the body of a branch or loop).
+fn branch_ratio(coverage: &Coverage) -> CoverageRatio {
+  let mut ratio = CoverageRatio::default();
+  for function in &coverage.script_coverage.functions {
+    for range in function.ranges.iter().skip(1) {
+      ratio.found += 1;
+      if range.count > 0 {
+        ratio.hit += 1;
+      }
+    }
+  }
+  ratio
+}
+
+/// Renders coverages as an LCOV tracefile (see geninfo(1) for the format
+/// spec). Line numbers are remapped through the emitted source map, so that
+/// TypeScript modules are reported against their original source.
+#[derive(Default)]
+pub struct LcovCoverageReporter {
+  buffer: String,
+}
+
+impl LcovCoverageReporter {
+  pub fn new() -> LcovCoverageReporter {
+    LcovCoverageReporter {
+      buffer: String::new(),
+    }
+  }
+
+  pub fn visit_coverage(
+    &mut self,
+    coverage: &Coverage,
+    maybe_source_map: Option<SourceMap>,
+  ) {
+    let (line_counts, line_map) =
+      remap_line_counts(coverage, maybe_source_map.as_ref());
+
+    let line_offsets = line_offsets(&coverage.script_source);
+    // Returns the 1-based original line of an offset in the generated source.
+    let to_line = |offset: usize| -> usize {
+      let generated_line = match line_offsets.binary_search(&offset) {
+        Ok(index) => index,
+        Err(index) => index - 1,
+      };
+      let line = if line_map.is_empty() {
+        generated_line
+      } else {
+        line_map
+          .range(..=generated_line)
+          .next_back()
+          .map(|(_, original_line)| *original_line)
+          .unwrap_or(0)
+      };
+      line + 1
+    };
+
+    let url = &coverage.script_coverage.url;
+    let source_file = match Url::parse(url) {
+      Ok(url) if url.scheme() == "file" => url
+        .to_file_path()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| url.to_string()),
+      _ => url.to_string(),
+    };
+
+    writeln!(self.buffer, "SF:{}", source_file).unwrap();
+
+    let functions = &coverage.script_coverage.functions;
+    let mut function_names = Vec::new();
+    for (index, function) in functions.iter().enumerate() {
+      if is_top_level(coverage, function) {
+        continue;
+      }
+      // Function names must be unique within a record.
+      let name = if function.function_name.is_empty() {
+        format!("(anonymous_{})", index)
+      } else {
+        format!("{}_{}", function.function_name, index)
+      };
+      let range = &function.ranges[0];
+      writeln!(self.buffer, "FN:{},{}", to_line(range.start_offset), name)
+        .unwrap();
+      function_names.push((name, range.count));
+    }
+    for (name, count) in &function_names {
+      writeln!(self.buffer, "FNDA:{},{}", count, name).unwrap();
+    }
+    let function_ratio = function_ratio(coverage);
+    writeln!(self.buffer, "FNF:{}", function_ratio.found).unwrap();
+    writeln!(self.buffer, "FNH:{}", function_ratio.hit).unwrap();
+
+    for (block, function) in functions.iter().enumerate() {
+      let function_called =
+        function.ranges.first().map_or(false, |r| r.count > 0);
+      for (branch, range) in function.ranges.iter().skip(1).enumerate() {
+        let taken = if function_called {
+          range.count.to_string()
+        } else {
+          "-".to_string()
+        };
+        writeln!(
+          self.buffer,
+          "BRDA:{},{},{},{}",
+          to_line(range.start_offset),
+          block,
+          branch,
+          taken
+        )
+        .unwrap();
+      }
+    }
+    let branch_ratio = branch_ratio(coverage);
+    writeln!(self.buffer, "BRF:{}", branch_ratio.found).unwrap();
+    writeln!(self.buffer, "BRH:{}", branch_ratio.hit).unwrap();
+
+    let mut lines_hit = 0;
+    for (line, count) in &line_counts {
+      if *count > 0 {
+        lines_hit += 1;
+      }
+      // LCOV line numbers are 1-based.
+      writeln!(self.buffer, "DA:{},{}", line + 1, count).unwrap();
+    }
+    writeln!(self.buffer, "LF:{}", line_counts.len()).unwrap();
+    writeln!(self.buffer, "LH:{}", lines_hit).unwrap();
+    writeln!(self.buffer, "end_of_record").unwrap();
+  }
+
+  pub fn into_string(self) -> String {
+    self.buffer
+  }
+}
+
+/// Returns the UTF-16 offset at which every line of `source` starts.
+fn line_offsets(source: &str) -> Vec<usize> {
+  let mut offsets = vec![0];
+  let mut offset = 0;
+  for c in source.chars() {
+    offset += c.len_utf16();
+    if c == '\n' {
+      offsets.push(offset);
+    }
+  }
+  offsets
+}
+
+/// Returns the narrowest range that contains `offset`.
+fn innermost_range<'a>(
+  ranges: impl Iterator<Item = &'a CoverageRange>,
+  offset: usize,
+) -> Option<&'a CoverageRange> {
+  let mut innermost: Option<&CoverageRange> = None;
+  for range in ranges {
+    if range.start_offset <= offset && range.end_offset > offset {
+      let is_narrower = innermost.map_or(true, |r| {
+        range.end_offset - range.start_offset <= r.end_offset - r.start_offset
+      });
+      if is_narrower {
+        innermost = Some(range);
+      }
+    }
+  }
+  innermost
+}
+
+/// Returns the line counts of a script against its original source, along
+/// with a map from every generated line that has a mapping to the first
+/// original line it was emitted from. Without a source map, the generated
+/// line counts are returned and the map is empty.
+fn remap_line_counts(
+  coverage: &Coverage,
+  maybe_source_map: Option<&SourceMap>,
+) -> (BTreeMap<usize, usize>, BTreeMap<usize, usize>) {
+  let generated_line_counts = line_counts(coverage);
+  let mut line_map: BTreeMap<usize, usize> = BTreeMap::new();
+  let source_map = match maybe_source_map {
+    Some(source_map) => source_map,
+    None => return (generated_line_counts, line_map),
+  };
+
+  let mut original_line_counts: BTreeMap<usize, usize> = BTreeMap::new();
+  for token in source_map.tokens() {
+    if token.get_source().is_none() {
+      continue;
+    }
+    let generated_line = token.get_dst_line() as usize;
+    let original_line = token.get_src_line() as usize;
+    line_map.entry(generated_line).or_insert(original_line);
+    if let Some(count) = generated_line_counts.get(&generated_line) {
+      let entry = original_line_counts.entry(original_line).or_insert(0);
+      *entry = (*entry).max(*count);
+    }
+  }
+  (original_line_counts, line_map)
+}
+
+/// Returns the execution count of every non-blank line in the script, keyed
+/// by its 0-based line number. A line takes the count of the innermost range
+/// that contains its first non-whitespace character. Offsets are counted in
+/// UTF-16 code units, like those of the ranges.
+fn line_counts(coverage: &Coverage) -> BTreeMap<usize, usize> {
+  let mut counts = BTreeMap::new();
+
+  let mut line_start_offset = 0;
+  for (index, line) in coverage.script_source.split('\n').enumerate() {
+    let line_end_offset = line_start_offset + utf16_len(line);
+    let trimmed = line.trim_start();
+    if !trimmed.is_empty() {
+      let offset = line_end_offset - utf16_len(trimmed);
+      let ranges = coverage
+        .script_coverage
+        .functions
+        .iter()
+        .flat_map(|function| function.ranges.iter());
+      if let Some(range) = innermost_range(ranges, offset) {
+        counts.insert(index, range.count);
+      }
+    }
+    // Skip over the line feed as well.
+    line_start_offset = line_end_offset + 1;
+  }
+
+  counts
+}
+
+pub fn get_source_map<G: SourceMapGetter>(
+  source_map_getter: &G,
+  url: &str,
+) -> Option<SourceMap> {
+  source_map_getter
+    .get_source_map(url)
+    .and_then(|raw| SourceMap::from_slice(&raw).ok())
+}
+
+/// Writes the raw V8 coverage of every script, along with an LCOV tracefile
+/// of the run, into `dir`.
+pub fn write_coverages<G: SourceMapGetter>(
+  dir: &Path,
+  coverages: &[Coverage],
+  source_map_getter: &G,
+) -> Result<(), AnyError> {
+  std::fs::create_dir_all(dir)?;
+
+  let mut lcov_reporter = LcovCoverageReporter::new();
+  for coverage in coverages {
+    let url = &coverage.script_coverage.url;
+    let json = serde_json::to_string(coverage)?;
+    // Files are named uniquely so that several runs, for example test
+    // shards, can save into the same directory without overwriting each
+    // other. `deno coverage` merges the profiles later on.
+    let filename = format!("{}.json", Uuid::new_v4());
+    deno_fs::write_file(&dir.join(filename), json, 0o666)?;
+
+    lcov_reporter
+      .visit_coverage(coverage, get_source_map(source_map_getter, url));
+  }
+
+  deno_fs::write_file(
+    &dir.join(format!("{}.info", Uuid::new_v4())),
+    lcov_reporter.into_string(),
+    0o666,
+  )?;
+
+  Ok(())
+}
+
+/// Reads the raw coverages saved by `write_coverages` from `dir`.
+pub fn load_coverages(dir: &Path) -> Result<Vec<Coverage>, AnyError> {
+  let mut paths = Vec::new();
+  for entry in std::fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().map_or(false, |ext| ext == "json") {
+      paths.push(path);
+    }
+  }
+  paths.sort();
+
+  let mut coverages = Vec::new();
+  for path in paths {
+    let json = std::fs::read_to_string(&path)?;
+    let coverage: Coverage = serde_json::from_str(&json).map_err(|e| {
+      generic_error(format!("Invalid coverage file {:?}: {}", path, e))
+    })?;
+    coverages.push(coverage);
+  }
+
+  Ok(coverages)
+}
+
+/// Merges the coverages of scripts that were profiled more than once, for
+/// example by several test shards, summing up their counts.
+pub fn merge_coverages(coverages: Vec<Coverage>) -> Vec<Coverage> {
+  let mut coverages_by_url: BTreeMap<String, Vec<Coverage>> = BTreeMap::new();
+  for coverage in coverages {
+    coverages_by_url
+      .entry(coverage.script_coverage.url.clone())
+      .or_default()
+      .push(coverage);
+  }
+
+  coverages_by_url
+    .into_iter()
+    .map(|(_, coverages)| merge_script_coverages(coverages))
+    .collect()
+}
+
+fn merge_script_coverages(mut coverages: Vec<Coverage>) -> Coverage {
+  if coverages.len() == 1 {
+    return coverages.remove(0);
+  }
+
+  // Functions are identified by their root range, sorted so that outer
+  // functions come before the functions nested inside of them.
+  type FunctionKey = (usize, Reverse<usize>);
+  let mut functions: BTreeMap<FunctionKey, Vec<&FunctionCoverage>> =
+    BTreeMap::new();
+  for coverage in &coverages {
+    for function in &coverage.script_coverage.functions {
+      if let Some(root) = function.ranges.first() {
+        functions
+          .entry((root.start_offset, Reverse(root.end_offset)))
+          .or_default()
+          .push(function);
+      }
+    }
+  }
+
+  let mut merged_functions = Vec::new();
+  for ((start_offset, Reverse(end_offset)), profiles) in functions {
+    let mut range_keys: BTreeSet<(usize, Reverse<usize>)> = BTreeSet::new();
+    for function in &profiles {
+      for range in &function.ranges {
+        range_keys.insert((range.start_offset, Reverse(range.end_offset)));
+      }
+    }
+
+    // V8 omits blocks whose count equals the one of their parent range, so
+    // a range missing from a profile takes the count of the innermost range
+    // that encloses it. Profiles without the function did not call it.
+    let mut ranges = Vec::new();
+    for (range_start, Reverse(range_end)) in range_keys {
+      let count = profiles
+        .iter()
+        .filter_map(|function| {
+          innermost_range(function.ranges.iter(), range_start)
+            .filter(|range| range.end_offset >= range_end)
+        })
+        .map(|range| range.count)
+        .sum();
+      ranges.push(CoverageRange {
+        start_offset: range_start,
+        end_offset: range_end,
+        count,
+      });
+    }
+    // Keep the root range first, as V8 does.
+    let root_index = ranges
+      .iter()
+      .position(|r| {
+        r.start_offset == start_offset && r.end_offset == end_offset
+      })
+      .unwrap();
+    let root = ranges.remove(root_index);
+    ranges.insert(0, root);
+
+    merged_functions.push(FunctionCoverage {
+      function_name: profiles[0].function_name.clone(),
+      ranges,
+      is_block_coverage: profiles.iter().any(|f| f.is_block_coverage),
+    });
+  }
+
+  let first = &coverages[0];
+  Coverage {
+    script_coverage: ScriptCoverage {
+      script_id: first.script_coverage.script_id.clone(),
+      url: first.script_coverage.url.clone(),
+      functions: merged_functions,
+    },
+    script_source: first.script_source.clone(),
+  }
+}
+
+/// Keeps the coverages whose URL matches any of the `include` patterns and
+/// none of the `exclude` patterns.
+pub fn filter_coverages_by_url(
+  coverages: Vec<Coverage>,
+  include: &[String],
+  exclude: &[String],
+) -> Result<Vec<Coverage>, AnyError> {
+  let compile = |patterns: &[String]| -> Result<Vec<Regex>, AnyError> {
+    patterns
+      .iter()
+      .map(|pattern| {
+        Regex::new(pattern).map_err(|e| {
+          generic_error(format!("Invalid pattern {}: {}", pattern, e))
+        })
+      })
+      .collect()
+  };
+  let include = compile(include)?;
+  let exclude = compile(exclude)?;
+
+  Ok(
+    coverages
+      .into_iter()
+      .filter(|coverage| {
+        let url = &coverage.script_coverage.url;
+        (include.is_empty() || include.iter().any(|re| re.is_match(url)))
+          && !exclude.iter().any(|re| re.is_match(url))
+      })
+      .collect(),
+  )
+}
+
+/// Writes an HTML report into a directory: an `index.html` with the summary
+/// of every script, linking to a page per script that shows its source with
+/// covered and uncovered lines highlighted.
+pub struct HtmlCoverageReporter {
+  dir: PathBuf,
+  summaries: Vec<(String, String, CoverageSummary)>,
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+td, th { padding: 0 8px; text-align: left; }
+pre { margin: 0; }
+.hit { background-color: #dfd; }
+.miss { background-color: #fdd; }
+.count, .line { color: #888; text-align: right; }";
+
+impl HtmlCoverageReporter {
+  pub fn new(dir: PathBuf) -> Result<HtmlCoverageReporter, AnyError> {
+    std::fs::create_dir_all(&dir)?;
+    Ok(HtmlCoverageReporter {
+      dir,
+      summaries: Vec::new(),
+    })
+  }
+
+  /// Writes the page of a script. `source` is the original source the
+  /// source map points into, or the script source if there is none.
+  pub fn visit_coverage(
+    &mut self,
+    coverage: &Coverage,
+    maybe_source_map: Option<SourceMap>,
+    source: &str,
+  ) -> Result<(), AnyError> {
+    let (line_counts, _) =
+      remap_line_counts(coverage, maybe_source_map.as_ref());
+    let url = &coverage.script_coverage.url;
+    let page = format!("{}.html", self.summaries.len());
+
+    let mut rows = String::new();
+    for (index, line) in source.split('\n').enumerate() {
+      let (class, count) = match line_counts.get(&index) {
+        Some(0) => ("miss", "0".to_string()),
+        Some(count) => ("hit", count.to_string()),
+        None => ("", "".to_string()),
+      };
+      writeln!(
+        rows,
+        "<tr class=\"{}\"><td class=\"line\">{}</td><td class=\"count\">{}</td><td><pre>{}</pre></td></tr>",
+        class,
+        index + 1,
+        count,
+        escape_html(line)
+      )
+      .unwrap();
+    }
+    let html = format!(
+      "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p><a href=\"index.html\">index</a></p>\n<table>\n{rows}</table>\n</body>\n</html>\n",
+      title = escape_html(url),
+      style = HTML_STYLE,
+      rows = rows
+    );
+    deno_fs::write_file(&self.dir.join(&page), html, 0o666)?;
+
+    let lines_hit = line_counts.values().filter(|count| **count > 0).count();
+    self.summaries.push((
+      url.clone(),
+      page,
+      CoverageSummary {
+        lines: CoverageRatio::new(lines_hit, line_counts.len()),
+        functions: function_ratio(coverage),
+        branches: branch_ratio(coverage),
+      },
+    ));
+    Ok(())
+  }
+
+  /// Writes `index.html`.
+  pub fn finish(self) -> Result<(), AnyError> {
+    let mut total = CoverageSummary::default();
+    let mut rows = String::new();
+    for (url, page, summary) in &self.summaries {
+      total.add(summary);
+      writeln!(
+        rows,
+        "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+        page,
+        escape_html(url),
+        summary.lines,
+        summary.functions,
+        summary.branches
+      )
+      .unwrap();
+    }
+    writeln!(
+      rows,
+      "<tr><th>total</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+      total.lines, total.functions, total.branches
+    )
+    .unwrap();
+
+    let html = format!(
+      "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Coverage report</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n<h1>Coverage report</h1>\n<table>\n<tr><th>file</th><th>lines</th><th>functions</th><th>branches</th></tr>\n{rows}</table>\n</body>\n</html>\n",
+      style = HTML_STYLE,
+      rows = rows
+    );
+    deno_fs::write_file(&self.dir.join("index.html"), html, 0o666)?;
+    Ok(())
+  }
+}
+
+fn escape_html(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
 }
 
 pub fn filter_script_coverages(
@@ -238,3 +994,179 @@ pub fn filter_script_coverages(
     })
     .collect::<Vec<Coverage>>()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_coverage(
+    source: &str,
+    ranges: Vec<(usize, usize, usize)>,
+  ) -> Coverage {
+    Coverage {
+      script_coverage: ScriptCoverage {
+        script_id: "1".to_string(),
+        url: "https://deno.land/x/mod.js".to_string(),
+        functions: vec![FunctionCoverage {
+          function_name: "".to_string(),
+          ranges: ranges
+            .into_iter()
+            .map(|(start_offset, end_offset, count)| CoverageRange {
+              start_offset,
+              end_offset,
+              count,
+            })
+            .collect(),
+          is_block_coverage: true,
+        }],
+      },
+      script_source: source.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_line_counts() {
+    let source = "a();\n\nif (x) {\n  b();\n}\n";
+    // The block `{\n  b();\n}` was never taken.
+    let coverage = make_coverage(source, vec![(0, 24, 1), (13, 23, 0)]);
+    let counts = line_counts(&coverage);
+    let expected: BTreeMap<usize, usize> =
+      vec![(0, 1), (2, 1), (3, 0), (4, 0)].into_iter().collect();
+    assert_eq!(counts, expected);
+  }
+
+  #[test]
+  fn test_line_counts_utf16_offsets() {
+    // "ü" is two bytes in UTF-8 but a single UTF-16 code unit, and "😀" is
+    // four bytes but two code units.
+    let source = "a(\"ü😀\");\nif (x) {\n  b();\n}\n";
+    let coverage = make_coverage(source, vec![(0, 28, 1), (17, 27, 0)]);
+    assert!(is_top_level(
+      &coverage,
+      &coverage.script_coverage.functions[0]
+    ));
+    let counts = line_counts(&coverage);
+    let expected: BTreeMap<usize, usize> =
+      vec![(0, 1), (1, 1), (2, 0), (3, 0)].into_iter().collect();
+    assert_eq!(counts, expected);
+    assert_eq!(line_offsets(source), vec![0, 10, 19, 26, 28]);
+  }
+
+  #[test]
+  fn test_lcov_reporter() {
+    let source = "a();\n\nif (x) {\n  b();\n}\n";
+    let coverage = make_coverage(source, vec![(0, 24, 1), (13, 23, 0)]);
+    let mut reporter = LcovCoverageReporter::new();
+    reporter.visit_coverage(&coverage, None);
+    assert_eq!(
+      reporter.into_string(),
+      [
+        "SF:https://deno.land/x/mod.js",
+        "FNF:0",
+        "FNH:0",
+        "BRDA:3,0,0,0",
+        "BRF:1",
+        "BRH:0",
+        "DA:1,1",
+        "DA:3,1",
+        "DA:4,0",
+        "DA:5,0",
+        "LF:4",
+        "LH:2",
+        "end_of_record\n",
+      ]
+      .join("\n")
+    );
+  }
+
+  #[test]
+  fn test_function_and_branch_ratio() {
+    let source = "function f() {\n  if (x) {\n    g();\n  }\n}\nf();\n";
+    let mut coverage = make_coverage(source, vec![(0, 46, 1)]);
+    coverage.script_coverage.functions.push(FunctionCoverage {
+      function_name: "f".to_string(),
+      ranges: vec![
+        CoverageRange {
+          start_offset: 0,
+          end_offset: 41,
+          count: 1,
+        },
+        CoverageRange {
+          start_offset: 24,
+          end_offset: 38,
+          count: 0,
+        },
+      ],
+      is_block_coverage: true,
+    });
+    assert_eq!(function_ratio(&coverage), CoverageRatio::new(1, 1));
+    assert_eq!(branch_ratio(&coverage), CoverageRatio::new(0, 1));
+  }
+
+  #[test]
+  fn test_merge_coverages() {
+    let source = "a();\n\nif (x) {\n  b();\n}\n";
+    // The first shard never took the block, the second one did and had the
+    // count of the block merged into its parent range.
+    let first = make_coverage(source, vec![(0, 24, 1), (13, 23, 0)]);
+    let second = make_coverage(source, vec![(0, 24, 2)]);
+    let merged = merge_coverages(vec![first, second]);
+    assert_eq!(merged.len(), 1);
+    let ranges: Vec<(usize, usize, usize)> =
+      merged[0].script_coverage.functions[0]
+        .ranges
+        .iter()
+        .map(|r| (r.start_offset, r.end_offset, r.count))
+        .collect();
+    assert_eq!(ranges, vec![(0, 24, 3), (13, 23, 2)]);
+  }
+
+  #[test]
+  fn test_filter_coverages_by_url() {
+    let mut local = make_coverage("a();\n", vec![(0, 5, 1)]);
+    local.script_coverage.url = "file:///a/mod.ts".to_string();
+    let mut local_test = make_coverage("a();\n", vec![(0, 5, 1)]);
+    local_test.script_coverage.url = "file:///a/mod_test.ts".to_string();
+    let remote = make_coverage("a();\n", vec![(0, 5, 1)]);
+
+    let filtered = filter_coverages_by_url(
+      vec![local, local_test, remote],
+      &["^file:".to_string()],
+      &["_test\\.ts$".to_string()],
+    )
+    .unwrap();
+    let urls: Vec<&str> = filtered
+      .iter()
+      .map(|c| c.script_coverage.url.as_str())
+      .collect();
+    assert_eq!(urls, vec!["file:///a/mod.ts"]);
+
+    assert!(filter_coverages_by_url(vec![], &["(".to_string()], &[]).is_err());
+  }
+
+  #[test]
+  fn test_check_threshold() {
+    let summary = CoverageSummary {
+      lines: CoverageRatio::new(8, 10),
+      functions: CoverageRatio::new(1, 2),
+      branches: CoverageRatio::new(0, 0),
+    };
+    let threshold = CoverageThreshold {
+      lines: Some(80.0),
+      functions: None,
+      branches: Some(100.0),
+    };
+    assert!(summary.check_threshold(&threshold).is_ok());
+
+    let threshold = CoverageThreshold {
+      lines: Some(90.0),
+      functions: Some(50.0),
+      branches: None,
+    };
+    let err = summary.check_threshold(&threshold).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Coverage threshold not met: lines 80.000% is below 90%"
+    );
+  }
+}
@@ -182,6 +182,7 @@ pub fn prepare_doctests(
   fail_fast: bool,
   quiet: bool,
   filter: Option<String>,
+  reporter: &str,
 ) -> Result<String, AnyError> {
   let mut test_file =  "import * as assert from \"https://deno.land/std@0.70.0/testing/asserts.ts\";\n".to_string();
   let mut import_set = HashSet::new();
@@ -221,10 +222,11 @@ pub fn prepare_doctests(
   test_file.push_str("\n");
   test_file.push_str(&tests);
 
+  let report_to_console = !quiet || reporter != "pretty";
   let options = if let Some(filter) = filter {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet, "isDoctest": true, "filter": filter })
+    json!({ "failFast": fail_fast, "reportToConsole": report_to_console, "disableLog": quiet, "reporter": reporter, "isDoctest": true, "filter": filter })
   } else {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet, "isDoctest": true })
+    json!({ "failFast": fail_fast, "reportToConsole": report_to_console, "disableLog": quiet, "reporter": reporter, "isDoctest": true })
   };
 
   let run_tests_cmd = format!(
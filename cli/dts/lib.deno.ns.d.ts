@@ -1775,6 +1775,25 @@ declare namespace Deno {
     bytesSentControl: number;
     bytesSentData: number;
     bytesReceived: number;
+    /** The upper bounds, in milliseconds, of the buckets of
+     * `OpMetrics.latencyHistogram`. */
+    latencyBucketsMs: number[];
+    /** The metrics of each op that was called, by op name. */
+    ops: Record<string, OpMetrics>;
+  }
+
+  export interface OpMetrics {
+    opsDispatched: number;
+    opsDispatchedSync: number;
+    opsDispatchedAsync: number;
+    opsCompleted: number;
+    /** The number of async calls that have not completed yet. */
+    opsInFlight: number;
+    latencyTotalMs: number;
+    latencyMaxMs: number;
+    /** The number of calls that completed within each of the bounds of
+     * `Metrics.latencyBucketsMs`. The last entry counts the slower calls. */
+    latencyHistogram: number[];
   }
 
   /** Receive metrics from the privileged side of Deno. This is primarily used
@@ -1819,6 +1838,31 @@ declare namespace Deno {
    */
   export function resources(): ResourceMap;
 
+  export interface ResourceInfo {
+    name: string;
+    /** When the resource was created, in milliseconds since the epoch. */
+    createdAt: number;
+    /** The bytes read from and written to the resource with `Deno.read()`
+     * and `Deno.write()` and their sync versions. */
+    bytesRead: number;
+    bytesWritten: number;
+    /** The location of the code that created the resource, when it is known:
+     * with `deno test` or `--permission-audit`. */
+    callSite: string | null;
+  }
+
+  /** Returns a map of open resource ids (rid) along with their metadata.
+   *
+   * ```ts
+   * const file = Deno.openSync("../test.file");
+   * console.log(Deno.resources({ details: true })[file.rid]);
+   * // { name: "fsFile", createdAt: 1604000000000, bytesRead: 0, ... }
+   * ```
+   */
+  export function resources(
+    options: { details: true },
+  ): Record<number, ResourceInfo>;
+
   export interface FsEvent {
     kind: "any" | "access" | "create" | "modify" | "remove";
     paths: string[];
@@ -1923,8 +1967,13 @@ declare namespace Deno {
    *
    * By default subprocess inherits stdio of parent process. To change that
    * `opt.stdout`, `opt.stderr` and `opt.stdin` can be specified independently -
-   * they can be set to either an rid of open file or set to "inherit" "piped"
-   * or "null":
+   * they can be set to either an rid or set to "inherit" "piped" or "null".
+   *
+   * An rid can be that of an open file, which the child shares with the parent
+   * process. On Unix it can also be that of a TCP or Unix socket, or of a
+   * piped stream of another subprocess (e.g. `p.stdout.rid`). That resource is
+   * then moved to the child and closed in the parent process.
+   *
    *
    * `"inherit"` The default if unspecified. The child inherits from the
    * corresponding parent descriptor.
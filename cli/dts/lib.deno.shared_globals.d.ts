@@ -676,9 +676,11 @@ declare class Worker extends EventTarget {
        * Set to `true` to make `Deno` namespace and all of its methods
        * available to worker thread.
        *
-       * Currently worker inherits permissions from main thread (permissions
-       * given using `--allow-*` flags).
-       * Configurable permissions are on the roadmap to be implemented.
+       * By default the worker inherits the permissions of the thread that
+       * creates it. Pass an object with a `permissions` field to give it fewer
+       * permissions: "none", or an object in which each permission is
+       * `true`, `false`, an allow-list or "inherit" (the default). A worker
+       * can't be given permissions its parent doesn't have.
        *
        * Example:
        *
@@ -713,8 +715,32 @@ declare class Worker extends EventTarget {
        * hello world
        * hello world2
        *
+       * ```ts
+       * // a worker that may only read the "./data" directory
+       * const worker = new Worker(
+       *   new URL("deno_worker.ts", import.meta.url).href,
+       *   {
+       *     type: "module",
+       *     deno: {
+       *       namespace: true,
+       *       permissions: { net: false, read: ["./data"], write: false },
+       *     },
+       *   },
+       * );
+       * ```
        */
-      deno?: boolean;
+      deno?: boolean | {
+        namespace?: boolean;
+        permissions?: "inherit" | "none" | {
+          env?: "inherit" | boolean | string[];
+          hrtime?: "inherit" | boolean;
+          net?: "inherit" | boolean | string[];
+          plugin?: "inherit" | boolean;
+          read?: "inherit" | boolean | string[];
+          run?: "inherit" | boolean | string[];
+          write?: "inherit" | boolean | string[];
+        };
+      };
     },
   );
   postMessage(message: any, transfer: ArrayBuffer[]): void;
@@ -1002,6 +1002,69 @@ declare namespace Deno {
    * Requires `allow-run` permission. */
   export function kill(pid: number, signo: number): void;
 
+  export interface RunResourceLimits {
+    /** Maximum CPU time of the process, in seconds. */
+    cpu?: number;
+    /** Maximum size of the address space of the process, in bytes. */
+    memory?: number;
+    /** Maximum number of files the process may open at once. */
+    openFiles?: number;
+  }
+
+  export interface UnstableRunOptions extends RunOptions {
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Don't inherit the environment of the parent process: only the variables
+     * of `env` are set. */
+    clearEnv?: boolean;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Run the process as this user. Only supported on Unix. */
+    uid?: number;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Run the process as this group. Only supported on Unix. */
+    gid?: number;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Move the process to this process group, or to a new group whose id is
+     * its pid if `0`. Only supported on Unix. */
+    pgid?: number;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Run the process in a new session, detached from the terminal. Can't be
+     * combined with `pgid`. Only supported on Unix. */
+    setsid?: boolean;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Resource limits of the process. Only supported on Unix. */
+    rlimits?: RunResourceLimits;
+    /** **UNSTABLE**: new API, yet to be vetted.
+     *
+     * Kill the process if it is still running after this many milliseconds. */
+    timeout?: number;
+  }
+
+  /** **UNSTABLE**: new options, yet to be vetted.
+   *
+   * Spawns new subprocess, like the stable `Deno.run`, with options to isolate
+   * and limit it.
+   *
+   * ```ts
+   * const p = Deno.run({
+   *   cmd: ["./build.sh"],
+   *   clearEnv: true,
+   *   env: { PATH: "/usr/bin:/bin" },
+   *   rlimits: { cpu: 60, openFiles: 256 },
+   *   timeout: 120_000,
+   * });
+   * ```
+   *
+   * Requires `allow-run` permission. */
+  export function run<T extends UnstableRunOptions = UnstableRunOptions>(
+    opt: T,
+  ): Process<T>;
+
   /** The name of a "powerful feature" which needs permission.
    *
    * See: https://w3c.github.io/permissions/#permission-registry
@@ -1024,6 +1087,9 @@ declare namespace Deno {
 
   export interface RunPermissionDescriptor {
     name: "run";
+    /** Optional name or path of the executable associated with this
+     * descriptor. Names are resolved using the `PATH` environment variable. */
+    command?: string;
   }
 
   export interface ReadPermissionDescriptor {
@@ -1049,6 +1115,9 @@ declare namespace Deno {
 
   export interface EnvPermissionDescriptor {
     name: "env";
+    /** Optional name of the environment variable associated with this
+     * descriptor. */
+    variable?: string;
   }
 
   export interface PluginPermissionDescriptor {
@@ -1,6 +1,9 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::auth_tokens::AuthTokens;
 use crate::colors;
+use crate::http_cache;
+use crate::http_cache::FreshnessPolicy;
 use crate::http_cache::HttpCache;
 use crate::http_util;
 use crate::http_util::create_http_client;
@@ -20,6 +23,7 @@ use deno_core::ModuleSpecifier;
 use deno_fetch::reqwest;
 use log::info;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::future::Future;
 use std::io::Read;
@@ -73,6 +77,10 @@ pub struct SourceFileFetcher {
   use_disk_cache: bool,
   no_remote: bool,
   cached_only: bool,
+  /// Set with `--revalidate`, cached remote modules that are no longer fresh
+  /// according to the policy are revalidated with the server.
+  maybe_freshness: Option<FreshnessPolicy>,
+  auth_tokens: AuthTokens,
   http_client: reqwest::Client,
   // This field is public only to expose it's location
   pub http_cache: HttpCache,
@@ -85,6 +93,7 @@ impl SourceFileFetcher {
     cache_blocklist: Vec<String>,
     no_remote: bool,
     cached_only: bool,
+    maybe_freshness: Option<FreshnessPolicy>,
     ca_file: Option<&str>,
   ) -> Result<Self, AnyError> {
     let file_fetcher = Self {
@@ -94,6 +103,8 @@ impl SourceFileFetcher {
       use_disk_cache,
       no_remote,
       cached_only,
+      maybe_freshness,
+      auth_tokens: AuthTokens::new(env::var("DENO_AUTH_TOKENS").ok()),
       http_client: create_http_client(ca_file)?,
     };
 
@@ -405,6 +416,36 @@ impl SourceFileFetcher {
     }))
   }
 
+  /// Returns whether the cached entries of a remote module, and of the
+  /// redirects leading to it, can be used without revalidating them. They
+  /// always can unless `--revalidate` was given.
+  fn is_cache_fresh(&self, module_url: &Url) -> bool {
+    let freshness = match &self.maybe_freshness {
+      Some(freshness) => freshness,
+      None => return true,
+    };
+    let now = http_cache::now();
+    let mut url = module_url.clone();
+    for _ in 0..10 {
+      let metadata = match self.http_cache.get_metadata(&url) {
+        Ok(metadata) => metadata,
+        // Modules that are not cached are fetched anyway.
+        Err(_) => return true,
+      };
+      if !freshness.is_fresh(&url, &metadata, now) {
+        return false;
+      }
+      match metadata.headers.get("location") {
+        Some(location) => match url.join(location) {
+          Ok(redirect_url) => url = redirect_url,
+          Err(_) => return true,
+        },
+        None => return true,
+      }
+    }
+    true
+  }
+
   /// Asynchronously fetch remote source file specified by the URL following redirects.
   ///
   /// Note that this is a recursive method so it can't be "async", but rather return
@@ -428,8 +469,10 @@ impl SourceFileFetcher {
 
     let is_blocked =
       check_cache_blocklist(module_url, self.cache_blocklist.as_ref());
+    // Stale modules can only be revalidated if fetching is allowed.
+    let is_stale = !cached_only && !self.is_cache_fresh(module_url);
     // First try local cache
-    if use_disk_cache && !is_blocked {
+    if use_disk_cache && !is_blocked && !is_stale {
       match self.fetch_cached_remote_source(&module_url, redirect_limit) {
         Ok(Some(source_file)) => {
           return futures::future::ok(source_file).boxed_local();
@@ -462,13 +505,24 @@ impl SourceFileFetcher {
       Ok((_, headers)) => headers.get("etag").map(String::from),
       Err(_) => None,
     };
+    let maybe_auth_token = self.auth_tokens.get(&module_url);
     let permissions = permissions.clone();
     let http_client = self.http_client.clone();
     // Single pass fetch, either yields code or yields redirect.
     let f = async move {
-      match http_util::fetch_once(http_client, &module_url, module_etag).await?
+      match http_util::fetch_once(
+        http_client,
+        &module_url,
+        module_etag,
+        maybe_auth_token,
+      )
+      .await?
       {
         FetchOnceResult::NotModified => {
+          // The server confirmed the cached module, so it is fresh again.
+          if dir.maybe_freshness.is_some() {
+            dir.http_cache.refresh(&module_url)?;
+          }
           let source_file =
             dir.fetch_cached_remote_source(&module_url, 10)?.unwrap();
 
@@ -635,6 +689,7 @@ pub struct SourceCodeHeaders {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::flags::CacheTtl;
   use tempfile::TempDir;
 
   fn setup_file_fetcher(dir_path: &Path) -> SourceFileFetcher {
@@ -645,6 +700,7 @@ mod tests {
       false,
       false,
       None,
+      None,
     )
     .expect("setup fail")
   }
@@ -1807,6 +1863,61 @@ mod tests {
     assert_eq!(modified1, modified2);
   }
 
+  #[tokio::test]
+  async fn test_fetch_revalidates_stale_module() {
+    let _http_server_guard = test_util::http_server();
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let fetcher = SourceFileFetcher::new(
+      HttpCache::new(&temp_dir.path().join("deps")),
+      true,
+      vec![],
+      false,
+      false,
+      Some(FreshnessPolicy::new(&[CacheTtl {
+        host: Some("127.0.0.1:4545".to_string()),
+        seconds: 0,
+      }])),
+      None,
+    )
+    .unwrap();
+    let module_url =
+      Url::parse("http://127.0.0.1:4545/etag_script.ts").unwrap();
+
+    let source = fetcher
+      .fetch_remote_source(
+        &module_url,
+        true,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(source.source_code, "console.log('etag')");
+
+    // The module is stale right away, so it is revalidated instead of being
+    // read from the cache. The server answers with "304 Not Modified", which
+    // refreshes the fetch time of the cached module.
+    let file_name = fetcher.http_cache.get_cache_filename(&module_url);
+    fs::write(&file_name, "changed content").unwrap();
+    let mut metadata = fetcher.http_cache.get_metadata(&module_url).unwrap();
+    metadata.fetched_at = Some(0);
+    metadata.write(&file_name).unwrap();
+    let cached_source = fetcher
+      .fetch_remote_source(
+        &module_url,
+        true,
+        false,
+        1,
+        &Permissions::allow_all(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(cached_source.source_code, "changed content");
+    let metadata = fetcher.http_cache.get_metadata(&module_url).unwrap();
+    assert!(metadata.fetched_at.unwrap() > 0);
+  }
+
   #[tokio::test]
   async fn test_fetch_with_types_header() {
     let _http_server_guard = test_util::http_server();
@@ -24,6 +24,16 @@ pub enum DenoSubcommand {
   Completions {
     buf: Box<[u8]>,
   },
+  Dir {
+    command: DirCommand,
+  },
+  Coverage {
+    dir: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    lcov: bool,
+    html: Option<PathBuf>,
+  },
   Doc {
     private: bool,
     json: bool,
@@ -72,6 +82,8 @@ pub enum DenoSubcommand {
     allow_none: bool,
     include: Option<Vec<String>>,
     filter: Option<String>,
+    reporter: String,
+    jobs: usize,
   },
   Types,
   Upgrade {
@@ -81,6 +93,11 @@ pub enum DenoSubcommand {
     output: Option<PathBuf>,
     ca_file: Option<String>,
   },
+  Vendor {
+    files: Vec<String>,
+    output: PathBuf,
+    force: bool,
+  },
 }
 
 impl Default for DenoSubcommand {
@@ -89,6 +106,31 @@ impl Default for DenoSubcommand {
   }
 }
 
+/// The operations of `deno dir` on the cache in `$DENO_DIR`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DirCommand {
+  List { json: bool },
+  Headers { url: String },
+  Prune { roots: Vec<String>, dry_run: bool },
+  ClearEmit { files: Vec<String> },
+}
+
+/// Minimum coverage percentages that `deno test --coverage` must reach.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct CoverageThreshold {
+  pub lines: Option<f32>,
+  pub functions: Option<f32>,
+  pub branches: Option<f32>,
+}
+
+/// Time to live of cached remote modules, for one host or all of them, that
+/// overrides the caching headers of the responses with `--revalidate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheTtl {
+  pub host: Option<String>,
+  pub seconds: u64,
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Flags {
   /// Vector of CLI arguments - these are user script arguments, all Deno
@@ -104,30 +146,47 @@ pub struct Flags {
   pub allow_run: bool,
   pub allow_write: bool,
   pub cache_blocklist: Vec<String>,
+  pub cache_ttl: Vec<CacheTtl>,
   pub ca_file: Option<String>,
   pub cached_only: bool,
   pub config_path: Option<String>,
   pub coverage: bool,
+  pub coverage_dir: Option<String>,
+  pub coverage_threshold: Option<CoverageThreshold>,
+  pub env_allowlist: Vec<String>,
+  pub env_denylist: Vec<String>,
   pub ignore: Vec<String>,
   pub import_map_path: Option<String>,
   pub inspect: Option<SocketAddr>,
   pub inspect_brk: Option<SocketAddr>,
   pub lock: Option<String>,
+  pub lock_local: bool,
   pub lock_write: bool,
   pub log_level: Option<Level>,
   pub net_allowlist: Vec<String>,
+  pub net_denylist: Vec<String>,
   pub no_check: bool,
   pub no_prompts: bool,
   pub no_remote: bool,
+  pub permission_audit: Option<String>,
+  pub permission_audit_summary: bool,
+  pub permission_policy_path: Option<String>,
   pub read_allowlist: Vec<PathBuf>,
+  pub read_denylist: Vec<PathBuf>,
   pub reload: bool,
   pub repl: bool,
+  pub revalidate: bool,
+  pub run_allowlist: Vec<String>,
+  pub run_denylist: Vec<String>,
+  pub save_permissions: Option<String>,
   pub seed: Option<u64>,
+  pub trace_ops: Option<String>,
   pub unstable: bool,
   pub v8_flags: Option<Vec<String>>,
   pub version: bool,
   pub watch: bool,
   pub write_allowlist: Vec<PathBuf>,
+  pub write_denylist: Vec<PathBuf>,
 }
 
 fn join_paths(allowlist: &[PathBuf], d: &str) -> String {
@@ -172,10 +231,20 @@ impl Flags {
       args.push("--allow-net".to_string());
     }
 
+    if !self.env_allowlist.is_empty() {
+      let s = format!("--allow-env={}", self.env_allowlist.join(","));
+      args.push(s);
+    }
+
     if self.allow_env {
       args.push("--allow-env".to_string());
     }
 
+    if !self.run_allowlist.is_empty() {
+      let s = format!("--allow-run={}", self.run_allowlist.join(","));
+      args.push(s);
+    }
+
     if self.allow_run {
       args.push("--allow-run".to_string());
     }
@@ -188,11 +257,40 @@ impl Flags {
       args.push("--allow-hrtime".to_string());
     }
 
+    if !self.read_denylist.is_empty() {
+      let s = format!("--deny-read={}", join_paths(&self.read_denylist, ","));
+      args.push(s);
+    }
+
+    if !self.write_denylist.is_empty() {
+      let s = format!("--deny-write={}", join_paths(&self.write_denylist, ","));
+      args.push(s);
+    }
+
+    if !self.net_denylist.is_empty() {
+      args.push(format!("--deny-net={}", self.net_denylist.join(",")));
+    }
+
+    if !self.env_denylist.is_empty() {
+      args.push(format!("--deny-env={}", self.env_denylist.join(",")));
+    }
+
+    if !self.run_denylist.is_empty() {
+      args.push(format!("--deny-run={}", self.run_denylist.join(",")));
+    }
+
+    if let Some(policy_path) = &self.permission_policy_path {
+      args.push(format!("--permissions={}", policy_path));
+    }
+
     args
   }
 }
 
 static ENV_VARIABLES_HELP: &str = "ENVIRONMENT VARIABLES:
+    DENO_AUTH_TOKENS     Credentials for hosts that serve private modules
+                         (token@host or username:password@host, separated
+                         by semicolons)
     DENO_DIR             Set the cache directory
     DENO_INSTALL_ROOT    Set deno install's output directory
                          (defaults to $HOME/.deno/bin)
@@ -280,6 +378,10 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
     install_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("completions") {
     completions_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("coverage") {
+    coverage_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("dir") {
+    dir_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("test") {
     test_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("upgrade") {
@@ -288,6 +390,8 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
     doc_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("lint") {
     lint_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("vendor") {
+    vendor_parse(&mut flags, m);
   } else {
     repl_parse(&mut flags, &matches);
   }
@@ -338,6 +442,8 @@ If the flag is set, restrict these messages to errors.",
     .subcommand(bundle_subcommand())
     .subcommand(cache_subcommand())
     .subcommand(completions_subcommand())
+    .subcommand(coverage_subcommand())
+    .subcommand(dir_subcommand())
     .subcommand(doc_subcommand())
     .subcommand(eval_subcommand())
     .subcommand(fmt_subcommand())
@@ -349,6 +455,7 @@ If the flag is set, restrict these messages to errors.",
     .subcommand(test_subcommand())
     .subcommand(types_subcommand())
     .subcommand(upgrade_subcommand())
+    .subcommand(vendor_subcommand())
     .long_about(DENO_HELP)
     .after_help(ENV_VARIABLES_HELP)
 }
@@ -414,6 +521,7 @@ fn bundle_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   no_remote_arg_parse(flags, matches);
   config_arg_parse(flags, matches);
   reload_arg_parse(flags, matches);
+  revalidate_args_parse(flags, matches);
   lock_args_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
 
@@ -432,6 +540,50 @@ fn bundle_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn vendor_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  importmap_arg_parse(flags, matches);
+  reload_arg_parse(flags, matches);
+  revalidate_args_parse(flags, matches);
+  lock_args_parse(flags, matches);
+  ca_file_arg_parse(flags, matches);
+
+  let files = matches
+    .values_of("file")
+    .unwrap()
+    .map(String::from)
+    .collect();
+  let output = PathBuf::from(matches.value_of("output").unwrap());
+  let force = matches.is_present("force");
+  flags.subcommand = DenoSubcommand::Vendor {
+    files,
+    output,
+    force,
+  };
+}
+
+fn dir_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let command = match matches.subcommand() {
+    ("list", Some(m)) => DirCommand::List {
+      json: m.is_present("json"),
+    },
+    ("headers", Some(m)) => DirCommand::Headers {
+      url: m.value_of("url").unwrap().to_string(),
+    },
+    ("prune", Some(m)) => {
+      importmap_arg_parse(flags, m);
+      DirCommand::Prune {
+        roots: m.values_of("root").unwrap().map(String::from).collect(),
+        dry_run: m.is_present("dry-run"),
+      }
+    }
+    ("clear-emit", Some(m)) => DirCommand::ClearEmit {
+      files: m.values_of("file").unwrap().map(String::from).collect(),
+    },
+    _ => unreachable!(),
+  };
+  flags.subcommand = DenoSubcommand::Dir { command };
+}
+
 fn completions_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   let shell: &str = matches.value_of("shell").unwrap();
   let mut buf: Vec<u8> = vec![];
@@ -447,6 +599,27 @@ fn completions_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn coverage_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let dir = PathBuf::from(matches.value_of("dir").unwrap());
+  let include = match matches.values_of("include") {
+    Some(f) => f.map(String::from).collect(),
+    None => vec![],
+  };
+  let exclude = match matches.values_of("exclude") {
+    Some(f) => f.map(String::from).collect(),
+    None => vec![],
+  };
+  let lcov = matches.is_present("lcov");
+  let html = matches.value_of("html").map(PathBuf::from);
+  flags.subcommand = DenoSubcommand::Coverage {
+    dir,
+    include,
+    exclude,
+    lcov,
+    html,
+  };
+}
+
 fn repl_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   runtime_args_parse(flags, matches, false);
   flags.repl = true;
@@ -508,6 +681,9 @@ fn lock_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   if matches.is_present("lock-write") {
     flags.lock_write = true;
   }
+  if matches.is_present("lock-local") {
+    flags.lock_local = true;
+  }
 }
 
 fn compile_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
@@ -517,8 +693,11 @@ fn compile_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     .arg(config_arg())
     .arg(no_check_arg())
     .arg(reload_arg())
+    .arg(revalidate_arg())
+    .arg(cache_ttl_arg())
     .arg(lock_arg())
     .arg(lock_write_arg())
+    .arg(lock_local_arg())
     .arg(ca_file_arg())
 }
 
@@ -528,6 +707,7 @@ fn compile_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   config_arg_parse(flags, matches);
   no_check_arg_parse(flags, matches);
   reload_arg_parse(flags, matches);
+  revalidate_args_parse(flags, matches);
   lock_args_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
 }
@@ -543,6 +723,7 @@ fn runtime_args<'a, 'b>(app: App<'a, 'b>, include_perms: bool) -> App<'a, 'b> {
     .arg(cached_only_arg())
     .arg(v8_flags_arg())
     .arg(seed_arg())
+    .arg(trace_ops_arg())
 }
 
 fn runtime_args_parse(
@@ -557,6 +738,7 @@ fn runtime_args_parse(
   }
   v8_flags_arg_parse(flags, matches);
   seed_arg_parse(flags, matches);
+  trace_ops_arg_parse(flags, matches);
   inspect_arg_parse(flags, matches);
 }
 
@@ -587,10 +769,21 @@ fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   let allow_none = matches.is_present("allow_none");
   let quiet = matches.is_present("quiet");
   let filter = matches.value_of("filter").map(String::from);
+  let reporter = matches.value_of("reporter").unwrap().to_string();
+  let jobs = matches
+    .value_of("jobs")
+    .map(|jobs| jobs.parse::<usize>().unwrap())
+    .unwrap_or(1);
   let coverage = matches.is_present("coverage");
 
   if coverage {
     flags.coverage = true;
+    flags.coverage_dir = matches.value_of("coverage").map(String::from);
+  }
+
+  if let Some(values) = matches.values_of("coverage-threshold") {
+    let values: Vec<String> = values.map(String::from).collect();
+    flags.coverage_threshold = Some(parse_coverage_threshold(&values).unwrap());
   }
 
   let include = if matches.is_present("files") {
@@ -611,7 +804,51 @@ fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     include,
     filter,
     allow_none,
+    reporter,
+    jobs,
+  };
+}
+
+/// Parses `--coverage-threshold` values. A bare percentage applies to every
+/// kind of coverage, `lines=N`, `functions=N` and `branches=N` set a single
+/// kind.
+fn parse_coverage_threshold(
+  values: &[String],
+) -> Result<CoverageThreshold, String> {
+  let parse_percent = |s: &str| match s.parse::<f32>() {
+    Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+    _ => Err(format!("Invalid coverage percentage: {}", s)),
   };
+
+  let mut threshold = CoverageThreshold::default();
+  for value in values {
+    let parts: Vec<&str> = value.splitn(2, '=').collect();
+    match parts.as_slice() {
+      ["lines", percent] => {
+        threshold.lines = Some(parse_percent(*percent)?);
+      }
+      ["functions", percent] => {
+        threshold.functions = Some(parse_percent(*percent)?);
+      }
+      ["branches", percent] => {
+        threshold.branches = Some(parse_percent(*percent)?);
+      }
+      [kind, _] => {
+        return Err(format!("Unknown coverage kind: {}", kind));
+      }
+      _ => {
+        let percent = parse_percent(value.as_str())?;
+        threshold.lines = Some(percent);
+        threshold.functions = Some(percent);
+        threshold.branches = Some(percent);
+      }
+    }
+  }
+  Ok(threshold)
+}
+
+fn coverage_threshold_validator(value: String) -> Result<(), String> {
+  parse_coverage_threshold(&[value]).map(|_| ())
 }
 
 fn upgrade_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
@@ -794,8 +1031,11 @@ fn bundle_subcommand<'a, 'b>() -> App<'a, 'b> {
     .arg(no_remote_arg())
     .arg(config_arg())
     .arg(reload_arg())
+    .arg(revalidate_arg())
+    .arg(cache_ttl_arg())
     .arg(lock_arg())
     .arg(lock_write_arg())
+    .arg(lock_local_arg())
     .arg(ca_file_arg())
     .arg(
       Arg::with_name("source_file")
@@ -829,6 +1069,181 @@ fn completions_subcommand<'a, 'b>() -> App<'a, 'b> {
     )
 }
 
+fn coverage_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("coverage")
+    .about("Print coverage reports")
+    .long_about(
+      "Print coverage reports from coverage profiles.
+
+Collect a coverage profile with deno test:
+  deno test --unstable --coverage=cov_profile
+
+Print a report to stdout:
+  deno coverage --unstable cov_profile
+
+Profiles saved by several runs into the same directory, for example by test
+shards, are merged.
+
+Include URLs that match the regular expression and don't match the exclude
+expression:
+  deno coverage --unstable --include=\"^file:\" --exclude=\"test\\.(ts|js)\" cov_profile
+
+Write a report using the lcov format:
+  deno coverage --unstable --lcov cov_profile > cov.lcov
+
+Write an HTML report into a directory:
+  deno coverage --unstable --html=cov_html cov_profile",
+    )
+    .arg(
+      Arg::with_name("include")
+        .long("include")
+        .takes_value(true)
+        .value_name("regex")
+        .multiple(true)
+        .number_of_values(1)
+        .require_equals(true)
+        .default_value(r"^file:")
+        .help("Include source files in the report"),
+    )
+    .arg(
+      Arg::with_name("exclude")
+        .long("exclude")
+        .takes_value(true)
+        .value_name("regex")
+        .multiple(true)
+        .number_of_values(1)
+        .require_equals(true)
+        .default_value(r"test\.(js|mjs|ts|jsx|tsx)$")
+        .help("Exclude source files from the report"),
+    )
+    .arg(
+      Arg::with_name("lcov")
+        .long("lcov")
+        .help("Output coverage report in lcov format")
+        .takes_value(false)
+        .conflicts_with("html"),
+    )
+    .arg(
+      Arg::with_name("html")
+        .long("html")
+        .value_name("DIR")
+        .help("Write an HTML coverage report into the given directory")
+        .takes_value(true)
+        .require_equals(true),
+    )
+    .arg(
+      Arg::with_name("dir")
+        .takes_value(true)
+        .required(true)
+        .help("The directory of the coverage profiles"),
+    )
+}
+
+fn dir_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("dir")
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+    .subcommand(
+      SubCommand::with_name("list")
+        .arg(
+          Arg::with_name("json")
+            .long("json")
+            .help("Outputs the cached modules in JSON format"),
+        )
+        .about("List the cached remote modules"),
+    )
+    .subcommand(
+      SubCommand::with_name("headers")
+        .arg(Arg::with_name("url").takes_value(true).required(true))
+        .about("Show the cached response headers of a remote module"),
+    )
+    .subcommand(
+      SubCommand::with_name("prune")
+        .arg(importmap_arg())
+        .arg(
+          Arg::with_name("dry-run")
+            .long("dry-run")
+            .help("Print the entries that would be removed"),
+        )
+        .arg(
+          Arg::with_name("root")
+            .takes_value(true)
+            .required(true)
+            .min_values(1),
+        )
+        .about("Remove cached modules the given roots do not use"),
+    )
+    .subcommand(
+      SubCommand::with_name("clear-emit")
+        .arg(
+          Arg::with_name("file")
+            .takes_value(true)
+            .required(true)
+            .min_values(1),
+        )
+        .about("Remove the compiler output of the given modules"),
+    )
+    .about("Manage the cache in DENO_DIR")
+    .long_about(
+      "Manage the remote modules and the compiler output cached in DENO_DIR.
+
+List the cached remote modules with their size and fetch time:
+  deno dir list --unstable
+
+Show the response headers a remote module was cached with:
+  deno dir headers --unstable https://deno.land/std/http/server.ts
+
+Remove the remote modules, and their compiler output, that none of the given
+modules depend on:
+  deno dir prune --unstable main.ts worker.ts
+
+Remove the compiler output of a module, so it is type checked and emitted
+again on the next run:
+  deno dir clear-emit --unstable main.ts",
+    )
+}
+
+fn vendor_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("vendor")
+    .arg(importmap_arg())
+    .arg(reload_arg())
+    .arg(revalidate_arg())
+    .arg(cache_ttl_arg())
+    .arg(lock_arg())
+    .arg(lock_write_arg())
+    .arg(lock_local_arg())
+    .arg(ca_file_arg())
+    .arg(
+      Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .value_name("DIR")
+        .takes_value(true)
+        .default_value("vendor")
+        .help("The directory the remote modules are written to"),
+    )
+    .arg(
+      Arg::with_name("force")
+        .long("force")
+        .short("f")
+        .help("Replace the output directory if it is not empty"),
+    )
+    .arg(
+      Arg::with_name("file")
+        .takes_value(true)
+        .required(true)
+        .min_values(1),
+    )
+    .about("Vendor remote dependencies")
+    .long_about(
+      "Copy the remote dependencies of the given modules into a directory.
+
+Every remote module in the module graph is written to <output>/<host>/<path>,
+together with an import map that points the original URLs at the copies:
+  deno vendor --unstable main.ts
+  deno run --unstable --importmap=vendor/import_map.json main.ts",
+    )
+}
+
 fn eval_subcommand<'a, 'b>() -> App<'a, 'b> {
   runtime_args(SubCommand::with_name("eval"), false)
     .about("Eval script")
@@ -1104,11 +1519,19 @@ fn permission_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     .arg(
       Arg::with_name("allow-env")
         .long("allow-env")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
         .help("Allow environment access"),
     )
     .arg(
       Arg::with_name("allow-run")
         .long("allow-run")
+        .min_values(0)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
         .help("Allow running subprocesses"),
     )
     .arg(
@@ -1127,6 +1550,101 @@ fn permission_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         .long("allow-all")
         .help("Allow all permissions"),
     )
+    .arg(
+      Arg::with_name("deny-read")
+        .long("deny-read")
+        .min_values(1)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny file system read access, even if otherwise allowed"),
+    )
+    .arg(
+      Arg::with_name("deny-write")
+        .long("deny-write")
+        .min_values(1)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny file system write access, even if otherwise allowed"),
+    )
+    .arg(
+      Arg::with_name("deny-net")
+        .long("deny-net")
+        .min_values(1)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny network access, even if otherwise allowed")
+        .validator(crate::flags_allow_net::validator),
+    )
+    .arg(
+      Arg::with_name("deny-env")
+        .long("deny-env")
+        .min_values(1)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny environment access, even if otherwise allowed"),
+    )
+    .arg(
+      Arg::with_name("deny-run")
+        .long("deny-run")
+        .min_values(1)
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .help("Deny running subprocesses, even if otherwise allowed"),
+    )
+    .arg(
+      Arg::with_name("permissions")
+        .long("permissions")
+        .value_name("FILE")
+        .takes_value(true)
+        .require_equals(true)
+        .help("Load permissions from a JSON or TOML policy file")
+        .long_help(
+          "Load permissions from a JSON policy file, or a TOML one with a .toml
+extension. Flags may deny more than the policy, but not grant anything
+it doesn't.
+  {
+    \"allow\": { \"read\": [\"./data\"], \"net\": [\"deno.land\"] },
+    \"deny\": { \"read\": [\"./data/secrets\"] }
+  }",
+        ),
+    )
+    .arg(
+      Arg::with_name("save-permissions")
+        .long("save-permissions")
+        .value_name("FILE")
+        .takes_value(true)
+        .require_equals(true)
+        .help("Save the answers to permission prompts to a JSON policy file")
+        .long_help(
+          "Save the answers to permission prompts to a JSON policy file, which
+can be loaded with --permissions on the next run. An existing file is
+extended.",
+        ),
+    )
+    .arg(
+      Arg::with_name("permission-audit")
+        .long("permission-audit")
+        .value_name("FILE")
+        .takes_value(true)
+        .require_equals(true)
+        .help("Record every permission check to a JSON lines file")
+        .long_help(
+          "Record every permission check to a JSON lines file. Each line holds
+the permission, the path, host, variable or command checked, the result
+(granted, denied or prompt) and the location of the JavaScript code
+that triggered the check.",
+        ),
+    )
+    .arg(
+      Arg::with_name("permission-audit-summary")
+        .long("permission-audit-summary")
+        .help("Print the permission flags the program needed when it exits"),
+    )
 }
 
 fn run_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -1184,14 +1702,82 @@ fn test_subcommand<'a, 'b>() -> App<'a, 'b> {
         .takes_value(true)
         .help("Run tests with this string or pattern in the test name"),
     )
+    .arg(
+      Arg::with_name("reporter")
+        .long("reporter")
+        .takes_value(true)
+        .possible_values(&["pretty", "junit", "jsonl"])
+        .default_value("pretty")
+        .help("Select the format test results are reported in")
+        .long_help(
+          "Select the format test results are reported in.
+pretty
+  Human readable output (default)
+junit
+  JUnit XML, written once all tests have run
+jsonl
+  One JSON object per line for every test event, written as tests run",
+        ),
+    )
+    .arg(
+      Arg::with_name("jobs")
+        .short("j")
+        .long("jobs")
+        .value_name("N")
+        .takes_value(true)
+        // No `default_value`: clap would then treat `--jobs` as always
+        // present and reject `--docs` and `--coverage`.
+        .conflicts_with("docs")
+        .conflicts_with("coverage")
+        .validator(|val: String| match val.parse::<usize>() {
+          Ok(jobs) if jobs > 0 => Ok(()),
+          _ => Err("Jobs should be a positive number".to_string()),
+        })
+        .help("Run test modules in parallel on N threads [default: 1]")
+        .long_help(
+          "Run test modules in parallel on N threads, 1 by default.
+Every test module is evaluated in its own isolate with its own copy of the
+permissions. Results are reported in the order the modules were given.",
+        ),
+    )
     .arg(
       Arg::with_name("coverage")
         .long("coverage")
-        .takes_value(false)
+        .value_name("DIR")
+        .min_values(0)
+        .max_values(1)
+        .require_equals(true)
+        .takes_value(true)
         .requires("unstable")
         .conflicts_with("inspect")
         .conflicts_with("inspect-brk")
-        .help("Collect coverage information"),
+        .help("Collect coverage information")
+        .long_help(
+          "Collect coverage information.
+--coverage
+  Print a coverage report to standard output
+--coverage=cov/
+  Also write an LCOV tracefile (<uuid>.info) and the raw V8 coverage of
+  every module into the given directory",
+        ),
+    )
+    .arg(
+      Arg::with_name("coverage-threshold")
+        .long("coverage-threshold")
+        .value_name("PERCENT")
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .requires("coverage")
+        .validator(coverage_threshold_validator)
+        .help("Fail if coverage is below the given percentage")
+        .long_help(
+          "Fail if coverage is below the given percentage.
+--coverage-threshold=80
+  Require 80% line, function and branch coverage
+--coverage-threshold=lines=90,branches=75
+  Require 90% line coverage and 75% branch coverage",
+        ),
     )
     .arg(
       Arg::with_name("files")
@@ -1234,6 +1820,17 @@ fn lock_write_arg<'a, 'b>() -> Arg<'a, 'b> {
     .long("lock-write")
     .requires("lock")
     .help("Write lock file (use with --lock)")
+    .long_help(
+      "Write lock file (use with --lock).
+Entries of modules that are no longer part of the module graph are removed.",
+    )
+}
+
+fn lock_local_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("lock-local")
+    .long("lock-local")
+    .requires("lock")
+    .help("Also check local modules against the lock file (use with --lock)")
 }
 
 fn config_arg<'a, 'b>() -> Arg<'a, 'b> {
@@ -1353,22 +1950,78 @@ fn reload_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
   }
 }
 
-fn importmap_arg<'a, 'b>() -> Arg<'a, 'b> {
-  Arg::with_name("importmap")
-    .long("importmap")
-    .value_name("FILE")
-    .help("UNSTABLE: Load import map file")
+fn revalidate_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("revalidate")
+    .long("revalidate")
+    .help("Revalidate cached remote modules that are no longer fresh")
     .long_help(
-      "UNSTABLE:
-Load import map file
-Docs: https://deno.land/manual/linking_to_external_code/import_maps
-Specification: https://wicg.github.io/import-maps/
-Examples: https://github.com/WICG/import-maps#the-import-map",
+      "Revalidate cached remote modules that are no longer fresh.
+How long a module is fresh for is determined by the Cache-Control (max-age,
+no-cache) and Expires headers it was served with. Stale modules are fetched
+again with a conditional request, so unchanged modules are not downloaded
+again. Modules served without these headers are always fresh, unless
+--cache-ttl applies to them.",
     )
+}
+
+fn cache_ttl_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("cache-ttl")
+    .long("cache-ttl")
     .takes_value(true)
+    .use_delimiter(true)
+    .require_equals(true)
+    .requires("revalidate")
+    .value_name("[HOST=]SECONDS")
+    .validator(|value| parse_cache_ttl(&value).map(|_| ()))
+    .help("Override how long cached remote modules are fresh for")
+    .long_help(
+      "Override how long cached remote modules are fresh for, in seconds, for
+all hosts or a single one (use with --revalidate). Takes precedence over the
+caching headers of the responses.
+--cache-ttl=3600
+  Revalidate all modules fetched more than an hour ago
+--cache-ttl=deno.land=86400,localhost:4545=0
+  Revalidate modules from deno.land once a day and from localhost:4545 always",
+    )
 }
 
-fn importmap_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+fn parse_cache_ttl(value: &str) -> Result<CacheTtl, String> {
+  let (host, seconds) = match value.rfind('=') {
+    Some(index) => (Some(value[..index].to_string()), &value[index + 1..]),
+    None => (None, value),
+  };
+  if host.as_deref() == Some("") {
+    return Err(format!("Missing host in cache TTL: {}", value));
+  }
+  let seconds = seconds.parse::<u64>().map_err(|_| {
+    format!("Invalid number of seconds in cache TTL: {}", value)
+  })?;
+  Ok(CacheTtl { host, seconds })
+}
+
+fn revalidate_args_parse(flags: &mut Flags, matches: &ArgMatches) {
+  flags.revalidate = matches.is_present("revalidate");
+  if let Some(values) = matches.values_of("cache-ttl") {
+    flags.cache_ttl = values.map(|v| parse_cache_ttl(v).unwrap()).collect();
+  }
+}
+
+fn importmap_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("importmap")
+    .long("importmap")
+    .value_name("FILE")
+    .help("UNSTABLE: Load import map file")
+    .long_help(
+      "UNSTABLE:
+Load import map file
+Docs: https://deno.land/manual/linking_to_external_code/import_maps
+Specification: https://wicg.github.io/import-maps/
+Examples: https://github.com/WICG/import-maps#the-import-map",
+    )
+    .takes_value(true)
+}
+
+fn importmap_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   flags.import_map_path = matches.value_of("importmap").map(ToOwned::to_owned);
 }
 
@@ -1432,6 +2085,23 @@ fn seed_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
   }
 }
 
+fn trace_ops_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("trace-ops")
+    .long("trace-ops")
+    .value_name("FILE")
+    .takes_value(true)
+    .require_equals(true)
+    .help("Write a trace of the op calls to a JSON file")
+    .long_help(
+      "Write a trace event for every op call to a JSON file, in the Chrome
+trace event format. Open it in chrome://tracing or https://ui.perfetto.dev.",
+    )
+}
+
+fn trace_ops_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
+  flags.trace_ops = matches.value_of("trace-ops").map(ToOwned::to_owned);
+}
+
 fn cached_only_arg<'a, 'b>() -> Arg<'a, 'b> {
   Arg::with_name("cached-only")
     .long("cached-only")
@@ -1501,11 +2171,23 @@ fn permission_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     }
   }
 
-  if matches.is_present("allow-env") {
-    flags.allow_env = true;
+  if let Some(env_wl) = matches.values_of("allow-env") {
+    let env_allowlist: Vec<String> = env_wl.map(String::from).collect();
+    if env_allowlist.is_empty() {
+      flags.allow_env = true;
+    } else {
+      flags.env_allowlist = env_allowlist;
+      debug!("env allowlist: {:#?}", &flags.env_allowlist);
+    }
   }
-  if matches.is_present("allow-run") {
-    flags.allow_run = true;
+  if let Some(run_wl) = matches.values_of("allow-run") {
+    let run_allowlist: Vec<String> = run_wl.map(String::from).collect();
+    if run_allowlist.is_empty() {
+      flags.allow_run = true;
+    } else {
+      flags.run_allowlist = run_allowlist;
+      debug!("run allowlist: {:#?}", &flags.run_allowlist);
+    }
   }
   if matches.is_present("allow-plugin") {
     flags.allow_plugin = true;
@@ -1513,6 +2195,32 @@ fn permission_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   if matches.is_present("allow-hrtime") {
     flags.allow_hrtime = true;
   }
+  if let Some(read_dl) = matches.values_of("deny-read") {
+    flags.read_denylist = read_dl.map(PathBuf::from).collect();
+  }
+  if let Some(write_dl) = matches.values_of("deny-write") {
+    flags.write_denylist = write_dl.map(PathBuf::from).collect();
+  }
+  if let Some(net_dl) = matches.values_of("deny-net") {
+    let raw_net_denylist: Vec<String> = net_dl.map(String::from).collect();
+    flags.net_denylist =
+      crate::flags_allow_net::parse(raw_net_denylist).unwrap();
+    debug!("net denylist: {:#?}", &flags.net_denylist);
+  }
+  if let Some(env_dl) = matches.values_of("deny-env") {
+    flags.env_denylist = env_dl.map(String::from).collect();
+  }
+  if let Some(run_dl) = matches.values_of("deny-run") {
+    flags.run_denylist = run_dl.map(String::from).collect();
+  }
+  flags.permission_policy_path =
+    matches.value_of("permissions").map(ToOwned::to_owned);
+  flags.save_permissions =
+    matches.value_of("save-permissions").map(ToOwned::to_owned);
+  flags.permission_audit =
+    matches.value_of("permission-audit").map(ToOwned::to_owned);
+  flags.permission_audit_summary =
+    matches.is_present("permission-audit-summary");
   if matches.is_present("allow-all") {
     flags.allow_read = true;
     flags.allow_env = true;
@@ -1699,6 +2407,26 @@ mod tests {
     );
   }
 
+  #[test]
+  fn run_trace_ops() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--trace-ops=trace.json",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        trace_ops: Some("trace.json".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn script_args() {
     let r = flags_from_vec_safe(svec![
@@ -2271,6 +2999,307 @@ mod tests {
     );
   }
 
+  #[test]
+  fn allow_run_allowlist() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-run=git,/usr/local/bin/deno",
+      "script.ts"
+    ]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_run: false,
+        run_allowlist: svec!["git", "/usr/local/bin/deno"],
+        ..Flags::default()
+      }
+    );
+    assert_eq!(
+      flags.to_permission_args(),
+      svec!["--allow-run=git,/usr/local/bin/deno"]
+    );
+  }
+
+  #[test]
+  fn allow_env_allowlist() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-env=HOME,PATH",
+      "script.ts"
+    ]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_env: false,
+        env_allowlist: svec!["HOME", "PATH"],
+        ..Flags::default()
+      }
+    );
+    assert_eq!(flags.to_permission_args(), svec!["--allow-env=HOME,PATH"]);
+  }
+
+  #[test]
+  fn deny_flags() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-read",
+      "--allow-net",
+      "--deny-read=/etc/secrets,.env",
+      "--deny-write=.git",
+      "--deny-net=169.254.169.254,:9229",
+      "--deny-env=AWS_SECRET_ACCESS_KEY",
+      "--deny-run=curl",
+      "script.ts"
+    ]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_read: true,
+        allow_net: true,
+        read_denylist: vec![
+          PathBuf::from("/etc/secrets"),
+          PathBuf::from(".env")
+        ],
+        write_denylist: vec![PathBuf::from(".git")],
+        net_denylist: svec![
+          "169.254.169.254",
+          "0.0.0.0:9229",
+          "127.0.0.1:9229",
+          "localhost:9229"
+        ],
+        env_denylist: svec!["AWS_SECRET_ACCESS_KEY"],
+        run_denylist: svec!["curl"],
+        ..Flags::default()
+      }
+    );
+    assert_eq!(
+      flags.to_permission_args(),
+      svec![
+        "--allow-read",
+        "--allow-net",
+        "--deny-read=/etc/secrets,.env",
+        "--deny-write=.git",
+        "--deny-net=169.254.169.254,0.0.0.0:9229,127.0.0.1:9229,localhost:9229",
+        "--deny-env=AWS_SECRET_ACCESS_KEY",
+        "--deny-run=curl"
+      ]
+    );
+
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--deny-read", "script.ts"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn permission_policy() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--permissions=policy.json",
+      "--allow-hrtime",
+      "script.ts"
+    ]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        allow_hrtime: true,
+        permission_policy_path: Some("policy.json".to_string()),
+        ..Flags::default()
+      }
+    );
+    assert_eq!(
+      flags.to_permission_args(),
+      svec!["--allow-hrtime", "--permissions=policy.json"]
+    );
+  }
+
+  #[test]
+  fn save_permissions() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--permissions=policy.json",
+      "--save-permissions=policy.json",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        permission_policy_path: Some("policy.json".to_string()),
+        save_permissions: Some("policy.json".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn permission_audit() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--permission-audit=audit.jsonl",
+      "--permission-audit-summary",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        permission_audit: Some("audit.jsonl".to_string()),
+        permission_audit_summary: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn dir_list() {
+    let r = flags_from_vec_safe(svec!["deno", "dir", "list", "--json"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Dir {
+          command: DirCommand::List { json: true },
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn dir_headers() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "dir",
+      "headers",
+      "https://deno.land/std/http/server.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Dir {
+          command: DirCommand::Headers {
+            url: "https://deno.land/std/http/server.ts".to_string(),
+          },
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn dir_prune() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "dir",
+      "prune",
+      "--importmap=import_map.json",
+      "--dry-run",
+      "main.ts",
+      "worker.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Dir {
+          command: DirCommand::Prune {
+            roots: svec!["main.ts", "worker.ts"],
+            dry_run: true,
+          },
+        },
+        import_map_path: Some("import_map.json".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn dir_clear_emit() {
+    let r = flags_from_vec_safe(svec!["deno", "dir", "clear-emit", "main.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Dir {
+          command: DirCommand::ClearEmit {
+            files: svec!["main.ts"],
+          },
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "dir"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn vendor() {
+    let r = flags_from_vec_safe(svec!["deno", "vendor", "main.ts", "deps.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Vendor {
+          files: svec!["main.ts", "deps.ts"],
+          output: PathBuf::from("vendor"),
+          force: false,
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn vendor_with_options() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "vendor",
+      "--importmap=import_map.json",
+      "--reload",
+      "--lock=lock.json",
+      "--output=third_party",
+      "--force",
+      "main.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Vendor {
+          files: svec!["main.ts"],
+          output: PathBuf::from("third_party"),
+          force: true,
+        },
+        import_map_path: Some("import_map.json".to_string()),
+        reload: true,
+        lock: Some("lock.json".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn bundle() {
     let r = flags_from_vec_safe(svec!["deno", "bundle", "source.ts"]);
@@ -2352,6 +3381,32 @@ mod tests {
     );
   }
 
+  #[test]
+  fn lock_local() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "cache",
+      "--lock=lock.json",
+      "--lock-local",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Cache {
+          files: svec!["script.ts"],
+        },
+        lock_local: true,
+        lock: Some("lock.json".to_string()),
+        ..Flags::default()
+      }
+    );
+
+    let r =
+      flags_from_vec_safe(svec!["deno", "cache", "--lock-local", "script.ts"]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn bundle_with_reload() {
     let r =
@@ -2769,6 +3824,57 @@ mod tests {
     );
   }
 
+  #[test]
+  fn revalidate_with_cache_ttl() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--revalidate",
+      "--cache-ttl=3600,deno.land=86400,localhost:4545=0",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        revalidate: true,
+        cache_ttl: vec![
+          CacheTtl {
+            host: None,
+            seconds: 3600,
+          },
+          CacheTtl {
+            host: Some("deno.land".to_string()),
+            seconds: 86400,
+          },
+          CacheTtl {
+            host: Some("localhost:4545".to_string()),
+            seconds: 0,
+          },
+        ],
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--cache-ttl=3600",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--revalidate",
+      "--cache-ttl=deno.land=1h",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn allow_net_allowlist_with_ports() {
     let r = flags_from_vec_safe(svec![
@@ -2870,6 +3976,8 @@ mod tests {
           allow_none: true,
           quiet: false,
           include: Some(svec!["dir1/", "dir2/"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
         },
         allow_net: true,
         ..Flags::default()
@@ -2877,6 +3985,61 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_reporter() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--reporter", "junit"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          docs: false,
+          fail_fast: false,
+          filter: None,
+          allow_none: false,
+          quiet: false,
+          include: None,
+          reporter: "junit".to_string(),
+          jobs: 1,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "--reporter", "tap"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn test_jobs() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--jobs", "4", "dir1"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          docs: false,
+          fail_fast: false,
+          filter: None,
+          allow_none: false,
+          quiet: false,
+          include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 4,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "-j", "0"]);
+    assert!(r.is_err());
+    let r = flags_from_vec_safe(svec!["deno", "test", "--jobs", "many"]);
+    assert!(r.is_err());
+    let r = flags_from_vec_safe(svec!["deno", "test", "--jobs", "4", "--docs"]);
+    assert!(r.is_err());
+    let r =
+      flags_from_vec_safe(svec!["deno", "test", "--jobs", "4", "--coverage"]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn test_filter() {
     let r = flags_from_vec_safe(svec!["deno", "test", "--filter=foo", "dir1"]);
@@ -2890,6 +4053,8 @@ mod tests {
           quiet: false,
           filter: Some("foo".to_string()),
           include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
         },
         ..Flags::default()
       }
@@ -2908,6 +4073,8 @@ mod tests {
           quiet: false,
           filter: None,
           include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
         },
         ..Flags::default()
       }
@@ -2928,6 +4095,8 @@ mod tests {
           quiet: false,
           filter: Some("- foo".to_string()),
           include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
         },
         ..Flags::default()
       }
@@ -2953,8 +4122,168 @@ mod tests {
           quiet: false,
           filter: None,
           include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
+        },
+        coverage: true,
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn coverage() {
+    let r = flags_from_vec_safe(svec!["deno", "coverage", "--unstable", "foo"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          dir: PathBuf::from("foo"),
+          include: svec![r"^file:"],
+          exclude: svec![r"test\.(js|mjs|ts|jsx|tsx)$"],
+          lcov: false,
+          html: None,
+        },
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn coverage_with_options() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "coverage",
+      "--unstable",
+      "--include=^https:",
+      "--exclude=_bench\\.ts$",
+      "--lcov",
+      "foo"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          dir: PathBuf::from("foo"),
+          include: svec!["^https:"],
+          exclude: svec!["_bench\\.ts$"],
+          lcov: true,
+          html: None,
+        },
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "coverage",
+      "--unstable",
+      "--html=cov_html",
+      "foo"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          dir: PathBuf::from("foo"),
+          include: svec![r"^file:"],
+          exclude: svec![r"test\.(js|mjs|ts|jsx|tsx)$"],
+          lcov: false,
+          html: Some(PathBuf::from("cov_html")),
+        },
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_coverage_threshold() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--coverage",
+      "--coverage-threshold=lines=90,branches=75",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          docs: false,
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
+        },
+        coverage: true,
+        coverage_threshold: Some(CoverageThreshold {
+          lines: Some(90.0),
+          functions: None,
+          branches: Some(75.0),
+        }),
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--coverage",
+      "--coverage-threshold=80"
+    ]);
+    assert_eq!(
+      r.unwrap().coverage_threshold,
+      Some(CoverageThreshold {
+        lines: Some(80.0),
+        functions: Some(80.0),
+        branches: Some(80.0),
+      })
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--coverage",
+      "--coverage-threshold=statements=80"
+    ]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn test_coverage_dir() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--coverage=cov",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          docs: false,
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          reporter: "pretty".to_string(),
+          jobs: 1,
         },
         coverage: true,
+        coverage_dir: Some("cov".to_string()),
         unstable: true,
         ..Flags::default()
       }
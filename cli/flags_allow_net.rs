@@ -2,6 +2,7 @@
 
 use deno_core::url::Url;
 use std::net::IpAddr;
+use std::net::Ipv4Addr;
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -26,9 +27,108 @@ impl FromStr for BarePort {
   }
 }
 
-pub fn validator(host_and_port: String) -> Result<(), String> {
-  if Url::parse(&format!("deno://{}", host_and_port)).is_ok()
+/// A range of IP addresses in CIDR notation, like "10.0.0.0/8" or
+/// "fd00::/8".
+#[derive(Debug, PartialEq, Eq)]
+pub struct IpRange {
+  addr: IpAddr,
+  prefix_len: u8,
+}
+
+impl FromStr for IpRange {
+  type Err = String;
+  fn from_str(s: &str) -> Result<IpRange, String> {
+    let invalid = || format!("Bad IP address range: {}", s);
+    let index = s.find('/').ok_or_else(invalid)?;
+    let addr = parse_ip(&s[..index]).ok_or_else(invalid)?;
+    let prefix_len = s[index + 1..].parse::<u8>().map_err(|_| invalid())?;
+    let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix_len > max_prefix_len {
+      return Err(invalid());
+    }
+    Ok(IpRange { addr, prefix_len })
+  }
+}
+
+impl IpRange {
+  pub fn contains(&self, ip: &IpAddr) -> bool {
+    match (self.addr, ipv4_mapped(ip)) {
+      (IpAddr::V4(range), IpAddr::V4(ip)) => {
+        let mask = u32::MAX
+          .checked_shl(32 - self.prefix_len as u32)
+          .unwrap_or(0);
+        u32::from(range) & mask == u32::from(ip) & mask
+      }
+      (IpAddr::V6(range), IpAddr::V6(ip)) => {
+        let mask = u128::MAX
+          .checked_shl(128 - self.prefix_len as u32)
+          .unwrap_or(0);
+        u128::from(range) & mask == u128::from(ip) & mask
+      }
+      _ => false,
+    }
+  }
+}
+
+/// Parses an IP address, which may be an IPv6 address in brackets as found in
+/// URLs.
+pub fn parse_ip(s: &str) -> Option<IpAddr> {
+  let s = if s.starts_with('[') && s.ends_with(']') {
+    &s[1..s.len() - 1]
+  } else {
+    s
+  };
+  s.parse::<IpAddr>().ok()
+}
+
+/// Returns the IPv4 address of an IPv4-mapped IPv6 address ("::ffff:a.b.c.d"),
+/// so that IPv4 rules also apply to it.
+pub fn ipv4_mapped(ip: &IpAddr) -> IpAddr {
+  if let IpAddr::V6(ip) = ip {
+    if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = ip.segments() {
+      return IpAddr::V4(Ipv4Addr::from((hi as u32) << 16 | lo as u32));
+    }
+  }
+  *ip
+}
+
+/// Parses an entry with an IP address range and an optional port, like
+/// "10.0.0.0/8" or "10.0.0.0/8:443".
+pub fn parse_ip_range_entry(entry: &str) -> Option<(IpRange, Option<u16>)> {
+  if let Ok(range) = entry.parse::<IpRange>() {
+    return Some((range, None));
+  }
+  let index = entry.rfind(':')?;
+  let port = entry[index + 1..].parse::<u16>().ok()?;
+  let range = entry[..index].parse::<IpRange>().ok()?;
+  Some((range, Some(port)))
+}
+
+/// Returns whether `host_and_port` is a valid entry with a pattern: an IP
+/// address range or a wildcard subdomain like "*.example.com", optionally
+/// followed by a port.
+fn is_net_pattern(host_and_port: &str) -> bool {
+  if host_and_port.contains('/') {
+    return parse_ip_range_entry(host_and_port).is_some();
+  }
+  match host_and_port.strip_prefix("*.") {
+    Some(domain) => {
+      !domain.contains('*') && Url::parse(&format!("deno://{}", domain)).is_ok()
+    }
+    None => false,
+  }
+}
+
+fn is_host_and_port(host_and_port: &str) -> bool {
+  if host_and_port.contains('/') || host_and_port.contains('*') {
+    return is_net_pattern(host_and_port);
+  }
+  Url::parse(&format!("deno://{}", host_and_port)).is_ok()
     || host_and_port.parse::<IpAddr>().is_ok()
+}
+
+pub fn validator(host_and_port: String) -> Result<(), String> {
+  if is_host_and_port(&host_and_port)
     || host_and_port.parse::<BarePort>().is_ok()
   {
     Ok(())
@@ -43,9 +143,7 @@ pub fn validator(host_and_port: String) -> Result<(), String> {
 pub fn parse(paths: Vec<String>) -> clap::Result<Vec<String>> {
   let mut out: Vec<String> = vec![];
   for host_and_port in paths.iter() {
-    if Url::parse(&format!("deno://{}", host_and_port)).is_ok()
-      || host_and_port.parse::<IpAddr>().is_ok()
-    {
+    if is_host_and_port(host_and_port) {
       out.push(host_and_port.to_owned())
     } else if let Ok(port) = host_and_port.parse::<BarePort>() {
       // we got bare port, let's add default hosts
@@ -198,4 +296,55 @@ mod tests {
     let entries = svec!["[::q]:8080"];
     assert!(parse(entries).is_err());
   }
+
+  #[test]
+  fn parse_net_args_patterns() {
+    let entries = svec![
+      "10.0.0.0/8",
+      "10.0.0.0/8:443",
+      "fd00::/8",
+      "[fd00::]/8:443",
+      "*.example.com",
+      "*.example.com:8080"
+    ];
+    let expected = entries.clone();
+    let actual = parse(entries).unwrap();
+    assert_eq!(actual, expected);
+  }
+
+  #[test]
+  fn parse_net_args_patterns_error() {
+    assert!(parse(svec!["10.0.0.0/33"]).is_err());
+    assert!(parse(svec!["10.0.0.0/8/8"]).is_err());
+    assert!(parse(svec!["deno.land/8"]).is_err());
+    assert!(parse(svec!["*.*.example.com"]).is_err());
+    assert!(parse(svec!["foo.*.example.com"]).is_err());
+  }
+}
+
+#[cfg(test)]
+mod ip_range_tests {
+  use super::IpRange;
+  use std::net::IpAddr;
+
+  fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn ip_range_contains() {
+    let range = "10.0.0.0/8".parse::<IpRange>().unwrap();
+    assert!(range.contains(&ip("10.1.2.3")));
+    assert!(range.contains(&ip("::ffff:10.1.2.3")));
+    assert!(!range.contains(&ip("11.0.0.1")));
+    assert!(!range.contains(&ip("::1")));
+    let range = "169.254.169.254/32".parse::<IpRange>().unwrap();
+    assert!(range.contains(&ip("169.254.169.254")));
+    assert!(!range.contains(&ip("169.254.169.253")));
+    let range = "0.0.0.0/0".parse::<IpRange>().unwrap();
+    assert!(range.contains(&ip("1.2.3.4")));
+    let range = "fd00::/8".parse::<IpRange>().unwrap();
+    assert!(range.contains(&ip("fd12:3456::1")));
+    assert!(!range.contains(&ip("fe80::1")));
+  }
 }
@@ -58,6 +58,46 @@ pub fn resolve_from_cwd(path: &Path) -> Result<PathBuf, AnyError> {
   Ok(normalize_path(&resolved_path))
 }
 
+/// Resolves the symlinks of an absolute path, which may not exist: the longest
+/// ancestor of the path that exists is canonicalized, and the remaining
+/// components are appended to it.
+pub fn canonicalize_path(path: &Path) -> PathBuf {
+  let mut existing = path;
+  let mut rest = Vec::new();
+  loop {
+    if let Ok(canonical) = existing.canonicalize() {
+      let canonical = strip_verbatim_prefix(canonical);
+      return rest
+        .iter()
+        .rev()
+        .fold(canonical, |path, name| path.join(name));
+    }
+    match (existing.parent(), existing.file_name()) {
+      (Some(parent), Some(name)) => {
+        rest.push(name);
+        existing = parent;
+      }
+      _ => return path.to_path_buf(),
+    }
+  }
+}
+
+/// `canonicalize` returns `\\?\C:\...` paths on Windows, which no other path
+/// starts with.
+#[cfg(windows)]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+  let path_str = path.to_string_lossy();
+  match path_str.strip_prefix(r"\\?\") {
+    Some(rest) if rest.chars().nth(1) == Some(':') => PathBuf::from(rest),
+    _ => path,
+  }
+}
+
+#[cfg(not(windows))]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+  path
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -97,6 +137,20 @@ mod tests {
     }
   }
 
+  #[cfg(unix)]
+  #[test]
+  fn canonicalize_path_symlink() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    std::fs::create_dir(dir.join("target")).unwrap();
+    std::os::unix::fs::symlink(dir.join("target"), dir.join("link")).unwrap();
+    assert_eq!(
+      canonicalize_path(&dir.join("link").join("a").join("b")),
+      dir.join("target").join("a").join("b")
+    );
+    assert_eq!(canonicalize_path(&dir.join("new")), dir.join("new"));
+  }
+
   // TODO: Get a good expected value here for Windows.
   #[cfg(not(windows))]
   #[test]
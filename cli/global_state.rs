@@ -12,6 +12,7 @@ use crate::module_graph::ModuleGraphFile;
 use crate::module_graph::ModuleGraphLoader;
 use crate::module_graph2::GraphBuilder2;
 use crate::module_graph2::TranspileOptions;
+use crate::permission_policy::PermissionPolicy;
 use crate::permissions::Permissions;
 use crate::specifier_handler::FetchHandler;
 use crate::tsc::CompiledModule;
@@ -19,8 +20,10 @@ use crate::tsc::TargetLib;
 use crate::tsc::TsCompiler;
 use deno_core::error::AnyError;
 use deno_core::ModuleSpecifier;
+use deno_core::OpTracer;
 use std::cell::RefCell;
 use std::env;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -47,6 +50,8 @@ pub struct GlobalState {
   pub lockfile: Option<Mutex<Lockfile>>,
   pub maybe_import_map: Option<ImportMap>,
   pub maybe_inspector_server: Option<Arc<InspectorServer>>,
+  /// The tracer of `--trace-ops`, forked for every worker.
+  pub maybe_op_tracer: Option<OpTracer>,
 }
 
 impl GlobalState {
@@ -56,6 +61,11 @@ impl GlobalState {
     let deps_cache_location = dir.root.join("deps");
     let http_cache = http_cache::HttpCache::new(&deps_cache_location);
     let ca_file = flags.ca_file.clone().or_else(|| env::var("DENO_CERT").ok());
+    let maybe_freshness = if flags.revalidate {
+      Some(http_cache::FreshnessPolicy::new(&flags.cache_ttl))
+    } else {
+      None
+    };
 
     let file_fetcher = SourceFileFetcher::new(
       http_cache,
@@ -63,6 +73,7 @@ impl GlobalState {
       flags.cache_blocklist.clone(),
       flags.no_remote,
       flags.cached_only,
+      maybe_freshness,
       ca_file.as_deref(),
     )?;
 
@@ -73,7 +84,8 @@ impl GlobalState {
     )?;
 
     let lockfile = if let Some(filename) = &flags.lock {
-      let lockfile = Lockfile::new(filename.clone(), flags.lock_write)?;
+      let lockfile =
+        Lockfile::new(filename.clone(), flags.lock_write, flags.lock_local)?;
       Some(Mutex::new(lockfile))
     } else {
       None
@@ -96,15 +108,27 @@ impl GlobalState {
       None => None,
     };
 
+    let maybe_op_tracer = match &flags.trace_ops {
+      Some(path) => Some(OpTracer::create(Path::new(path))?),
+      None => None,
+    };
+
+    let mut permissions = Permissions::from_flags(&flags);
+    if let Some(policy_path) = &flags.permission_policy_path {
+      let policy = PermissionPolicy::load(policy_path)?;
+      permissions = policy.to_permissions()?.merge(permissions)?;
+    }
+
     let global_state = GlobalState {
       dir,
-      permissions: Permissions::from_flags(&flags),
+      permissions,
       flags,
       file_fetcher,
       ts_compiler,
       lockfile,
       maybe_import_map,
       maybe_inspector_server,
+      maybe_op_tracer,
     };
     Ok(Arc::new(global_state))
   }
@@ -167,18 +191,17 @@ impl GlobalState {
       // Check integrity of every file in module graph
       if let Some(ref lockfile) = self.lockfile {
         let mut g = lockfile.lock().unwrap();
-
-        for graph_file in &module_graph_files {
-          let check_passed =
-            g.check_or_insert(&graph_file.url, &graph_file.source_code);
-
-          if !check_passed {
-            eprintln!(
-              "Subresource integrity check failed --lock={}\n{}",
-              g.filename, graph_file.url
-            );
-            std::process::exit(10);
-          }
+        let modules = module_graph_files.iter().map(|graph_file| {
+          (
+            graph_file.url.as_str(),
+            graph_file.redirect.as_deref(),
+            graph_file.source_code.as_str(),
+          )
+        });
+
+        if let Err(err) = g.check_or_insert_modules(modules) {
+          eprintln!("{}", err);
+          std::process::exit(10);
         }
       }
 
@@ -242,6 +265,7 @@ impl GlobalState {
           CompiledModule {
             code: "".to_string(),
             name: out.url.to_string(),
+            media_type: MediaType::JavaScript,
           }
         }
       }
@@ -249,6 +273,7 @@ impl GlobalState {
       CompiledModule {
         code: out.source_code,
         name: out.url.to_string(),
+        media_type: out.media_type,
       }
     };
 
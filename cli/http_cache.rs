@@ -4,6 +4,7 @@
 /// as defined in RFC 7234 (https://tools.ietf.org/html/rfc7234).
 /// Currently it's a very simplified version to fulfill Deno needs
 /// at hand.
+use crate::flags::CacheTtl;
 use crate::fs as deno_fs;
 use crate::http_util::HeadersMap;
 use deno_core::error::AnyError;
@@ -11,11 +12,15 @@ use deno_core::serde_json;
 use deno_core::url::Url;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
 
 /// Turn base of url (scheme, hostname, port) into a valid filename.
 /// This method replaces port part with a special string token (because
@@ -79,6 +84,10 @@ pub struct HttpCache {
 pub struct Metadata {
   pub headers: HeadersMap,
   pub url: String,
+  /// Seconds since the UNIX epoch at which the resource was fetched. Missing
+  /// for entries written by older versions of Deno.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub fetched_at: Option<u64>,
 }
 
 impl Metadata {
@@ -100,6 +109,158 @@ impl Metadata {
   pub fn filename(cache_filename: &Path) -> PathBuf {
     cache_filename.with_extension("metadata.json")
   }
+
+  /// Returns for how many seconds after it was fetched the resource is fresh,
+  /// according to the `Cache-Control` and `Expires` response headers. Returns
+  /// `None` if the headers don't say.
+  pub fn freshness_lifetime(&self) -> Option<u64> {
+    if let Some(cache_control) = self.headers.get("cache-control") {
+      let mut max_age = None;
+      for directive in cache_control.split(',') {
+        let directive = directive.trim().to_lowercase();
+        if directive == "no-cache" || directive == "no-store" {
+          return Some(0);
+        }
+        if let Some(value) = directive.strip_prefix("max-age=") {
+          // A max-age that can't be parsed makes the resource stale.
+          max_age = Some(value.trim_matches('"').parse().unwrap_or(0));
+        }
+      }
+      if max_age.is_some() {
+        return max_age;
+      }
+    }
+    let expires = self.headers.get("expires")?;
+    // An invalid date in `Expires` means the resource is already expired.
+    let expires = parse_http_date(expires).unwrap_or(0);
+    let date = self
+      .headers
+      .get("date")
+      .and_then(|date| parse_http_date(date))
+      .or(self.fetched_at)?;
+    Some(expires.saturating_sub(date))
+  }
+
+  /// Returns the age of the resource in seconds at the time `now`, or `None`
+  /// if it is not known when the resource was fetched.
+  pub fn age(&self, now: u64) -> Option<u64> {
+    let age_header = self
+      .headers
+      .get("age")
+      .and_then(|age| age.trim().parse::<u64>().ok())
+      .unwrap_or(0);
+    self
+      .fetched_at
+      .map(|fetched_at| now.saturating_sub(fetched_at) + age_header)
+  }
+}
+
+/// Returns the number of days between the UNIX epoch and a civil date, see
+/// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let yoe = year.rem_euclid(400);
+  let mp = if month > 2 { month - 3 } else { month + 9 };
+  let doy = (153 * mp + 2) / 5 + day - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  era * 146_097 + doe - 719_468
+}
+
+/// Parses an HTTP date in the preferred IMF-fixdate format, for example
+/// "Sun, 06 Nov 1994 08:49:37 GMT", into seconds since the UNIX epoch.
+fn parse_http_date(date: &str) -> Option<u64> {
+  const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+    "Nov", "Dec",
+  ];
+  let parts: Vec<&str> = date.split_whitespace().collect();
+  if parts.len() != 6 || parts[5] != "GMT" {
+    return None;
+  }
+  let day: i64 = parts[1].parse().ok()?;
+  let month = MONTHS.iter().position(|m| *m == parts[2])? as i64 + 1;
+  let year: i64 = parts[3].parse().ok()?;
+  let time: Vec<i64> = parts[4]
+    .split(':')
+    .map(|part| part.parse().ok())
+    .collect::<Option<_>>()?;
+  if time.len() != 3 || day < 1 || day > 31 {
+    return None;
+  }
+  let secs = days_from_civil(year, month, day) * 86_400
+    + time[0] * 3600
+    + time[1] * 60
+    + time[2];
+  if secs < 0 {
+    None
+  } else {
+    Some(secs as u64)
+  }
+}
+
+/// Decides whether a cached resource can be used without revalidating it with
+/// the server, which is what `--revalidate` opts into. A time to live given
+/// for the host of a resource, or for all hosts, takes precedence over the
+/// caching headers of the response. Resources that have neither are
+/// considered fresh forever, as they are without `--revalidate`.
+#[derive(Clone, Debug, Default)]
+pub struct FreshnessPolicy {
+  ttl: Option<u64>,
+  host_ttls: HashMap<String, u64>,
+}
+
+impl FreshnessPolicy {
+  pub fn new(cache_ttls: &[CacheTtl]) -> Self {
+    let mut policy = Self::default();
+    for cache_ttl in cache_ttls {
+      match &cache_ttl.host {
+        Some(host) => {
+          policy
+            .host_ttls
+            .insert(host.to_lowercase(), cache_ttl.seconds);
+        }
+        None => policy.ttl = Some(cache_ttl.seconds),
+      }
+    }
+    policy
+  }
+
+  fn ttl_for(&self, url: &Url) -> Option<u64> {
+    let host = url.host_str().unwrap_or("");
+    let host_and_port = match url.port() {
+      Some(port) => format!("{}:{}", host, port),
+      None => host.to_string(),
+    };
+    self
+      .host_ttls
+      .get(&host_and_port)
+      .or_else(|| self.host_ttls.get(host))
+      .cloned()
+      .or(self.ttl)
+  }
+
+  /// Returns whether the cached resource is fresh at the time `now`, in
+  /// seconds since the UNIX epoch.
+  pub fn is_fresh(&self, url: &Url, metadata: &Metadata, now: u64) -> bool {
+    let lifetime =
+      match self.ttl_for(url).or_else(|| metadata.freshness_lifetime()) {
+        Some(lifetime) => lifetime,
+        None => return true,
+      };
+    match metadata.age(now) {
+      Some(age) => age < lifetime,
+      None => false,
+    }
+  }
+}
+
+/// Returns the current time in seconds since the UNIX epoch.
+pub fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
 }
 
 impl HttpCache {
@@ -171,9 +332,56 @@ impl HttpCache {
     let metadata = Metadata {
       url: url.to_string(),
       headers: headers_map,
+      fetched_at: Some(now()),
     };
     metadata.write(&cache_filename)
   }
+
+  /// Marks a cached resource as fetched now, after the server confirmed that
+  /// it has not been modified.
+  pub fn refresh(&self, url: &Url) -> Result<(), AnyError> {
+    let cache_filename = self.location.join(url_to_filename(url));
+    let mut metadata = Metadata::read(&cache_filename)?;
+    metadata.fetched_at = Some(now());
+    metadata.write(&cache_filename)
+  }
+
+  /// Returns the filename and metadata of every resource in the cache.
+  /// Files without readable metadata are skipped.
+  pub fn entries(&self) -> Vec<(PathBuf, Metadata)> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(&self.location)
+      .into_iter()
+      .filter_map(Result::ok)
+    {
+      let path = entry.path();
+      let is_metadata = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.ends_with(".metadata.json"))
+        .unwrap_or(false);
+      if !entry.file_type().is_file() || !is_metadata {
+        continue;
+      }
+      // Strip ".metadata.json" to get the hash the content is stored under.
+      let cache_filename = path.with_extension("").with_extension("");
+      if let Ok(metadata) = Metadata::read(&cache_filename) {
+        entries.push((cache_filename, metadata));
+      }
+    }
+    entries.sort_by(|a, b| a.1.url.cmp(&b.1.url));
+    entries
+  }
+
+  /// Removes a resource and its metadata from the cache.
+  pub fn remove(&self, url: &Url) -> Result<(), AnyError> {
+    let cache_filename = self.location.join(url_to_filename(url));
+    fs::remove_file(Metadata::filename(&cache_filename))?;
+    match fs::remove_file(&cache_filename) {
+      Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+      _ => Ok(()),
+    }
+  }
 }
 
 #[cfg(test)]
@@ -239,6 +447,126 @@ mod tests {
     assert_eq!(headers.get("foobar"), None);
   }
 
+  #[test]
+  fn test_entries_and_remove() {
+    let dir = TempDir::new().unwrap();
+    let cache = HttpCache::new(dir.path());
+    let url_a = Url::parse("https://deno.land/x/a.ts").unwrap();
+    let url_b = Url::parse("http://localhost:4545/b.ts").unwrap();
+    cache.set(&url_b, HashMap::new(), b"bb").unwrap();
+    cache.set(&url_a, HashMap::new(), b"a").unwrap();
+
+    let entries = cache.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0, cache.get_cache_filename(&url_a));
+    assert_eq!(entries[0].1.url, url_a.to_string());
+    assert!(entries[0].1.fetched_at.is_some());
+    assert_eq!(entries[1].1.url, url_b.to_string());
+
+    cache.remove(&url_a).unwrap();
+    assert!(cache.get(&url_a).is_err());
+    let entries = cache.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].1.url, url_b.to_string());
+  }
+
+  #[test]
+  fn test_parse_http_date() {
+    assert_eq!(
+      parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+      Some(784_111_777)
+    );
+    assert_eq!(
+      parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"),
+      Some(951_782_400)
+    );
+    assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    assert_eq!(parse_http_date("0"), None);
+  }
+
+  fn metadata_with(headers: &[(&str, &str)], fetched_at: u64) -> Metadata {
+    Metadata {
+      headers: headers
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect(),
+      url: "https://deno.land/x/mod.ts".to_string(),
+      fetched_at: Some(fetched_at),
+    }
+  }
+
+  #[test]
+  fn test_freshness_lifetime() {
+    let metadata =
+      metadata_with(&[("cache-control", "public, max-age=600")], 0);
+    assert_eq!(metadata.freshness_lifetime(), Some(600));
+    let metadata =
+      metadata_with(&[("cache-control", "max-age=600, no-cache")], 0);
+    assert_eq!(metadata.freshness_lifetime(), Some(0));
+    let metadata = metadata_with(
+      &[
+        ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ("expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+      ],
+      0,
+    );
+    assert_eq!(metadata.freshness_lifetime(), Some(3600));
+    let metadata = metadata_with(
+      &[
+        ("cache-control", "max-age=60"),
+        ("expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+      ],
+      0,
+    );
+    assert_eq!(metadata.freshness_lifetime(), Some(60));
+    let metadata = metadata_with(&[("expires", "0")], 100);
+    assert_eq!(metadata.freshness_lifetime(), Some(0));
+    let metadata = metadata_with(&[("etag", "abc")], 0);
+    assert_eq!(metadata.freshness_lifetime(), None);
+  }
+
+  #[test]
+  fn test_freshness_policy() {
+    let url = Url::parse("https://deno.land/x/mod.ts").unwrap();
+    let policy = FreshnessPolicy::default();
+    let metadata =
+      metadata_with(&[("cache-control", "max-age=600"), ("age", "100")], 1000);
+    assert!(policy.is_fresh(&url, &metadata, 1499));
+    assert!(!policy.is_fresh(&url, &metadata, 1500));
+    let metadata = metadata_with(&[], 1000);
+    assert!(policy.is_fresh(&url, &metadata, 1_000_000));
+
+    let policy = FreshnessPolicy::new(&[
+      CacheTtl {
+        host: None,
+        seconds: 10,
+      },
+      CacheTtl {
+        host: Some("deno.land".to_string()),
+        seconds: 3600,
+      },
+    ]);
+    assert!(policy.is_fresh(&url, &metadata, 4599));
+    assert!(!policy.is_fresh(&url, &metadata, 4600));
+    let other = Url::parse("http://localhost:4545/mod.ts").unwrap();
+    assert!(!policy.is_fresh(&other, &metadata, 1010));
+  }
+
+  #[test]
+  fn test_refresh() {
+    let dir = TempDir::new().unwrap();
+    let cache = HttpCache::new(dir.path());
+    let url = Url::parse("https://deno.land/x/welcome.ts").unwrap();
+    cache.set(&url, HashMap::new(), b"Hello").unwrap();
+    let cache_filename = cache.get_cache_filename(&url);
+    let mut metadata = Metadata::read(&cache_filename).unwrap();
+    metadata.fetched_at = Some(0);
+    metadata.write(&cache_filename).unwrap();
+    cache.refresh(&url).unwrap();
+    let metadata = cache.get_metadata(&url).unwrap();
+    assert!(metadata.fetched_at.unwrap() > 0);
+  }
+
   #[test]
   fn test_url_to_filename() {
     let test_cases = [
@@ -1,5 +1,6 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::auth_tokens::AuthToken;
 use crate::version;
 use bytes::Bytes;
 use deno_core::error::generic_error;
@@ -9,6 +10,7 @@ use deno_core::url::Url;
 use deno_fetch::reqwest;
 use deno_fetch::reqwest::header::HeaderMap;
 use deno_fetch::reqwest::header::HeaderValue;
+use deno_fetch::reqwest::header::AUTHORIZATION;
 use deno_fetch::reqwest::header::IF_NONE_MATCH;
 use deno_fetch::reqwest::header::LOCATION;
 use deno_fetch::reqwest::header::USER_AGENT;
@@ -95,10 +97,13 @@ pub enum FetchOnceResult {
 /// yields Code(ResultPayload).
 /// If redirect occurs, does not follow and
 /// yields Redirect(url).
+/// The credentials of `maybe_auth_token` are sent in the `Authorization`
+/// header; they are never part of the returned headers.
 pub async fn fetch_once(
   client: Client,
   url: &Url,
   cached_etag: Option<String>,
+  maybe_auth_token: Option<AuthToken>,
 ) -> Result<FetchOnceResult, AnyError> {
   let url = url.clone();
 
@@ -108,6 +113,13 @@ pub async fn fetch_once(
     let if_none_match_val = HeaderValue::from_str(&etag).unwrap();
     request = request.header(IF_NONE_MATCH, if_none_match_val);
   }
+  if let Some(auth_token) = maybe_auth_token {
+    let authorization_val = HeaderValue::from_str(&auth_token.header_value())
+      .map_err(|_| {
+      generic_error(format!("Invalid auth token for \"{}\"", url))
+    })?;
+    request = request.header(AUTHORIZATION, authorization_val);
+  }
   let response = request.send().await?;
 
   if response.status() == StatusCode::NOT_MODIFIED {
@@ -247,7 +259,7 @@ mod tests {
     let url =
       Url::parse("http://127.0.0.1:4545/cli/tests/fixture.json").unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(headers.get("content-type").unwrap(), "application/json");
@@ -267,7 +279,7 @@ mod tests {
     )
     .unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('gzip')");
       assert_eq!(
@@ -286,7 +298,7 @@ mod tests {
     let _http_server_guard = test_util::http_server();
     let url = Url::parse("http://127.0.0.1:4545/etag_script.ts").unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client.clone(), &url, None).await;
+    let result = fetch_once(client.clone(), &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('etag')");
@@ -300,7 +312,8 @@ mod tests {
     }
 
     let res =
-      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string())).await;
+      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string()), None)
+        .await;
     assert_eq!(res.unwrap(), FetchOnceResult::NotModified);
   }
 
@@ -313,7 +326,7 @@ mod tests {
     )
     .unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('brotli');");
@@ -338,7 +351,7 @@ mod tests {
     let target_url =
       Url::parse("http://localhost:4545/cli/tests/fixture.json").unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Redirect(url, _)) = result {
       assert_eq!(url, target_url);
     } else {
@@ -398,7 +411,7 @@ mod tests {
         .unwrap(),
     ))
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(headers.get("content-type").unwrap(), "application/json");
@@ -424,7 +437,7 @@ mod tests {
         .unwrap(),
     ))
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('gzip')");
       assert_eq!(
@@ -449,7 +462,7 @@ mod tests {
         .unwrap(),
     ))
     .unwrap();
-    let result = fetch_once(client.clone(), &url, None).await;
+    let result = fetch_once(client.clone(), &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('etag')");
@@ -464,7 +477,8 @@ mod tests {
     }
 
     let res =
-      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string())).await;
+      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string()), None)
+        .await;
     assert_eq!(res.unwrap(), FetchOnceResult::NotModified);
   }
 
@@ -483,7 +497,7 @@ mod tests {
         .unwrap(),
     ))
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('brotli');");
@@ -504,7 +518,7 @@ mod tests {
     let url_str = "http://127.0.0.1:4545/bad_redirect";
     let url = Url::parse(url_str).unwrap();
     let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, None).await;
     assert!(result.is_err());
     let err = result.unwrap_err();
     // Check that the error message contains the original URL
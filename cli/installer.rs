@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use crate::flags::Flags;
+use crate::fs::resolve_from_cwd;
 use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::url::Url;
@@ -13,6 +14,7 @@ use std::io;
 use std::io::Write;
 #[cfg(not(windows))]
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::path::PathBuf;
 
 lazy_static! {
@@ -130,7 +132,7 @@ fn infer_name_from_url(url: &Url) -> Option<String> {
 }
 
 pub fn install(
-  flags: Flags,
+  mut flags: Flags,
   module_url: &str,
   args: Vec<String>,
   name: Option<String>,
@@ -189,6 +191,12 @@ pub fn install(
     ));
   };
 
+  // The installed script may be run from any directory.
+  if let Some(policy_path) = flags.permission_policy_path.take() {
+    let policy_path = resolve_from_cwd(Path::new(&policy_path))?;
+    flags.permission_policy_path = Some(policy_path.display().to_string());
+  }
+
   let mut executable_args = vec!["run".to_string()];
   executable_args.extend_from_slice(&flags.to_permission_args());
   if let Some(ca_file) = flags.ca_file {
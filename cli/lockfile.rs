@@ -1,31 +1,147 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::fs::normalize_path;
 use deno_core::serde_json;
-use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
+use deno_core::url::Url;
+use serde::Deserialize;
+use serde::Serialize;
 use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::ErrorKind;
 use std::io::Result;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The version of the lock file format written by this version of Deno.
+/// Lock files without a version are the flat map of version 1, which maps
+/// remote specifiers to checksums, and are still read.
+const LOCKFILE_VERSION: &str = "2";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct LockfileContent {
+  version: String,
+  /// Checksums of remote modules, keyed by the URL their source was served
+  /// from.
+  #[serde(default)]
+  remote: BTreeMap<String, String>,
+  /// Remote specifiers that were redirected, mapped to the URL they were
+  /// redirected to.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  redirects: BTreeMap<String, String>,
+  /// Checksums of local modules, only recorded with `--lock-local`.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  local: BTreeMap<String, String>,
+}
+
+impl LockfileContent {
+  fn parse(s: &str) -> Result<LockfileContent> {
+    let value: Value = serde_json::from_str(s)?;
+    match value.get("version").cloned() {
+      None => Ok(LockfileContent {
+        version: LOCKFILE_VERSION.to_string(),
+        remote: serde_json::from_value(value)?,
+        ..Default::default()
+      }),
+      Some(Value::String(version)) if version == LOCKFILE_VERSION => {
+        Ok(serde_json::from_value(value)?)
+      }
+      Some(version) => Err(std::io::Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported lock file version: {}", version),
+      )),
+    }
+  }
+}
+
+/// A module that does not match its entry in the lock file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockfileMismatch {
+  pub specifier: String,
+  /// The locked checksum or redirect target, `None` if the module is not in
+  /// the lock file.
+  pub expected: Option<String>,
+  pub actual: String,
+}
+
+/// The modules of a module graph that failed the integrity check, listed like
+/// a diff of the lock file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockfileError {
+  pub filename: String,
+  pub mismatches: Vec<LockfileMismatch>,
+}
+
+impl fmt::Display for LockfileError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "Subresource integrity check failed --lock={}",
+      self.filename
+    )?;
+    for mismatch in &self.mismatches {
+      write!(f, "\n{}", mismatch.specifier)?;
+      match &mismatch.expected {
+        Some(expected) => write!(f, "\n  - {}", expected)?,
+        None => write!(f, "\n  - (not in lock file)")?,
+      }
+      write!(f, "\n  + {}", mismatch.actual)?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for LockfileError {}
 
 #[derive(Debug, Clone)]
 pub struct Lockfile {
   write: bool,
-  map: BTreeMap<String, String>,
+  local: bool,
+  content: LockfileContent,
+  /// Entries checked or inserted during this run. With `--lock-write` all
+  /// other entries are pruned.
+  seen: HashSet<String>,
+  /// The directory of the lock file, local modules are keyed by their path
+  /// relative to it.
+  dir: PathBuf,
   pub filename: String,
 }
 
 impl Lockfile {
-  pub fn new(filename: String, write: bool) -> Result<Lockfile> {
-    debug!("lockfile \"{}\", write: {}", filename, write);
+  pub fn new(filename: String, write: bool, local: bool) -> Result<Lockfile> {
+    debug!(
+      "lockfile \"{}\", write: {}, local: {}",
+      filename, write, local
+    );
 
-    let map = if write {
-      BTreeMap::new()
-    } else {
-      let s = std::fs::read_to_string(&filename)?;
-      serde_json::from_str(&s)?
+    let new_content = || LockfileContent {
+      version: LOCKFILE_VERSION.to_string(),
+      ..Default::default()
     };
+    let content = match std::fs::read_to_string(&filename) {
+      // With --lock-write a lock file that can't be read, like one of a newer
+      // version, is replaced.
+      Ok(s) if write => LockfileContent::parse(&s).unwrap_or_else(|err| {
+        debug!("lockfile \"{}\" is replaced: {}", filename, err);
+        new_content()
+      }),
+      Ok(s) => LockfileContent::parse(&s)?,
+      Err(err) if write && err.kind() == ErrorKind::NotFound => new_content(),
+      Err(err) => return Err(err),
+    };
+    let dir = normalize_path(&std::env::current_dir()?.join(&filename))
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
 
     Ok(Lockfile {
       write,
-      map,
+      local,
+      content,
+      seen: HashSet::new(),
+      dir,
       filename,
     })
   }
@@ -35,10 +151,21 @@ impl Lockfile {
     if !self.write {
       return Ok(());
     }
-    // Will perform sort so output is deterministic
-    let map: BTreeMap<_, _> = self.map.iter().collect();
-    let j = json!(map);
-    let s = serde_json::to_string_pretty(&j).unwrap();
+    let seen = |entries: &BTreeMap<String, String>| {
+      entries
+        .iter()
+        .filter(|(specifier, _)| self.seen.contains(*specifier))
+        .map(|(specifier, value)| (specifier.clone(), value.clone()))
+        .collect::<BTreeMap<_, _>>()
+    };
+    // BTreeMap keeps the output sorted and therefore deterministic.
+    let content = LockfileContent {
+      version: LOCKFILE_VERSION.to_string(),
+      remote: seen(&self.content.remote),
+      redirects: seen(&self.content.redirects),
+      local: seen(&self.content.local),
+    };
+    let s = serde_json::to_string_pretty(&content).unwrap();
     let mut f = std::fs::OpenOptions::new()
       .write(true)
       .create(true)
@@ -50,36 +177,128 @@ impl Lockfile {
     Ok(())
   }
 
-  pub fn check_or_insert(&mut self, specifier: &str, code: &str) -> bool {
-    if self.write {
-      // In case --lock-write is specified check always passes
-      self.insert(specifier, code);
-      true
+  /// Checks the integrity of the given modules, or records them when
+  /// `--lock-write` is specified. Every module is given as its specifier, the
+  /// URL it was redirected to, if any, and its source code. The error lists
+  /// all modules that do not match the lock file.
+  pub fn check_or_insert_modules<'a, I>(
+    &mut self,
+    modules: I,
+  ) -> std::result::Result<(), LockfileError>
+  where
+    I: IntoIterator<Item = (&'a str, Option<&'a str>, &'a str)>,
+  {
+    let mut mismatches: Vec<LockfileMismatch> = modules
+      .into_iter()
+      .filter_map(|(specifier, maybe_redirect, code)| match maybe_redirect {
+        Some(redirect) => self.check_or_insert_redirect(specifier, redirect),
+        None => self.check_or_insert_checksum(specifier, code),
+      })
+      .collect();
+
+    if mismatches.is_empty() {
+      Ok(())
     } else {
-      self.check(specifier, code)
+      mismatches.sort_by(|a, b| a.specifier.cmp(&b.specifier));
+      Err(LockfileError {
+        filename: self.filename.clone(),
+        mismatches,
+      })
     }
   }
 
-  /// Checks the given module is included.
-  /// Returns Ok(true) if check passed.
-  fn check(&mut self, specifier: &str, code: &str) -> bool {
-    if specifier.starts_with("file:") {
-      return true;
-    }
-    if let Some(lockfile_checksum) = self.map.get(specifier) {
-      let compiled_checksum = crate::checksum::gen(&[code.as_bytes()]);
-      lockfile_checksum == &compiled_checksum
-    } else {
-      false
-    }
+  pub fn check_or_insert(&mut self, specifier: &str, code: &str) -> bool {
+    self.check_or_insert_checksum(specifier, code).is_none()
   }
 
-  fn insert(&mut self, specifier: &str, code: &str) {
-    if specifier.starts_with("file:") {
-      return;
-    }
+  fn check_or_insert_checksum(
+    &mut self,
+    specifier: &str,
+    code: &str,
+  ) -> Option<LockfileMismatch> {
+    let (entries, key) = if specifier.starts_with("file:") {
+      if !self.local {
+        return None;
+      }
+      let key = self.local_key(specifier);
+      (&mut self.content.local, key)
+    } else {
+      (&mut self.content.remote, specifier.to_string())
+    };
     let checksum = crate::checksum::gen(&[code.as_bytes()]);
-    self.map.insert(specifier.to_string(), checksum);
+    self.seen.insert(key.clone());
+    check_or_insert_entry(entries, self.write, &key, checksum)
+  }
+
+  /// Returns the path of a local module relative to the lock file, so that
+  /// the lock file still applies to a checkout in another directory. Falls
+  /// back to the specifier when there is no such path, like for a module on
+  /// another drive.
+  fn local_key(&self, specifier: &str) -> String {
+    Url::parse(specifier)
+      .ok()
+      .and_then(|url| url.to_file_path().ok())
+      .and_then(|path| relative_path(&self.dir, &path))
+      .unwrap_or_else(|| specifier.to_string())
+  }
+
+  fn check_or_insert_redirect(
+    &mut self,
+    specifier: &str,
+    redirect: &str,
+  ) -> Option<LockfileMismatch> {
+    self.seen.insert(specifier.to_string());
+    check_or_insert_entry(
+      &mut self.content.redirects,
+      self.write,
+      specifier,
+      redirect.to_string(),
+    )
+  }
+}
+
+/// Returns `path` relative to the directory `base`, with `/` separators and
+/// starting with `./` or `../`.
+fn relative_path(base: &Path, path: &Path) -> Option<String> {
+  if base.components().next() != path.components().next() {
+    return None;
+  }
+  let mut base_components = base.components().peekable();
+  let mut path_components = path.components().peekable();
+  while base_components.peek().is_some()
+    && base_components.peek() == path_components.peek()
+  {
+    base_components.next();
+    path_components.next();
+  }
+  let mut parts: Vec<&str> = base_components.map(|_| "..").collect();
+  if parts.is_empty() {
+    parts.push(".");
+  }
+  for component in path_components {
+    parts.push(component.as_os_str().to_str()?);
+  }
+  Some(parts.join("/"))
+}
+
+// In case --lock-write is specified the check always passes.
+fn check_or_insert_entry(
+  entries: &mut BTreeMap<String, String>,
+  write: bool,
+  specifier: &str,
+  actual: String,
+) -> Option<LockfileMismatch> {
+  if write {
+    entries.insert(specifier.to_string(), actual);
+    return None;
+  }
+  match entries.get(specifier) {
+    Some(expected) if expected == &actual => None,
+    expected => Some(LockfileMismatch {
+      specifier: specifier.to_string(),
+      expected: expected.cloned(),
+      actual,
+    }),
   }
 }
 
@@ -116,19 +335,28 @@ mod tests {
     temp_dir.close().expect("file close error");
   }
 
+  fn read_lockfile(file_path: &str) -> serde_json::Value {
+    let mut checkfile = File::open(file_path).expect("Unable to open the file");
+    let mut contents = String::new();
+    checkfile
+      .read_to_string(&mut contents)
+      .expect("Unable to read the file");
+    serde_json::from_str(&contents).unwrap()
+  }
+
   #[test]
   fn new_nonexistent_lockfile() {
     let file_path = String::from("nonexistent_lock_file.json");
-    assert!(Lockfile::new(file_path, false).is_err());
+    assert!(Lockfile::new(file_path, false, false).is_err());
   }
 
   #[test]
   fn new_valid_lockfile() {
     let (temp_dir, file_path) = setup();
 
-    let result = Lockfile::new(file_path, false).unwrap();
+    let result = Lockfile::new(file_path, false, false).unwrap();
 
-    let keys: Vec<String> = result.map.keys().cloned().collect();
+    let keys: Vec<String> = result.content.remote.keys().cloned().collect();
     let expected_keys = vec![
       String::from("https://deno.land/std@0.71.0/async/delay.ts"),
       String::from("https://deno.land/std@0.71.0/textproto/mod.ts"),
@@ -140,18 +368,31 @@ mod tests {
     teardown(temp_dir);
   }
 
+  #[test]
+  fn new_lockfile_with_unsupported_version() {
+    let temp_dir = TempDir::new().expect("could not create temp dir");
+    let file_path = temp_dir.path().join("lockfile.json");
+    std::fs::write(&file_path, r#"{ "version": "3" }"#).unwrap();
+
+    let file_path = file_path.to_str().unwrap().to_string();
+    let err = Lockfile::new(file_path, false, false).unwrap_err();
+    assert_eq!(err.to_string(), "Unsupported lock file version: \"3\"");
+
+    teardown(temp_dir);
+  }
+
   #[test]
   fn new_lockfile_from_file_and_insert() {
     let (temp_dir, file_path) = setup();
 
-    let mut lockfile = Lockfile::new(file_path, false).unwrap();
+    let mut lockfile = Lockfile::new(file_path, true, false).unwrap();
 
-    lockfile.insert(
+    lockfile.check_or_insert(
       "https://deno.land/std@0.71.0/io/util.ts",
       "Here is some source code",
     );
 
-    let keys: Vec<String> = lockfile.map.keys().cloned().collect();
+    let keys: Vec<String> = lockfile.content.remote.keys().cloned().collect();
     let expected_keys = vec![
       String::from("https://deno.land/std@0.71.0/async/delay.ts"),
       String::from("https://deno.land/std@0.71.0/io/util.ts"),
@@ -167,36 +408,31 @@ mod tests {
   fn new_lockfile_and_write() {
     let (temp_dir, file_path) = setup();
 
-    let mut lockfile = Lockfile::new(file_path, true).unwrap();
+    let mut lockfile = Lockfile::new(file_path.clone(), true, false).unwrap();
 
-    lockfile.insert(
+    lockfile.check_or_insert(
       "https://deno.land/std@0.71.0/textproto/mod.ts",
       "Here is some source code",
     );
-    lockfile.insert(
+    lockfile.check_or_insert(
       "https://deno.land/std@0.71.0/io/util.ts",
       "more source code here",
     );
-    lockfile.insert(
-      "https://deno.land/std@0.71.0/async/delay.ts",
-      "this source is really exciting",
-    );
 
     lockfile.write().expect("unable to write");
 
-    let file_path_buf = temp_dir.path().join("valid_lockfile.json");
-    let file_path = file_path_buf.to_str().expect("file path fail").to_string();
-
-    // read the file contents back into a string and check
-    let mut checkfile = File::open(file_path).expect("Unable to open the file");
-    let mut contents = String::new();
-    checkfile
-      .read_to_string(&mut contents)
-      .expect("Unable to read the file");
-
-    assert!(contents.contains(
-      "fedebba9bb82cce293196f54b21875b649e457f0eaf55556f1e318204947a28f"
-    )); // sha-256 hash of the source 'Here is some source code'
+    // The entry of delay.ts is pruned, as it was not part of the graph.
+    assert_eq!(
+      read_lockfile(&file_path),
+      json!({
+        "version": "2",
+        "remote": {
+          // sha-256 hash of the source 'Here is some source code'
+          "https://deno.land/std@0.71.0/textproto/mod.ts": "fedebba9bb82cce293196f54b21875b649e457f0eaf55556f1e318204947a28f",
+          "https://deno.land/std@0.71.0/io/util.ts": "9d3ec2a04876aa84ac14876d10e9911bb28ebd6b4132985b7a1f16179fa7502e"
+        }
+      })
+    );
 
     teardown(temp_dir);
   }
@@ -205,12 +441,14 @@ mod tests {
   fn check_or_insert_lockfile_false() {
     let (temp_dir, file_path) = setup();
 
-    let mut lockfile = Lockfile::new(file_path, false).unwrap();
-
-    lockfile.insert(
+    let mut lockfile = Lockfile::new(file_path.clone(), true, false).unwrap();
+    lockfile.check_or_insert(
       "https://deno.land/std@0.71.0/textproto/mod.ts",
       "Here is some source code",
     );
+    lockfile.write().expect("unable to write");
+
+    let mut lockfile = Lockfile::new(file_path, false, false).unwrap();
 
     let check_true = lockfile.check_or_insert(
       "https://deno.land/std@0.71.0/textproto/mod.ts",
@@ -226,4 +464,149 @@ mod tests {
 
     teardown(temp_dir);
   }
+
+  #[test]
+  fn check_or_insert_modules_with_redirects_and_local() {
+    let (temp_dir, file_path) = setup();
+
+    let modules = vec![
+      (
+        "https://deno.land/std/fs/mod.ts",
+        Some("https://deno.land/std@0.71.0/fs/mod.ts"),
+        "",
+      ),
+      (
+        "https://deno.land/std@0.71.0/fs/mod.ts",
+        None,
+        "Here is some source code",
+      ),
+      (
+        "file:///main.ts",
+        None,
+        "import \"https://deno.land/std/fs/mod.ts\";",
+      ),
+    ];
+    let mut lockfile = Lockfile::new(file_path.clone(), true, true).unwrap();
+    lockfile
+      .check_or_insert_modules(modules.clone())
+      .expect("write always passes");
+    lockfile.write().expect("unable to write");
+
+    let lockfile_json = read_lockfile(&file_path);
+    assert_eq!(
+      lockfile_json["redirects"],
+      json!({
+        "https://deno.land/std/fs/mod.ts": "https://deno.land/std@0.71.0/fs/mod.ts"
+      })
+    );
+    assert_eq!(lockfile_json["local"].as_object().unwrap().len(), 1);
+
+    let mut lockfile = Lockfile::new(file_path.clone(), false, true).unwrap();
+    assert!(lockfile.check_or_insert_modules(modules.clone()).is_ok());
+
+    let changed = vec![
+      (
+        "https://deno.land/std/fs/mod.ts",
+        Some("https://deno.land/std@0.72.0/fs/mod.ts"),
+        "",
+      ),
+      (
+        "https://deno.land/std@0.72.0/fs/mod.ts",
+        None,
+        "Here is some source code",
+      ),
+      (
+        "file:///main.ts",
+        None,
+        "import \"https://deno.land/std/fs/mod.ts\";",
+      ),
+    ];
+    let err = lockfile.check_or_insert_modules(changed).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Subresource integrity check failed --lock={}
+https://deno.land/std/fs/mod.ts
+  - https://deno.land/std@0.71.0/fs/mod.ts
+  + https://deno.land/std@0.72.0/fs/mod.ts
+https://deno.land/std@0.72.0/fs/mod.ts
+  - (not in lock file)
+  + fedebba9bb82cce293196f54b21875b649e457f0eaf55556f1e318204947a28f",
+        file_path
+      )
+    );
+
+    // Local modules are only checked when opted in.
+    let mut lockfile = Lockfile::new(file_path, false, false).unwrap();
+    assert!(lockfile.check_or_insert("file:///main.ts", "changed"));
+
+    teardown(temp_dir);
+  }
+
+  #[test]
+  fn local_modules_relative_to_lockfile() {
+    let temp_dir = TempDir::new().expect("could not create temp dir");
+    let file_path = temp_dir.path().join("lock.json");
+    let file_path = file_path.to_str().unwrap().to_string();
+    let main_url = Url::from_file_path(temp_dir.path().join("src/main.ts"))
+      .unwrap()
+      .to_string();
+
+    let mut lockfile = Lockfile::new(file_path.clone(), true, true).unwrap();
+    assert!(lockfile.check_or_insert(&main_url, "source"));
+    lockfile.write().expect("unable to write");
+    let lockfile_json = read_lockfile(&file_path);
+    assert!(lockfile_json["local"]["./src/main.ts"].is_string());
+
+    // The lock file applies to a copy of the directory.
+    let copy_dir = TempDir::new().expect("could not create temp dir");
+    let copy_path = copy_dir.path().join("lock.json");
+    std::fs::copy(&file_path, &copy_path).unwrap();
+    let copy_url = Url::from_file_path(copy_dir.path().join("src/main.ts"))
+      .unwrap()
+      .to_string();
+    let mut lockfile =
+      Lockfile::new(copy_path.to_str().unwrap().to_string(), false, true)
+        .unwrap();
+    assert!(lockfile.check_or_insert(&copy_url, "source"));
+    assert!(!lockfile.check_or_insert(&copy_url, "changed"));
+
+    teardown(copy_dir);
+    teardown(temp_dir);
+  }
+
+  #[test]
+  fn relative_path_to_lockfile_dir() {
+    let base = Path::new("/project");
+    assert_eq!(
+      relative_path(base, Path::new("/project/src/main.ts")),
+      Some("./src/main.ts".to_string())
+    );
+    assert_eq!(
+      relative_path(base, Path::new("/shared/mod.ts")),
+      Some("../shared/mod.ts".to_string())
+    );
+  }
+
+  #[test]
+  fn write_replaces_unreadable_lockfile() {
+    let temp_dir = TempDir::new().expect("could not create temp dir");
+    let file_path = temp_dir.path().join("lockfile.json");
+    std::fs::write(&file_path, r#"{ "version": "3" }"#).unwrap();
+    let file_path = file_path.to_str().unwrap().to_string();
+
+    let mut lockfile = Lockfile::new(file_path.clone(), true, false).unwrap();
+    lockfile.check_or_insert(
+      "https://deno.land/std@0.71.0/io/util.ts",
+      "more source code here",
+    );
+    lockfile.write().expect("unable to write");
+    assert_eq!(read_lockfile(&file_path)["version"], "2");
+
+    std::fs::write(&file_path, "not json").unwrap();
+    assert!(Lockfile::new(file_path.clone(), true, false).is_ok());
+    assert!(Lockfile::new(file_path, false, false).is_err());
+
+    teardown(temp_dir);
+  }
 }
@@ -8,6 +8,8 @@ extern crate lazy_static;
 extern crate log;
 
 mod ast;
+mod auth_tokens;
+mod cache_dir;
 mod checksum;
 pub mod colors;
 mod coverage;
@@ -41,6 +43,8 @@ mod module_graph;
 mod module_graph2;
 mod op_fetch_asset;
 pub mod ops;
+mod permission_audit;
+mod permission_policy;
 pub mod permissions;
 mod repl;
 pub mod resolve_addr;
@@ -54,18 +58,24 @@ mod tokio_util;
 mod tsc;
 mod tsc_config;
 mod upgrade;
+mod vendor;
 pub mod version;
 pub mod worker;
 
 use crate::coverage::CoverageCollector;
+use crate::coverage::HtmlCoverageReporter;
+use crate::coverage::LcovCoverageReporter;
 use crate::coverage::PrettyCoverageReporter;
 use crate::file_fetcher::SourceFile;
 use crate::file_fetcher::SourceFileFetcher;
 use crate::fs as deno_fs;
 use crate::global_state::GlobalState;
 use crate::media_type::MediaType;
+use crate::module_graph2::GraphBuilder2;
 use crate::permissions::Permissions;
+use crate::specifier_handler::FetchHandler;
 use crate::worker::MainWorker;
+use deno_core::error::generic_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::FutureExt;
 use deno_core::futures::Future;
@@ -77,16 +87,19 @@ use deno_core::ModuleSpecifier;
 use deno_doc as doc;
 use deno_doc::parser::DocFileLoader;
 use flags::DenoSubcommand;
+use flags::DirCommand;
 use flags::Flags;
 use global_state::exit_unstable;
 use log::Level;
 use log::LevelFilter;
+use std::cell::RefCell;
 use std::env;
 use std::io::Read;
 use std::io::Write;
 use std::iter::once;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::Arc;
 use upgrade::upgrade_command;
 
@@ -271,6 +284,159 @@ async fn cache_command(
   Ok(())
 }
 
+async fn dir_command(
+  flags: Flags,
+  command: DirCommand,
+) -> Result<(), AnyError> {
+  if !flags.unstable {
+    exit_unstable("dir");
+  }
+
+  let global_state = GlobalState::new(flags)?;
+  let http_cache = &global_state.file_fetcher.http_cache;
+  let gen_cache = &global_state.dir.gen_cache;
+  match command {
+    DirCommand::List { json } => {
+      let modules = cache_dir::list(http_cache);
+      if json {
+        return write_json_to_stdout(&json!(modules));
+      }
+      let total: u64 = modules.iter().map(|m| m.size).sum();
+      let mut out = String::new();
+      for module in &modules {
+        out.push_str(&format!("{}\n", module));
+      }
+      out.push_str(&format!(
+        "{} {} remote modules ({})\n",
+        colors::bold("total:"),
+        modules.len(),
+        info::human_size(total as f64)
+      ));
+      write_to_stdout_ignore_sigpipe(out.as_bytes()).map_err(AnyError::from)
+    }
+    DirCommand::Headers { url } => {
+      let url = Url::parse(&url)?;
+      let metadata = http_cache
+        .get_metadata(&url)
+        .map_err(|_| generic_error(format!("{} is not in the cache", url)))?;
+      let mut headers: Vec<_> = metadata.headers.iter().collect();
+      headers.sort();
+      let mut out = String::new();
+      for (name, value) in headers {
+        out.push_str(&format!("{}: {}\n", name, value));
+      }
+      write_to_stdout_ignore_sigpipe(out.as_bytes()).map_err(AnyError::from)
+    }
+    DirCommand::Prune { roots, dry_run } => {
+      let handler = Rc::new(RefCell::new(FetchHandler::new(
+        &global_state,
+        Permissions::allow_all(),
+      )?));
+      let mut builder =
+        GraphBuilder2::new(handler, global_state.maybe_import_map.clone());
+      for root in roots {
+        let specifier = ModuleSpecifier::resolve_url_or_path(&root)?;
+        builder.insert(&specifier).await?;
+      }
+      let graph = builder.get_graph(&None)?;
+      let specifiers = graph
+        .get_sources()
+        .into_iter()
+        .map(|(specifier, _, _)| specifier.as_url().clone());
+      let reachable = cache_dir::with_redirects(http_cache, specifiers);
+      let removed =
+        cache_dir::prune(http_cache, gen_cache, &reachable, dry_run)?;
+      for url in &removed {
+        info!("{} {}", colors::red("Remove"), url);
+      }
+      info!(
+        "{} {} remote modules{}",
+        colors::green("Pruned"),
+        removed.len(),
+        if dry_run { " (dry run)" } else { "" }
+      );
+      Ok(())
+    }
+    DirCommand::ClearEmit { files } => {
+      for file in files {
+        let specifier = ModuleSpecifier::resolve_url_or_path(&file)?;
+        let count = cache_dir::clear_emit(gen_cache, specifier.as_url())?;
+        info!(
+          "{} {} emitted files of {}",
+          colors::green("Remove"),
+          count,
+          specifier
+        );
+      }
+      Ok(())
+    }
+  }
+}
+
+async fn vendor_command(
+  flags: Flags,
+  files: Vec<String>,
+  output: PathBuf,
+  force: bool,
+) -> Result<(), AnyError> {
+  if !flags.unstable {
+    exit_unstable("vendor");
+  }
+
+  let replace_output = output.is_dir() && output.read_dir()?.next().is_some();
+  if replace_output {
+    if !force {
+      return Err(generic_error(format!(
+        "Output directory {:?} is not empty, use --force to replace it",
+        output
+      )));
+    }
+    // Only a directory written by a previous run is removed, not another one
+    // given by mistake.
+    if !output.join(vendor::IMPORT_MAP_FILE).is_file() {
+      return Err(generic_error(format!(
+        "Output directory {:?} has no {}, refusing to replace it",
+        output,
+        vendor::IMPORT_MAP_FILE
+      )));
+    }
+  }
+
+  let global_state = GlobalState::new(flags)?;
+  let handler = Rc::new(RefCell::new(FetchHandler::new(
+    &global_state,
+    Permissions::allow_all(),
+  )?));
+  let mut builder =
+    GraphBuilder2::new(handler, global_state.maybe_import_map.clone());
+  for file in files {
+    let specifier = ModuleSpecifier::resolve_url_or_path(&file)?;
+    builder.insert(&specifier).await?;
+  }
+  let graph = builder.get_graph(&global_state.lockfile)?;
+  if let Some(ref lockfile) = global_state.lockfile {
+    let g = lockfile.lock().unwrap();
+    g.write()?;
+  }
+
+  // The previous modules are kept until the new ones could all be loaded.
+  if replace_output {
+    std::fs::remove_dir_all(&output)?;
+  }
+  let count = vendor::vendor(&graph, &output)?;
+  info!(
+    "{} {} remote modules to {:?}",
+    colors::green("Vendor"),
+    count,
+    output
+  );
+  info!(
+    "Use the import map with --importmap={:?}",
+    output.join(vendor::IMPORT_MAP_FILE)
+  );
+  Ok(())
+}
+
 async fn eval_command(
   flags: Flags,
   code: String,
@@ -531,6 +697,7 @@ async fn run_command(flags: Flags, script: String) -> Result<(), AnyError> {
   Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn test_command(
   docs: bool,
   flags: Flags,
@@ -539,6 +706,8 @@ async fn test_command(
   quiet: bool,
   allow_none: bool,
   filter: Option<String>,
+  reporter: String,
+  jobs: usize,
 ) -> Result<(), AnyError> {
   let global_state = GlobalState::new(flags.clone())?;
   let cwd = std::env::current_dir().expect("No current directory");
@@ -570,12 +739,15 @@ async fn test_command(
       test_modules.clone(),
       fail_fast,
       quiet,
-      filter,
+      filter.clone(),
+      &reporter,
     )
   } else {
     let jsdocs =
       doctest_runner::parse_jsdocs(&test_modules, flags.clone()).await?;
-    doctest_runner::prepare_doctests(jsdocs, fail_fast, quiet, filter)?
+    doctest_runner::prepare_doctests(
+      jsdocs, fail_fast, quiet, filter, &reporter,
+    )?
   };
 
   let main_module =
@@ -595,6 +767,22 @@ async fn test_command(
     .file_fetcher
     .save_source_file_in_cache(&main_module, source_file);
 
+  if jobs > 1 {
+    // Type check all test modules once, before they are spread over threads.
+    worker.preload_module(&main_module).await?;
+    return test_runner::run_tests_in_parallel(
+      &global_state,
+      &mut worker,
+      test_modules,
+      jobs,
+      fail_fast,
+      quiet,
+      filter,
+      &reporter,
+    )
+    .await;
+  }
+
   let mut maybe_coverage_collector = if flags.coverage {
     let inspector = worker
       .inspector
@@ -623,15 +811,99 @@ async fn test_command(
     let filtered_coverages =
       coverage::filter_script_coverages(coverages, test_file_url, test_modules);
 
+    if let Some(coverage_dir) = flags.coverage_dir.as_ref() {
+      coverage::write_coverages(
+        &PathBuf::from(coverage_dir),
+        &filtered_coverages,
+        &global_state.ts_compiler,
+      )?;
+    }
+
     let mut coverage_reporter = PrettyCoverageReporter::new(quiet);
     for coverage in filtered_coverages {
       coverage_reporter.visit_coverage(&coverage);
     }
+    coverage_reporter.print_summary();
+
+    if let Some(threshold) = flags.coverage_threshold.as_ref() {
+      coverage_reporter.total().check_threshold(threshold)?;
+    }
   }
 
   Ok(())
 }
 
+async fn coverage_command(
+  flags: Flags,
+  dir: PathBuf,
+  include: Vec<String>,
+  exclude: Vec<String>,
+  lcov: bool,
+  html: Option<PathBuf>,
+) -> Result<(), AnyError> {
+  if !flags.unstable {
+    exit_unstable("coverage");
+  }
+
+  let global_state = GlobalState::new(flags)?;
+  let coverages = coverage::load_coverages(&dir)?;
+  let coverages = coverage::merge_coverages(coverages);
+  let coverages =
+    coverage::filter_coverages_by_url(coverages, &include, &exclude)?;
+  let source_map_getter = &global_state.ts_compiler;
+
+  if lcov {
+    let mut coverage_reporter = LcovCoverageReporter::new();
+    for coverage in &coverages {
+      let url = &coverage.script_coverage.url;
+      coverage_reporter.visit_coverage(
+        coverage,
+        coverage::get_source_map(source_map_getter, url),
+      );
+    }
+    write_to_stdout_ignore_sigpipe(coverage_reporter.into_string().as_bytes())
+      .map_err(AnyError::from)
+  } else if let Some(html_dir) = html {
+    let mut coverage_reporter = HtmlCoverageReporter::new(html_dir)?;
+    for coverage in &coverages {
+      let url = &coverage.script_coverage.url;
+      let maybe_source_map = coverage::get_source_map(source_map_getter, url);
+      // The report shows the original source the source map points into.
+      let maybe_original_source = if maybe_source_map.is_some() {
+        ModuleSpecifier::resolve_url(url)
+          .ok()
+          .and_then(|specifier| {
+            global_state
+              .file_fetcher
+              .fetch_cached_source_file(&specifier, Permissions::allow_all())
+          })
+      } else {
+        None
+      };
+      match maybe_original_source {
+        Some(source_file) => coverage_reporter.visit_coverage(
+          coverage,
+          maybe_source_map,
+          &source_file.source_code,
+        )?,
+        None => coverage_reporter.visit_coverage(
+          coverage,
+          None,
+          &coverage.script_source,
+        )?,
+      }
+    }
+    coverage_reporter.finish()
+  } else {
+    let mut coverage_reporter = PrettyCoverageReporter::new(false);
+    for coverage in &coverages {
+      coverage_reporter.visit_coverage(coverage);
+    }
+    coverage_reporter.print_summary();
+    Ok(())
+  }
+}
+
 pub fn main() {
   #[cfg(windows)]
   colors::enable_ansi(); // For Windows 10
@@ -695,6 +967,20 @@ pub fn main() {
   })
   .init();
 
+  if let Err(err) = permission_audit::init(
+    flags.permission_audit.as_deref(),
+    flags.permission_audit_summary,
+  ) {
+    eprintln!("{}: {}", colors::red_bold("error"), err);
+    std::process::exit(1);
+  }
+  if let Err(err) =
+    permission_policy::init_saved_answers(flags.save_permissions.as_deref())
+  {
+    eprintln!("{}: {}", colors::red_bold("error"), err);
+    std::process::exit(1);
+  }
+
   let fut = match flags.clone().subcommand {
     DenoSubcommand::Bundle {
       source_file,
@@ -714,6 +1000,18 @@ pub fn main() {
     DenoSubcommand::Cache { files } => {
       cache_command(flags, files).boxed_local()
     }
+    DenoSubcommand::Coverage {
+      dir,
+      include,
+      exclude,
+      lcov,
+      html,
+    } => {
+      coverage_command(flags, dir, include, exclude, lcov, html).boxed_local()
+    }
+    DenoSubcommand::Dir { command } => {
+      dir_command(flags, command).boxed_local()
+    }
     DenoSubcommand::Fmt {
       check,
       files,
@@ -746,10 +1044,13 @@ pub fn main() {
       include,
       allow_none,
       filter,
-    } => {
-      test_command(docs, flags, include, fail_fast, quiet, allow_none, filter)
-        .boxed_local()
-    }
+      reporter,
+      jobs,
+    } => test_command(
+      docs, flags, include, fail_fast, quiet, allow_none, filter, reporter,
+      jobs,
+    )
+    .boxed_local(),
     DenoSubcommand::Completions { buf } => {
       if let Err(e) = write_to_stdout_ignore_sigpipe(&buf) {
         eprintln!("{}", e);
@@ -774,10 +1075,16 @@ pub fn main() {
     } => {
       upgrade_command(dry_run, force, version, output, ca_file).boxed_local()
     }
+    DenoSubcommand::Vendor {
+      files,
+      output,
+      force,
+    } => vendor_command(flags, files, output, force).boxed_local(),
     _ => unreachable!(),
   };
 
   let result = tokio_util::run_basic(fut);
+  permission_audit::print_summary();
   if let Err(err) = result {
     let msg = format!("{}: {}", colors::red_bold("error"), err.to_string(),);
     eprintln!("{}", msg);
@@ -66,8 +66,6 @@ pub enum GraphError {
   InvalidDowngrade(ModuleSpecifier, Location),
   /// A remote module is trying to import a local module.
   InvalidLocalImport(ModuleSpecifier, Location),
-  /// A remote module is trying to import a local module.
-  InvalidSource(ModuleSpecifier, String),
   /// A module specifier could not be resolved for a given import.
   InvalidSpecifier(String, Location),
   /// An unexpected dependency was requested for a module.
@@ -86,7 +84,6 @@ impl fmt::Display for GraphError {
     match self {
       InvalidDowngrade(ref specifier, ref location) => write!(f, "Modules imported via https are not allowed to import http modules.\n  Importing: {}\n    at {}:{}:{}", specifier, location.filename, location.line, location.col),
       InvalidLocalImport(ref specifier, ref location) => write!(f, "Remote modules are not allowed to import local modules.\n  Importing: {}\n    at {}:{}:{}", specifier, location.filename, location.line, location.col),
-      InvalidSource(ref specifier, ref lockfile) => write!(f, "The source code is invalid, as it does not match the expected hash in the lock file.\n  Specifier: {}\n  Lock file: {}", specifier, lockfile),
       InvalidSpecifier(ref specifier, ref location) => write!(f, "Unable to resolve dependency specifier.\n  Specifier: {}\n    at {}:{}:{}", specifier, location.filename, location.line, location.col),
       MissingDependency(ref referrer, specifier) => write!(
         f,
@@ -238,6 +235,11 @@ impl Module {
   }
 
   pub fn parse(&mut self) -> Result<(), AnyError> {
+    // JSON modules have no dependencies and are not transpiled.
+    if self.media_type == MediaType::Json {
+      return Ok(());
+    }
+
     let parsed_module = parse(&self.specifier, &self.source, &self.media_type)?;
 
     // parse out any triple slash references
@@ -403,6 +405,8 @@ pub struct Graph2 {
   build_info: BuildInfoMap,
   handler: Rc<RefCell<dyn SpecifierHandler>>,
   modules: HashMap<ModuleSpecifier, Module>,
+  /// Modules that were redirected, mapped to the URL they were redirected to.
+  redirects: HashMap<ModuleSpecifier, ModuleSpecifier>,
   roots: Vec<ModuleSpecifier>,
 }
 
@@ -417,6 +421,7 @@ impl Graph2 {
       build_info: HashMap::new(),
       handler,
       modules: HashMap::new(),
+      redirects: HashMap::new(),
       roots: Vec::new(),
     }
   }
@@ -462,20 +467,46 @@ impl Graph2 {
   ) -> Result<(), AnyError> {
     if let Some(lf) = maybe_lockfile {
       let mut lockfile = lf.lock().unwrap();
-      for (ms, module) in self.modules.iter() {
-        let specifier = module.specifier.to_string();
-        let valid = lockfile.check_or_insert(&specifier, &module.source);
-        if !valid {
-          return Err(
-            InvalidSource(ms.clone(), lockfile.filename.clone()).into(),
-          );
+      // A redirected module is locked as the redirect and the source of the
+      // URL it was redirected to.
+      let mut modules: Vec<(&str, Option<&str>, &str)> = vec![];
+      for module in self.modules.values() {
+        match self.redirects.get(&module.specifier) {
+          Some(redirect) => {
+            modules.push((
+              module.specifier.as_str(),
+              Some(redirect.as_str()),
+              "",
+            ));
+            modules.push((redirect.as_str(), None, module.source.as_str()));
+          }
+          None => modules.push((
+            module.specifier.as_str(),
+            None,
+            module.source.as_str(),
+          )),
         }
       }
+      lockfile.check_or_insert_modules(modules)?;
     }
 
     Ok(())
   }
 
+  /// Return the specifier, media type and source of every module in the
+  /// graph, ordered by specifier.
+  pub fn get_sources(&self) -> Vec<(&ModuleSpecifier, MediaType, &str)> {
+    let mut sources: Vec<(&ModuleSpecifier, MediaType, &str)> = self
+      .modules
+      .values()
+      .map(|module| {
+        (&module.specifier, module.media_type, module.source.as_str())
+      })
+      .collect();
+    sources.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    sources
+  }
+
   /// Transpile (only transform) the graph, updating any emitted modules
   /// with the specifier handler.  The result contains any performance stats
   /// from the compiler and optionally any user provided configuration compiler
@@ -521,8 +552,10 @@ impl Graph2 {
       // we start to support other methods on the graph.  Especially managing
       // the dirty state is something the module itself should "own".
 
-      // if the module is a Dts file we should skip it
-      if module.media_type == MediaType::Dts {
+      // if the module is a Dts file or a JSON module we should skip it
+      if module.media_type == MediaType::Dts
+        || module.media_type == MediaType::Json
+      {
         continue;
       }
       // if we don't have check_js enabled, we won't touch non TypeScript
@@ -605,6 +638,12 @@ impl GraphBuilder2 {
   /// module into the graph.
   fn visit(&mut self, cached_module: CachedModule) -> Result<(), AnyError> {
     let specifier = cached_module.specifier.clone();
+    if let Some(redirect) = &cached_module.maybe_redirect {
+      self
+        .graph
+        .redirects
+        .insert(specifier.clone(), redirect.clone());
+    }
     let mut module =
       Module::new(specifier.clone(), self.maybe_import_map.clone());
     module.hydrate(cached_module);
@@ -684,6 +723,7 @@ mod tests {
   use super::*;
 
   use deno_core::futures::future;
+  use deno_core::serde_json;
   use std::env;
   use std::fs;
   use std::path::PathBuf;
@@ -699,6 +739,7 @@ mod tests {
     pub build_info_calls: Vec<(ModuleSpecifier, EmitType, String)>,
     pub cache_calls: Vec<(ModuleSpecifier, EmitType, String, Option<String>)>,
     pub deps_calls: Vec<(ModuleSpecifier, DependencyMap)>,
+    pub redirects: HashMap<ModuleSpecifier, ModuleSpecifier>,
     pub types_calls: Vec<(ModuleSpecifier, String)>,
     pub version_calls: Vec<(ModuleSpecifier, String)>,
   }
@@ -708,7 +749,10 @@ mod tests {
       &self,
       specifier: ModuleSpecifier,
     ) -> Result<CachedModule, AnyError> {
-      let specifier_text = specifier
+      let maybe_redirect = self.redirects.get(&specifier).cloned();
+      let specifier_text = maybe_redirect
+        .as_ref()
+        .unwrap_or(&specifier)
         .to_string()
         .replace(":///", "_")
         .replace("://", "_")
@@ -726,11 +770,13 @@ mod tests {
           "tsx" => MediaType::TSX,
           "js" => MediaType::JavaScript,
           "jsx" => MediaType::JSX,
+          "json" => MediaType::Json,
           _ => MediaType::Unknown,
         };
       let source = fs::read_to_string(specifier_path)?;
 
       Ok(CachedModule {
+        maybe_redirect,
         source,
         specifier,
         media_type,
@@ -945,6 +991,35 @@ mod tests {
     assert_eq!(h.deps_calls[6].1.len(), 0, "should have no dependencies");
   }
 
+  #[tokio::test]
+  async fn test_graph_transpile_json() {
+    let c = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let fixtures = c.join("tests/module_graph");
+    let handler = Rc::new(RefCell::new(MockSpecifierHandler {
+      fixtures,
+      ..MockSpecifierHandler::default()
+    }));
+    let mut builder = GraphBuilder2::new(handler.clone(), None);
+    let specifier =
+      ModuleSpecifier::resolve_url_or_path("file:///tests/json_import.ts")
+        .expect("could not resolve module");
+    builder
+      .insert(&specifier)
+      .await
+      .expect("module not inserted");
+    let mut graph = builder.get_graph(&None).expect("could not get graph");
+    graph.transpile(TranspileOptions::default()).unwrap();
+    let h = handler.borrow();
+    assert_eq!(h.cache_calls.len(), 1, "only one file should be emitted");
+    assert_eq!(h.cache_calls[0].0, specifier);
+    assert_eq!(h.deps_calls.len(), 2);
+    assert_eq!(
+      h.deps_calls[1].0,
+      ModuleSpecifier::resolve_url_or_path("file:///tests/data.json").unwrap()
+    );
+    assert_eq!(h.deps_calls[1].1.len(), 0, "should have no dependencies");
+  }
+
   #[tokio::test]
   async fn test_graph_transpile_user_config() {
     let c = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
@@ -988,7 +1063,7 @@ mod tests {
     let fixtures = c.join("tests/module_graph");
     let lockfile_path = fixtures.join("lockfile.json");
     let lockfile =
-      Lockfile::new(lockfile_path.to_string_lossy().to_string(), false)
+      Lockfile::new(lockfile_path.to_string_lossy().to_string(), false, false)
         .expect("could not load lockfile");
     let maybe_lockfile = Some(Mutex::new(lockfile));
     let handler = Rc::new(RefCell::new(MockSpecifierHandler {
@@ -1008,13 +1083,56 @@ mod tests {
       .expect("could not get graph");
   }
 
+  #[tokio::test]
+  async fn test_graph_with_lockfile_redirect() {
+    let c = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let fixtures = c.join("tests/module_graph");
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let lockfile_path = temp_dir.path().join("lock.json");
+    let lockfile =
+      Lockfile::new(lockfile_path.to_string_lossy().to_string(), true, false)
+        .expect("could not load lockfile");
+    let maybe_lockfile = Some(Mutex::new(lockfile));
+    let specifier =
+      ModuleSpecifier::resolve_url_or_path("https://deno.land/x/latest/a.ts")
+        .expect("could not resolve module");
+    let redirect =
+      ModuleSpecifier::resolve_url_or_path("https://deno.land/x/a.ts")
+        .expect("could not resolve module");
+    let handler = Rc::new(RefCell::new(MockSpecifierHandler {
+      fixtures,
+      redirects: vec![(specifier.clone(), redirect)].into_iter().collect(),
+      ..MockSpecifierHandler::default()
+    }));
+    let mut builder = GraphBuilder2::new(handler.clone(), None);
+    builder
+      .insert(&specifier)
+      .await
+      .expect("module not inserted");
+    builder
+      .get_graph(&maybe_lockfile)
+      .expect("could not get graph");
+    maybe_lockfile.unwrap().lock().unwrap().write().unwrap();
+    let lockfile_json: serde_json::Value =
+      serde_json::from_str(&fs::read_to_string(&lockfile_path).unwrap())
+        .unwrap();
+    assert_eq!(
+      lockfile_json["redirects"]["https://deno.land/x/latest/a.ts"],
+      "https://deno.land/x/a.ts"
+    );
+    assert!(lockfile_json["remote"]["https://deno.land/x/a.ts"].is_string());
+    assert!(
+      lockfile_json["remote"]["https://deno.land/x/latest/a.ts"].is_null()
+    );
+  }
+
   #[tokio::test]
   async fn test_graph_with_lockfile_fail() {
     let c = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
     let fixtures = c.join("tests/module_graph");
     let lockfile_path = fixtures.join("lockfile_fail.json");
     let lockfile =
-      Lockfile::new(lockfile_path.to_string_lossy().to_string(), false)
+      Lockfile::new(lockfile_path.to_string_lossy().to_string(), false, false)
         .expect("could not load lockfile");
     let maybe_lockfile = Some(Mutex::new(lockfile));
     let handler = Rc::new(RefCell::new(MockSpecifierHandler {
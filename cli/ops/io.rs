@@ -254,8 +254,9 @@ pub fn op_read(
 
   if is_sync {
     MinimalOp::Sync({
+      let mut state = state.borrow_mut();
       // First we look up the rid in the resource table.
-      std_file_resource(&mut state.borrow_mut(), rid as u32, move |r| match r {
+      std_file_resource(&mut state, rid as u32, move |r| match r {
         Ok(std_file) => {
           use std::io::Read;
           std_file
@@ -265,6 +266,10 @@ pub fn op_read(
         }
         Err(_) => Err(type_error("sync read not allowed on this resource")),
       })
+      .map(|nread| {
+        state.resource_table.record_read(rid as u32, nread as usize);
+        nread
+      })
     })
   } else {
     let mut zero_copy = zero_copy[0].clone();
@@ -290,6 +295,7 @@ pub fn op_read(
             return Poll::Pending;
           }
         }?;
+        state.resource_table.record_read(rid as u32, nread);
         Poll::Ready(Ok(nread as i32))
       })
       .boxed_local(),
@@ -372,8 +378,9 @@ pub fn op_write(
 
   if is_sync {
     MinimalOp::Sync({
+      let mut state = state.borrow_mut();
       // First we look up the rid in the resource table.
-      std_file_resource(&mut state.borrow_mut(), rid as u32, move |r| match r {
+      std_file_resource(&mut state, rid as u32, move |r| match r {
         Ok(std_file) => {
           use std::io::Write;
           std_file
@@ -383,6 +390,12 @@ pub fn op_write(
         }
         Err(_) => Err(type_error("sync read not allowed on this resource")),
       })
+      .map(|nwritten| {
+        state
+          .resource_table
+          .record_write(rid as u32, nwritten as usize);
+        nwritten
+      })
     })
   } else {
     let zero_copy = zero_copy[0].clone();
@@ -397,6 +410,10 @@ pub fn op_write(
           resource_holder.resource.poll_write(cx, &zero_copy)
         })
         .await?;
+        state
+          .borrow_mut()
+          .resource_table
+          .record_write(rid as u32, nwritten);
 
         // TODO(bartlomieju): this step was added during upgrade to Tokio 0.2
         // and the reasons for the need to explicitly flush are not fully known.
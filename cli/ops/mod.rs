@@ -19,6 +19,7 @@ pub mod random;
 pub mod runtime;
 pub mod runtime_compiler;
 pub mod signal;
+pub mod testing;
 pub mod timers;
 pub mod tls;
 pub mod tty;
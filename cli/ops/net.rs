@@ -200,12 +200,14 @@ async fn op_datagram_send(
       transport,
       transport_args: ArgsEnum::Ip(args),
     } if transport == "udp" => {
-      {
+      let addr = {
         let s = state.borrow();
-        s.borrow::<Permissions>()
-          .check_net(&args.hostname, args.port)?;
-      }
-      let addr = resolve_addr(&args.hostname, args.port)?;
+        let permissions = s.borrow::<Permissions>();
+        permissions.check_net_lookup(&args.hostname, args.port)?;
+        let addr = resolve_addr(&args.hostname, args.port)?;
+        permissions.check_net_addr(&args.hostname, &addr)?;
+        addr
+      };
       poll_fn(move |cx| {
         let mut state = state.borrow_mut();
         let resource = state
@@ -266,13 +268,14 @@ async fn op_connect(
       transport,
       transport_args: ArgsEnum::Ip(args),
     } if transport == "tcp" => {
-      {
+      let addr = {
         let state_ = state.borrow();
-        state_
-          .borrow::<Permissions>()
-          .check_net(&args.hostname, args.port)?;
-      }
-      let addr = resolve_addr(&args.hostname, args.port)?;
+        let permissions = state_.borrow::<Permissions>();
+        permissions.check_net_lookup(&args.hostname, args.port)?;
+        let addr = resolve_addr(&args.hostname, args.port)?;
+        permissions.check_net_addr(&args.hostname, &addr)?;
+        addr
+      };
       let tcp_stream = TcpStream::connect(&addr).await?;
       let local_addr = tcp_stream.local_addr()?;
       let remote_addr = tcp_stream.peer_addr()?;
@@ -502,9 +505,10 @@ fn op_listen(
         if transport == "udp" {
           super::check_unstable(state, "Deno.listenDatagram");
         }
-        permissions.check_net(&args.hostname, args.port)?;
+        permissions.check_net_lookup(&args.hostname, args.port)?;
       }
       let addr = resolve_addr(&args.hostname, args.port)?;
+      permissions.check_net_addr(&args.hostname, &addr)?;
       let (rid, local_addr) = if transport == "tcp" {
         listen_tcp(state, addr)?
       } else {
@@ -22,6 +22,8 @@ struct PermissionArgs {
   name: String,
   url: Option<String>,
   path: Option<String>,
+  command: Option<String>,
+  variable: Option<String>,
 }
 
 pub fn op_query_permission(
@@ -36,8 +38,8 @@ pub fn op_query_permission(
     "read" => permissions.query_read(&path.as_deref().map(Path::new)),
     "write" => permissions.query_write(&path.as_deref().map(Path::new)),
     "net" => permissions.query_net_url(&args.url.as_deref())?,
-    "env" => permissions.query_env(),
-    "run" => permissions.query_run(),
+    "env" => permissions.query_env(&args.variable.as_deref()),
+    "run" => permissions.query_run(&args.command.as_deref()),
     "plugin" => permissions.query_plugin(),
     "hrtime" => permissions.query_hrtime(),
     n => {
@@ -62,8 +64,8 @@ pub fn op_revoke_permission(
     "read" => permissions.revoke_read(&path.as_deref().map(Path::new)),
     "write" => permissions.revoke_write(&path.as_deref().map(Path::new)),
     "net" => permissions.revoke_net(&args.url.as_deref())?,
-    "env" => permissions.revoke_env(),
-    "run" => permissions.revoke_run(),
+    "env" => permissions.revoke_env(&args.variable.as_deref()),
+    "run" => permissions.revoke_run(&args.command.as_deref()),
     "plugin" => permissions.revoke_plugin(),
     "hrtime" => permissions.revoke_hrtime(),
     n => {
@@ -88,8 +90,8 @@ pub fn op_request_permission(
     "read" => permissions.request_read(&path.as_deref().map(Path::new)),
     "write" => permissions.request_write(&path.as_deref().map(Path::new)),
     "net" => permissions.request_net(&args.url.as_deref())?,
-    "env" => permissions.request_env(),
-    "run" => permissions.request_run(),
+    "env" => permissions.request_env(&args.variable.as_deref()),
+    "run" => permissions.request_run(&args.command.as_deref()),
     "plugin" => permissions.request_plugin(),
     "hrtime" => permissions.request_hrtime(),
     n => {
@@ -1,9 +1,14 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use super::io::{std_file_resource, StreamResource, StreamResourceHolder};
+use crate::permissions::env_key;
+use crate::permissions::is_command_path;
+use crate::permissions::resolve_command;
 use crate::permissions::Permissions;
 use crate::signal::kill;
 use deno_core::error::bad_resource_id;
+#[cfg(not(unix))]
+use deno_core::error::not_supported;
 use deno_core::error::type_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::poll_fn;
@@ -16,7 +21,14 @@ use deno_core::OpState;
 use deno_core::ZeroCopyBuf;
 use serde::Deserialize;
 use std::cell::RefCell;
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::process::Command;
 
 #[cfg(unix)]
@@ -38,6 +50,112 @@ fn clone_file(
   })
 }
 
+/// Returns a stdio stream of a child process for the resource `rid`. A file
+/// is shared with the child, while a socket or a pipe of another child
+/// process is moved to it: its rid is added to `moved_rids`, for the resource
+/// to be closed once the child is spawned so that, like in a shell pipeline,
+/// the child is the only one to read or write it.
+fn resource_stdio(
+  state: &mut OpState,
+  rid: u32,
+  moved_rids: &mut Vec<u32>,
+) -> Result<std::process::Stdio, AnyError> {
+  let is_file = match state.resource_table.get::<StreamResourceHolder>(rid) {
+    Some(holder) => match holder.resource {
+      StreamResource::FsFile(_) => true,
+      StreamResource::TcpStream(Some(_))
+      | StreamResource::ChildStdin(_)
+      | StreamResource::ChildStdout(_)
+      | StreamResource::ChildStderr(_) => false,
+      #[cfg(not(windows))]
+      StreamResource::UnixStream(_) => false,
+      _ => return Err(type_error("Invalid resource for stdio")),
+    },
+    None => return Err(bad_resource_id()),
+  };
+  if is_file {
+    return Ok(clone_file(state, rid)?.into());
+  }
+  let holder = state
+    .resource_table
+    .get::<StreamResourceHolder>(rid)
+    .unwrap();
+  let child_stdio = stream_stdio(&holder.resource)?;
+  if !moved_rids.contains(&rid) {
+    moved_rids.push(rid);
+  }
+  Ok(child_stdio)
+}
+
+#[cfg(unix)]
+fn stream_fd(resource: &StreamResource) -> std::os::unix::io::RawFd {
+  use std::os::unix::io::AsRawFd;
+
+  match resource {
+    StreamResource::TcpStream(Some(stream)) => stream.as_raw_fd(),
+    StreamResource::UnixStream(stream) => stream.as_raw_fd(),
+    StreamResource::ChildStdin(stdin) => stdin.as_raw_fd(),
+    StreamResource::ChildStdout(stdout) => stdout.as_raw_fd(),
+    StreamResource::ChildStderr(stderr) => stderr.as_raw_fd(),
+    _ => unreachable!(),
+  }
+}
+
+#[cfg(unix)]
+fn set_nonblocking(
+  resource: &StreamResource,
+  nonblocking: bool,
+) -> Result<(), AnyError> {
+  use nix::fcntl::{fcntl, FcntlArg, OFlag};
+
+  let fd = stream_fd(resource);
+  let mut flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+  flags.set(OFlag::O_NONBLOCK, nonblocking);
+  fcntl(fd, FcntlArg::F_SETFL(flags))?;
+  Ok(())
+}
+
+#[cfg(unix)]
+fn stream_stdio(
+  resource: &StreamResource,
+) -> Result<std::process::Stdio, AnyError> {
+  use std::os::unix::io::FromRawFd;
+
+  // The stream closes its descriptor when it is dropped, so the child gets a
+  // duplicate. The event loop made the descriptor non-blocking, which most
+  // programs don't expect of their stdio; the duplicate shares that flag, so
+  // it has to be set back if the child isn't spawned.
+  let fd = nix::unistd::dup(stream_fd(resource))?;
+  let child_stdio = unsafe { std::process::Stdio::from_raw_fd(fd) };
+  set_nonblocking(resource, false)?;
+  Ok(child_stdio)
+}
+
+#[cfg(not(unix))]
+fn stream_stdio(
+  _resource: &StreamResource,
+) -> Result<std::process::Stdio, AnyError> {
+  Err(not_supported())
+}
+
+/// Hands the streams that were to be moved to a child back to the event loop,
+/// when the child could not be spawned.
+#[cfg(unix)]
+fn restore_streams(state: &OpState, moved_rids: &[u32]) {
+  for rid in moved_rids {
+    let holder = state
+      .resource_table
+      .get::<StreamResourceHolder>(*rid)
+      .unwrap();
+    if let Err(err) = set_nonblocking(&holder.resource, true) {
+      debug!("Failed to restore stream {}: {}", rid, err);
+    }
+  }
+}
+
+#[cfg(not(unix))]
+fn restore_streams(_state: &OpState, _moved_rids: &[u32]) {}
+
 fn subprocess_stdio_map(s: &str) -> Result<std::process::Stdio, AnyError> {
   match s {
     "inherit" => Ok(std::process::Stdio::inherit()),
@@ -53,16 +171,135 @@ struct RunArgs {
   cmd: Vec<String>,
   cwd: Option<String>,
   env: Vec<(String, String)>,
+  #[serde(default)]
+  clear_env: bool,
   stdin: String,
   stdout: String,
   stderr: String,
   stdin_rid: u32,
   stdout_rid: u32,
   stderr_rid: u32,
+  uid: Option<u32>,
+  gid: Option<u32>,
+  pgid: Option<i32>,
+  #[serde(default)]
+  setsid: bool,
+  rlimits: Option<RunLimits>,
+  /// In milliseconds.
+  timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RunLimits {
+  /// CPU time, in seconds.
+  cpu: Option<u64>,
+  /// Size of the address space, in bytes.
+  memory: Option<u64>,
+  open_files: Option<u64>,
+}
+
+/// Returns the PATH that the child process is run with.
+fn child_path(run_args: &RunArgs) -> Option<OsString> {
+  let path_key = env_key("PATH");
+  let env = &run_args.env;
+  match env.iter().rev().find(|(key, _)| env_key(key) == path_key) {
+    Some((_, value)) => Some(value.into()),
+    None if run_args.clear_env => None,
+    None => env::var_os("PATH"),
+  }
 }
 
 struct ChildResource {
   child: tokio::process::Child,
+  /// Set once the child has been waited for, after which its pid may be
+  /// reused and must not be killed anymore.
+  exited: Arc<AtomicBool>,
+}
+
+impl Drop for ChildResource {
+  fn drop(&mut self) {
+    self.exited.store(true, Ordering::SeqCst);
+  }
+}
+
+#[cfg(unix)]
+const SIGKILL: i32 = libc::SIGKILL;
+#[cfg(not(unix))]
+const SIGKILL: i32 = 9;
+
+#[cfg(not(unix))]
+fn set_unix_options(
+  _c: &mut std::process::Command,
+  run_args: &RunArgs,
+) -> Result<(), AnyError> {
+  if run_args.uid.is_some()
+    || run_args.gid.is_some()
+    || run_args.pgid.is_some()
+    || run_args.setsid
+    || run_args.rlimits.is_some()
+  {
+    return Err(not_supported());
+  }
+  Ok(())
+}
+
+/// Applies the options of `Deno.run` that are specific to Unix.
+#[cfg(unix)]
+fn set_unix_options(
+  c: &mut std::process::Command,
+  run_args: &RunArgs,
+) -> Result<(), AnyError> {
+  use std::os::unix::process::CommandExt;
+
+  if let Some(uid) = run_args.uid {
+    c.uid(uid);
+  }
+  if let Some(gid) = run_args.gid {
+    c.gid(gid);
+  }
+  if run_args.setsid && run_args.pgid.is_some() {
+    return Err(type_error("setsid and pgid can't be used together"));
+  }
+  let setsid = run_args.setsid;
+  let pgid = run_args.pgid;
+  let limits = run_args.rlimits.as_ref().map(|limits| {
+    [
+      (libc::RLIMIT_CPU, limits.cpu),
+      (libc::RLIMIT_AS, limits.memory),
+      (libc::RLIMIT_NOFILE, limits.open_files),
+    ]
+  });
+  if !setsid && pgid.is_none() && limits.is_none() {
+    return Ok(());
+  }
+  // The closure runs in the child, between fork and exec, so it may only make
+  // async-signal-safe calls.
+  unsafe {
+    c.pre_exec(move || {
+      if setsid && libc::setsid() == -1 {
+        return Err(std::io::Error::last_os_error());
+      }
+      if let Some(pgid) = pgid {
+        if libc::setpgid(0, pgid) == -1 {
+          return Err(std::io::Error::last_os_error());
+        }
+      }
+      for (resource, limit) in limits.iter().flatten() {
+        if let Some(limit) = limit {
+          let rlimit = libc::rlimit {
+            rlim_cur: *limit as libc::rlim_t,
+            rlim_max: *limit as libc::rlim_t,
+          };
+          if libc::setrlimit(*resource, &rlimit) == -1 {
+            return Err(std::io::Error::last_os_error());
+          }
+        }
+      }
+      Ok(())
+    });
+  }
+  Ok(())
 }
 
 fn op_run(
@@ -71,49 +308,75 @@ fn op_run(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
   let run_args: RunArgs = serde_json::from_value(args)?;
-  state.borrow::<Permissions>().check_run()?;
+  let cmd = run_args
+    .cmd
+    .get(0)
+    .ok_or_else(|| type_error("cmd must not be empty"))?;
+  let cwd = run_args.cwd.as_ref().map(Path::new);
+  {
+    let permissions = state.borrow::<Permissions>();
+    permissions.check_run(cmd, cwd)?;
+    // A command name is looked up in the PATH of the child, so with another
+    // PATH it could run any executable rather than the one that was allowed.
+    if !is_command_path(cmd) && child_path(&run_args) != env::var_os("PATH") {
+      permissions.check_run_all()?;
+    }
+  }
+  if run_args.clear_env {
+    super::check_unstable(state, "Deno.run.clearEnv");
+  }
+  if run_args.uid.is_some() || run_args.gid.is_some() {
+    super::check_unstable(state, "Deno.run.uid");
+  }
+  if run_args.pgid.is_some() || run_args.setsid {
+    super::check_unstable(state, "Deno.run.pgid");
+  }
+  if run_args.rlimits.is_some() {
+    super::check_unstable(state, "Deno.run.rlimits");
+  }
+  if run_args.timeout.is_some() {
+    super::check_unstable(state, "Deno.run.timeout");
+  }
 
-  let args = run_args.cmd;
-  let env = run_args.env;
-  let cwd = run_args.cwd;
+  let args = &run_args.cmd;
+  let env = &run_args.env;
 
-  let mut c = Command::new(args.get(0).unwrap());
+  // A command given as a path is spawned as the absolute path that was
+  // checked, as platforms don't agree on whether a relative one is resolved
+  // from the working directory of the child.
+  let mut c = std::process::Command::new(resolve_command(cmd, cwd));
   (1..args.len()).for_each(|i| {
     let arg = args.get(i).unwrap();
     c.arg(arg);
   });
   cwd.map(|d| c.current_dir(d));
-  for (key, value) in &env {
-    c.env(key, value);
+  if run_args.clear_env {
+    c.env_clear();
   }
-
-  // TODO: make this work with other resources, eg. sockets
-  if run_args.stdin != "" {
-    c.stdin(subprocess_stdio_map(run_args.stdin.as_ref())?);
-  } else {
-    let file = clone_file(state, run_args.stdin_rid)?;
-    c.stdin(file);
+  for (key, value) in env {
+    c.env(key, value);
   }
 
-  if run_args.stdout != "" {
-    c.stdout(subprocess_stdio_map(run_args.stdout.as_ref())?);
-  } else {
-    let file = clone_file(state, run_args.stdout_rid)?;
-    c.stdout(file);
-  }
+  set_unix_options(&mut c, &run_args)?;
 
-  if run_args.stderr != "" {
-    c.stderr(subprocess_stdio_map(run_args.stderr.as_ref())?);
-  } else {
-    let file = clone_file(state, run_args.stderr_rid)?;
-    c.stderr(file);
+  let mut moved_rids = Vec::new();
+  let spawned =
+    set_stdio(state, &mut c, &run_args, &mut moved_rids).and_then(|()| {
+      // We want to kill child when it's closed
+      let mut c = Command::from(c);
+      c.kill_on_drop(true);
+      c.spawn().map_err(AnyError::from)
+    });
+  let mut child = match spawned {
+    Ok(child) => child,
+    Err(err) => {
+      restore_streams(state, &moved_rids);
+      return Err(err);
+    }
+  };
+  for rid in moved_rids {
+    state.resource_table.remove::<StreamResourceHolder>(rid);
   }
-
-  // We want to kill child when it's closed
-  c.kill_on_drop(true);
-
-  // Spawn the command.
-  let mut child = c.spawn()?;
   let pid = child.id();
 
   let stdin_rid = match child.stdin.take() {
@@ -155,7 +418,20 @@ fn op_run(
     None => None,
   };
 
-  let child_resource = ChildResource { child };
+  let exited = Arc::new(AtomicBool::new(false));
+  if let Some(timeout) = run_args.timeout {
+    let exited = exited.clone();
+    tokio::spawn(async move {
+      tokio::time::delay_for(Duration::from_millis(timeout)).await;
+      if !exited.load(Ordering::SeqCst) {
+        if let Err(err) = kill(pid as i32, SIGKILL) {
+          debug!("Failed to kill child process {}: {}", pid, err);
+        }
+      }
+    });
+  }
+
+  let child_resource = ChildResource { child, exited };
   let child_rid = state.resource_table.add("child", Box::new(child_resource));
 
   Ok(json!({
@@ -167,6 +443,32 @@ fn op_run(
   }))
 }
 
+fn set_stdio(
+  state: &mut OpState,
+  c: &mut std::process::Command,
+  run_args: &RunArgs,
+  moved_rids: &mut Vec<u32>,
+) -> Result<(), AnyError> {
+  if run_args.stdin != "" {
+    c.stdin(subprocess_stdio_map(run_args.stdin.as_ref())?);
+  } else {
+    c.stdin(resource_stdio(state, run_args.stdin_rid, moved_rids)?);
+  }
+
+  if run_args.stdout != "" {
+    c.stdout(subprocess_stdio_map(run_args.stdout.as_ref())?);
+  } else {
+    c.stdout(resource_stdio(state, run_args.stdout_rid, moved_rids)?);
+  }
+
+  if run_args.stderr != "" {
+    c.stderr(subprocess_stdio_map(run_args.stderr.as_ref())?);
+  } else {
+    c.stderr(resource_stdio(state, run_args.stderr_rid, moved_rids)?);
+  }
+  Ok(())
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RunStatusArgs {
@@ -181,11 +483,8 @@ async fn op_run_status(
   let args: RunStatusArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
 
-  {
-    let s = state.borrow();
-    s.borrow::<Permissions>().check_run()?;
-  }
-
+  // No permission check: the child resource only exists if `op_run` was
+  // allowed to spawn the process.
   let run_status = poll_fn(|cx| {
     let mut state = state.borrow_mut();
     let child_resource = state
@@ -193,7 +492,11 @@ async fn op_run_status(
       .get_mut::<ChildResource>(rid)
       .ok_or_else(bad_resource_id)?;
     let child = &mut child_resource.child;
-    child.poll_unpin(cx).map_err(AnyError::from)
+    let poll = child.poll_unpin(cx);
+    if poll.is_ready() {
+      child_resource.exited.store(true, Ordering::SeqCst);
+    }
+    poll.map_err(AnyError::from)
   })
   .await?;
 
@@ -220,6 +523,9 @@ async fn op_run_status(
 struct KillArgs {
   pid: i32,
   signo: i32,
+  /// The resource of the child process, when it is killed with
+  /// `Deno.Process.kill`.
+  rid: Option<u32>,
 }
 
 fn op_kill(
@@ -228,9 +534,19 @@ fn op_kill(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
   super::check_unstable(state, "Deno.kill");
-  state.borrow::<Permissions>().check_run()?;
 
   let args: KillArgs = serde_json::from_value(args)?;
+  // A child process may be killed by whoever was allowed to spawn it, any
+  // other process requires access to run any subprocess.
+  let is_child = args.rid.map_or(false, |rid| {
+    state
+      .resource_table
+      .get::<ChildResource>(rid)
+      .map_or(false, |child| child.child.id() as i32 == args.pid)
+  });
+  if !is_child {
+    state.borrow::<Permissions>().check_run_all()?;
+  }
   kill(args.pid, args.signo)?;
   Ok(json!({}))
 }
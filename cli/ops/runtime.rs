@@ -9,8 +9,10 @@ use deno_core::serde_json;
 use deno_core::serde_json::json;
 use deno_core::serde_json::Value;
 use deno_core::ModuleSpecifier;
+use deno_core::OpMetrics;
 use deno_core::OpState;
 use deno_core::ZeroCopyBuf;
+use deno_core::LATENCY_BUCKETS_US;
 use std::env;
 
 pub fn init(rt: &mut deno_core::JsRuntime, main_module: ModuleSpecifier) {
@@ -70,6 +72,11 @@ fn op_metrics(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
   let m = state.borrow::<Metrics>();
+  let ops: serde_json::Map<String, Value> = state
+    .op_metrics()
+    .into_iter()
+    .map(|(name, metrics)| (name.to_string(), op_metrics_json(metrics)))
+    .collect();
 
   Ok(json!({
     "opsDispatched": m.ops_dispatched,
@@ -82,10 +89,28 @@ fn op_metrics(
     "opsCompletedAsyncUnref": m.ops_completed_async_unref,
     "bytesSentControl": m.bytes_sent_control,
     "bytesSentData": m.bytes_sent_data,
-    "bytesReceived": m.bytes_received
+    "bytesReceived": m.bytes_received,
+    "latencyBucketsMs": LATENCY_BUCKETS_US
+      .iter()
+      .map(|bound| *bound as f64 / 1000.0)
+      .collect::<Vec<f64>>(),
+    "ops": ops,
   }))
 }
 
+fn op_metrics_json(metrics: &OpMetrics) -> Value {
+  json!({
+    "opsDispatched": metrics.ops_dispatched(),
+    "opsDispatchedSync": metrics.ops_dispatched_sync,
+    "opsDispatchedAsync": metrics.ops_dispatched_async,
+    "opsCompleted": metrics.ops_completed(),
+    "opsInFlight": metrics.ops_in_flight(),
+    "latencyTotalMs": metrics.latency.total.as_secs_f64() * 1000.0,
+    "latencyMaxMs": metrics.latency.max.as_secs_f64() * 1000.0,
+    "latencyHistogram": metrics.latency.counts.to_vec(),
+  })
+}
+
 fn ppid() -> Value {
   #[cfg(windows)]
   {
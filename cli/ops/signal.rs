@@ -17,8 +17,12 @@ use deno_core::serde_json;
 #[cfg(unix)]
 use deno_core::serde_json::json;
 #[cfg(unix)]
+use deno_core::Resource;
+#[cfg(unix)]
 use serde::Deserialize;
 #[cfg(unix)]
+use std::borrow::Cow;
+#[cfg(unix)]
 use std::task::Waker;
 #[cfg(unix)]
 use tokio::signal::unix::{signal, Signal, SignalKind};
@@ -34,6 +38,21 @@ pub fn init(rt: &mut deno_core::JsRuntime) {
 /// The second element is the waker of polling future.
 pub struct SignalStreamResource(pub Signal, pub Option<Waker>);
 
+#[cfg(unix)]
+impl Resource for SignalStreamResource {
+  fn name(&self) -> Cow<str> {
+    "signal".into()
+  }
+
+  fn close(&mut self) {
+    if let Some(waker) = self.1.take() {
+      // Wakes up the pending poll if exists.
+      // This prevents the poll future from getting stuck forever.
+      waker.wake();
+    }
+  }
+}
+
 #[cfg(unix)]
 #[derive(Deserialize)]
 struct BindSignalArgs {
@@ -54,13 +73,10 @@ fn op_signal_bind(
 ) -> Result<Value, AnyError> {
   super::check_unstable(state, "Deno.signal");
   let args: BindSignalArgs = serde_json::from_value(args)?;
-  let rid = state.resource_table.add(
-    "signal",
-    Box::new(SignalStreamResource(
-      signal(SignalKind::from_raw(args.signo)).expect(""),
-      None,
-    )),
-  );
+  let rid = state.resource_table.add_resource(SignalStreamResource(
+    signal(SignalKind::from_raw(args.signo)).expect(""),
+    None,
+  ));
   Ok(json!({
     "rid": rid,
   }))
@@ -99,14 +115,6 @@ pub fn op_signal_unbind(
   super::check_unstable(state, "Deno.signal");
   let args: SignalArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
-  let resource = state.resource_table.get_mut::<SignalStreamResource>(rid);
-  if let Some(signal) = resource {
-    if let Some(waker) = &signal.1 {
-      // Wakes up the pending poll if exists.
-      // This prevents the poll future from getting stuck forever.
-      waker.clone().wake();
-    }
-  }
   state
     .resource_table
     .close(rid)
@@ -0,0 +1,96 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::test_runner::TestJobEvent;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::futures::channel::mpsc;
+use deno_core::serde_json;
+use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
+use deno_core::OpState;
+use deno_core::ZeroCopyBuf;
+use serde::Deserialize;
+
+/// Sends the messages of the test runner of an isolate that runs a single
+/// test module of a parallel test run back to the reporting isolate.
+pub struct TestMessageSender {
+  pub index: usize,
+  pub sender: mpsc::UnboundedSender<TestJobEvent>,
+}
+
+pub fn init(rt: &mut deno_core::JsRuntime) {
+  super::reg_json_sync(rt, "op_post_test_message", op_post_test_message);
+  super::reg_json_sync(rt, "op_sanitizer_snapshot", op_sanitizer_snapshot);
+  super::reg_json_sync(rt, "op_track_op_call_sites", op_track_op_call_sites);
+}
+
+fn op_post_test_message(
+  state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let test_message_sender =
+    state.try_borrow::<TestMessageSender>().ok_or_else(|| {
+      generic_error("Test messages can only be posted from a test job")
+    })?;
+  test_message_sender
+    .sender
+    .unbounded_send(TestJobEvent::Message(test_message_sender.index, args))?;
+  Ok(json!({}))
+}
+
+/// Returns the pending async ops, except unref ops, and the open resources,
+/// with the location of the JavaScript code that created them, for the
+/// sanitizers of `Deno.test`.
+fn op_sanitizer_snapshot(
+  state: &mut OpState,
+  _args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let ops: Vec<Value> = state
+    .pending_ops()
+    .into_iter()
+    .filter(|op| !op.is_unref)
+    .map(|op| {
+      json!({
+        "id": op.id,
+        "name": op.name,
+        "callSite": op.call_site,
+      })
+    })
+    .collect();
+  let resources: serde_json::Map<String, Value> = state
+    .resource_table
+    .infos()
+    .into_iter()
+    .map(|(rid, info)| {
+      let resource = json!({
+        "name": info.name,
+        "callSite": info.call_site,
+      });
+      (rid.to_string(), resource)
+    })
+    .collect();
+  Ok(json!({
+    "ops": ops,
+    "resources": resources,
+  }))
+}
+
+#[derive(Deserialize)]
+struct TrackOpCallSitesArgs {
+  enabled: bool,
+}
+
+/// Turns the capture of op call sites on or off, for the duration of a test
+/// with sanitizers. Returns whether it was on.
+fn op_track_op_call_sites(
+  state: &mut OpState,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<Value, AnyError> {
+  let args: TrackOpCallSitesArgs = serde_json::from_value(args)?;
+  let was_enabled = state.track_op_call_sites;
+  state.track_op_call_sites = args.enabled;
+  Ok(json!(was_enabled))
+}
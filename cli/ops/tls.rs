@@ -150,7 +150,7 @@ async fn op_connect_tls(
   {
     let s = state.borrow();
     let permissions = s.borrow::<Permissions>();
-    permissions.check_net(&args.hostname, args.port)?;
+    permissions.check_net_lookup(&args.hostname, args.port)?;
     if let Some(path) = cert_file.clone() {
       permissions.check_read(Path::new(&path))?;
     }
@@ -161,6 +161,11 @@ async fn op_connect_tls(
   }
 
   let addr = resolve_addr(&args.hostname, args.port)?;
+  {
+    let s = state.borrow();
+    s.borrow::<Permissions>()
+      .check_net_addr(&args.hostname, &addr)?;
+  }
   let tcp_stream = TcpStream::connect(&addr).await?;
   let local_addr = tcp_stream.local_addr()?;
   let remote_addr = tcp_stream.peer_addr()?;
@@ -325,7 +330,7 @@ fn op_listen_tls(
   let key_file = args.key_file;
   {
     let permissions = state.borrow::<Permissions>();
-    permissions.check_net(&args.hostname, args.port)?;
+    permissions.check_net_lookup(&args.hostname, args.port)?;
     permissions.check_read(Path::new(&cert_file))?;
     permissions.check_read(Path::new(&key_file))?;
   }
@@ -335,6 +340,9 @@ fn op_listen_tls(
     .expect("invalid key or certificate");
   let tls_acceptor = TlsAcceptor::from(Arc::new(config));
   let addr = resolve_addr(&args.hostname, args.port)?;
+  state
+    .borrow::<Permissions>()
+    .check_net_addr(&args.hostname, &addr)?;
   let std_listener = std::net::TcpListener::bind(&addr)?;
   let listener = TcpListener::from_std(std_listener)?;
   let local_addr = listener.local_addr()?;
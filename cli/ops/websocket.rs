@@ -1,9 +1,11 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 use crate::permissions::Permissions;
+use crate::resolve_addr::resolve_addr;
 use core::task::Poll;
 use deno_core::error::bad_resource_id;
 use deno_core::error::type_error;
+use deno_core::error::uri_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::poll_fn;
 use deno_core::futures::StreamExt;
@@ -58,10 +60,22 @@ pub async fn op_ws_create(
   _bufs: BufVec,
 ) -> Result<Value, AnyError> {
   let args: CreateArgs = serde_json::from_value(args)?;
+  let url = url::Url::parse(&args.url)?;
+  let host = url.host_str().ok_or_else(|| uri_error("missing host"))?;
+  let port = url.port_or_known_default().unwrap_or(0);
   {
     let s = state.borrow();
-    s.borrow::<Permissions>()
-      .check_net_url(&url::Url::parse(&args.url)?)?;
+    s.borrow::<Permissions>().check_net_lookup(host, port)?;
+  }
+  // The host is resolved once, so that the address that is checked is the one
+  // that is connected to.
+  let addr = match resolve_addr(host, port) {
+    Ok(addr) => addr,
+    Err(_) => return Ok(json!({"success": false})),
+  };
+  {
+    let s = state.borrow();
+    s.borrow::<Permissions>().check_net_addr(host, &addr)?;
   }
   let ca_file = {
     let cli_state = super::global_state2(&state);
@@ -74,13 +88,7 @@ pub async fn op_ws_create(
     .header("Sec-WebSocket-Protocol", args.protocols)
     .body(())?;
   let domain = &uri.host().unwrap().to_string();
-  let port = &uri.port_u16().unwrap_or(match uri.scheme_str() {
-    Some("wss") => 443,
-    Some("ws") => 80,
-    _ => unreachable!(),
-  });
-  let addr = format!("{}:{}", domain, port);
-  let try_socket = TcpStream::connect(addr).await;
+  let try_socket = TcpStream::connect(&addr).await;
   let tcp_socket = match try_socket.map_err(TungsteniteError::Io) {
     Ok(socket) => socket,
     Err(_) => return Ok(json!({"success": false})),
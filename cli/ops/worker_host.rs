@@ -1,13 +1,19 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::flags_allow_net;
 use crate::fmt_errors::JsError;
+use crate::fs::resolve_from_cwd;
 use crate::global_state::GlobalState;
 use crate::ops::io::get_stdio;
+use crate::permissions::env_key;
+use crate::permissions::PermissionState;
 use crate::permissions::Permissions;
+use crate::permissions::UnaryPermission;
 use crate::tokio_util::create_basic_runtime;
 use crate::worker::WebWorker;
 use crate::worker::WebWorkerHandle;
 use crate::worker::WorkerEvent;
+use deno_core::error::type_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::FutureExt;
 use deno_core::serde_json;
@@ -20,7 +26,11 @@ use deno_core::ZeroCopyBuf;
 use serde::Deserialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::convert::From;
+use std::hash::Hash;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::thread::JoinHandle;
@@ -174,6 +184,103 @@ fn run_worker_thread(
   Ok((join_handle, worker_handle))
 }
 
+/// A permission of a worker: `true` or `false` for the whole permission, or
+/// a list of paths, hosts, environment variables or commands.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PermissionArg {
+  Bool(bool),
+  List(Vec<String>),
+}
+
+/// The permissions of a worker. Those that are not given are inherited from
+/// the parent.
+#[derive(Deserialize)]
+struct PermissionsArg {
+  read: Option<PermissionArg>,
+  write: Option<PermissionArg>,
+  net: Option<PermissionArg>,
+  env: Option<PermissionArg>,
+  run: Option<PermissionArg>,
+  plugin: Option<bool>,
+  hrtime: Option<bool>,
+}
+
+fn unary_permission_from_arg<T: Eq + Hash + Clone>(
+  parent: &UnaryPermission<T>,
+  maybe_arg: Option<PermissionArg>,
+  parse: impl Fn(&str) -> Result<Vec<T>, AnyError>,
+) -> Result<UnaryPermission<T>, AnyError> {
+  let (global_state, granted_list) = match maybe_arg {
+    None => return Ok(parent.clone()),
+    Some(PermissionArg::Bool(true)) => {
+      (PermissionState::Granted, HashSet::new())
+    }
+    Some(PermissionArg::Bool(false)) => {
+      (PermissionState::Denied, HashSet::new())
+    }
+    Some(PermissionArg::List(entries)) => {
+      let mut granted_list = HashSet::new();
+      for entry in entries {
+        granted_list.extend(parse(&entry)?);
+      }
+      (PermissionState::Prompt, granted_list)
+    }
+  };
+  // What the parent denies stays denied.
+  let global_state = if parent.global_state == PermissionState::Denied
+    && global_state == PermissionState::Prompt
+  {
+    PermissionState::Denied
+  } else {
+    global_state
+  };
+  Ok(UnaryPermission {
+    global_state,
+    granted_list,
+    denied_list: parent.denied_list.clone(),
+  })
+}
+
+fn permission_state_from_arg(
+  parent: PermissionState,
+  maybe_arg: Option<bool>,
+) -> PermissionState {
+  match maybe_arg {
+    None => parent,
+    Some(true) => PermissionState::Granted,
+    Some(false) => PermissionState::Denied,
+  }
+}
+
+/// Creates the permissions of a worker, which must not escalate those of its
+/// parent.
+fn create_worker_permissions(
+  parent: &Permissions,
+  args: PermissionsArg,
+) -> Result<Permissions, AnyError> {
+  let path = |path: &str| -> Result<Vec<PathBuf>, AnyError> {
+    Ok(vec![resolve_from_cwd(Path::new(path))?])
+  };
+  let net = |host: &str| -> Result<Vec<String>, AnyError> {
+    flags_allow_net::validator(host.to_string()).map_err(type_error)?;
+    Ok(flags_allow_net::parse(vec![host.to_string()]).unwrap())
+  };
+  let var =
+    |var: &str| -> Result<Vec<String>, AnyError> { Ok(vec![env_key(var)]) };
+  let cmd =
+    |cmd: &str| -> Result<Vec<String>, AnyError> { Ok(vec![cmd.to_string()]) };
+  parent.fork(
+    unary_permission_from_arg(&parent.read, args.read, path)?,
+    unary_permission_from_arg(&parent.write, args.write, path)?,
+    unary_permission_from_arg(&parent.net, args.net, net)?,
+    unary_permission_from_arg(&parent.env, args.env, var)?,
+    unary_permission_from_arg(&parent.run, args.run, cmd)?,
+    permission_state_from_arg(parent.plugin, args.plugin),
+    permission_state_from_arg(parent.hrtime, args.hrtime),
+  )
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct CreateWorkerArgs {
@@ -182,6 +289,7 @@ struct CreateWorkerArgs {
   has_source_code: bool,
   source_code: String,
   use_deno_namespace: bool,
+  permissions: Option<PermissionsArg>,
 }
 
 /// Create worker as the host
@@ -203,7 +311,14 @@ fn op_create_worker(
   if use_deno_namespace {
     super::check_unstable(state, "Worker.deno");
   }
-  let permissions = state.borrow::<Permissions>().clone();
+  let parent_permissions = state.borrow::<Permissions>().clone();
+  let permissions = match args.permissions {
+    Some(permissions) => {
+      super::check_unstable(state, "Worker.deno.permissions");
+      create_worker_permissions(&parent_permissions, permissions)?
+    }
+    None => parent_permissions,
+  };
   let worker_id = state.take::<WorkerId>();
   state.put::<WorkerId>(worker_id + 1);
 
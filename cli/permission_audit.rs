@@ -0,0 +1,215 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Records the permission checks of a program for `--permission-audit` and
+//! `--permission-audit-summary`. The audit is shared by every worker of the
+//! process.
+
+use crate::permissions::PermissionState;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Debug, PartialEq, Serialize)]
+struct AuditRecord {
+  permission: &'static str,
+  /// The path, host, variable or command being checked. Checks of a whole
+  /// permission have no target.
+  target: Option<String>,
+  result: String,
+  /// The location of the JavaScript code that triggered the check.
+  location: Option<String>,
+}
+
+struct PermissionAudit {
+  maybe_file: Option<File>,
+  summary: bool,
+  /// The targets checked for each permission. `None` stands for the whole
+  /// permission.
+  used: BTreeMap<&'static str, BTreeSet<Option<String>>>,
+}
+
+lazy_static! {
+  static ref PERMISSION_AUDIT: Mutex<Option<PermissionAudit>> =
+    Mutex::new(None);
+}
+
+/// Starts recording permission checks, to the JSON lines file at
+/// `maybe_path` and, if `summary` is set, for `print_summary`.
+pub fn init(maybe_path: Option<&str>, summary: bool) -> Result<(), AnyError> {
+  if maybe_path.is_none() && !summary {
+    return Ok(());
+  }
+  let maybe_file = match maybe_path {
+    Some(path) => Some(
+      OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| {
+          generic_error(format!(
+            "Unable to open permission audit file \"{}\": {}",
+            path, err
+          ))
+        })?,
+    ),
+    None => None,
+  };
+  *PERMISSION_AUDIT.lock().unwrap() = Some(PermissionAudit {
+    maybe_file,
+    summary,
+    used: BTreeMap::new(),
+  });
+  Ok(())
+}
+
+/// Records the result of a permission check, if the audit is enabled.
+pub fn record(
+  permission: &'static str,
+  target: Option<String>,
+  result: PermissionState,
+) {
+  let mut guard = PERMISSION_AUDIT.lock().unwrap();
+  let audit = match guard.as_mut() {
+    Some(audit) => audit,
+    None => return,
+  };
+  let record = AuditRecord {
+    permission,
+    target,
+    result: result.to_string(),
+    location: deno_core::op_call_site(),
+  };
+  if let Some(file) = audit.maybe_file.as_mut() {
+    let line = serde_json::to_string(&record).unwrap();
+    if let Err(err) = writeln!(file, "{}", line) {
+      error!("Unable to write to the permission audit file: {}", err);
+    }
+  }
+  if audit.summary {
+    audit
+      .used
+      .entry(record.permission)
+      .or_insert_with(BTreeSet::new)
+      .insert(record.target);
+  }
+}
+
+/// Prints the flags that grant the permissions checked so far, if
+/// `--permission-audit-summary` was given. Called before the process exits.
+pub fn print_summary() {
+  let guard = PERMISSION_AUDIT.lock().unwrap();
+  let audit = match guard.as_ref() {
+    Some(audit) if audit.summary => audit,
+    _ => return,
+  };
+  let flags = summarize(&audit.used);
+  if flags.is_empty() {
+    eprintln!("The program did not use any permission.");
+  } else {
+    eprintln!("The program used the permissions granted by:");
+    eprintln!("  {}", flags.join(" "));
+  }
+}
+
+/// Returns the minimal set of flags that grant the permissions in `used`.
+fn summarize(
+  used: &BTreeMap<&'static str, BTreeSet<Option<String>>>,
+) -> Vec<String> {
+  let mut flags = Vec::new();
+  for (permission, targets) in used {
+    if targets.contains(&None) {
+      flags.push(format!("--allow-{}", permission));
+      continue;
+    }
+    let targets: Vec<&str> =
+      targets.iter().filter_map(|t| t.as_deref()).collect();
+    let targets: Vec<&str> = match *permission {
+      // A path is covered by the permission of any of its ancestors.
+      "read" | "write" => targets
+        .iter()
+        .filter(|target| {
+          !targets.iter().any(|other| {
+            other != *target && Path::new(target).starts_with(other)
+          })
+        })
+        .cloned()
+        .collect(),
+      "plugin" | "hrtime" => {
+        flags.push(format!("--allow-{}", permission));
+        continue;
+      }
+      _ => targets,
+    };
+    flags.push(format!("--allow-{}={}", permission, targets.join(",")));
+  }
+  flags
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_summarize() {
+    let mut used = BTreeMap::new();
+    used.insert(
+      "read",
+      vec![
+        Some("/project".to_string()),
+        Some("/project/data/a.json".to_string()),
+        Some("/etc/hosts".to_string()),
+      ]
+      .into_iter()
+      .collect::<BTreeSet<_>>(),
+    );
+    used.insert(
+      "net",
+      vec![Some("deno.land:443".to_string())]
+        .into_iter()
+        .collect::<BTreeSet<_>>(),
+    );
+    used.insert(
+      "env",
+      vec![Some("HOME".to_string()), None]
+        .into_iter()
+        .collect::<BTreeSet<_>>(),
+    );
+    used.insert(
+      "plugin",
+      vec![Some("/project/plugin.so".to_string())]
+        .into_iter()
+        .collect::<BTreeSet<_>>(),
+    );
+    assert_eq!(
+      summarize(&used),
+      vec![
+        "--allow-env",
+        "--allow-net=deno.land:443",
+        "--allow-plugin",
+        "--allow-read=/etc/hosts,/project",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_audit_record_json() {
+    let record = AuditRecord {
+      permission: "read",
+      target: Some("/etc/hosts".to_string()),
+      result: PermissionState::Denied.to_string(),
+      location: Some("file:///main.ts:1:1".to_string()),
+    };
+    assert_eq!(
+      serde_json::to_string(&record).unwrap(),
+      r#"{"permission":"read","target":"/etc/hosts","result":"denied","location":"file:///main.ts:1:1"}"#
+    );
+  }
+}
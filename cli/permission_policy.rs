@@ -0,0 +1,436 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::flags_allow_net;
+use crate::fs::canonicalize_path;
+use crate::fs::normalize_path;
+use crate::fs::resolve_from_cwd;
+use crate::permissions::env_key;
+use crate::permissions::is_command_path;
+use crate::permissions::PermissionState;
+use crate::permissions::Permissions;
+use crate::permissions::UnaryPermission;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A permission listed in a policy file: `true` for the whole permission, or
+/// a list of paths, hosts, environment variables or commands.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+enum PolicyEntry {
+  All(bool),
+  List(Vec<String>),
+}
+
+impl Default for PolicyEntry {
+  fn default() -> Self {
+    PolicyEntry::All(false)
+  }
+}
+
+impl PolicyEntry {
+  fn is_empty(&self) -> bool {
+    *self == PolicyEntry::All(false)
+  }
+
+  /// Adds `item` to the list, unless the entry covers the whole permission.
+  fn add(&mut self, item: String) {
+    match self {
+      PolicyEntry::All(true) => {}
+      PolicyEntry::All(false) => *self = PolicyEntry::List(vec![item]),
+      PolicyEntry::List(items) => {
+        if !items.contains(&item) {
+          items.push(item);
+        }
+      }
+    }
+  }
+}
+
+fn is_false(value: &bool) -> bool {
+  !value
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyRules {
+  #[serde(skip_serializing_if = "PolicyEntry::is_empty")]
+  read: PolicyEntry,
+  #[serde(skip_serializing_if = "PolicyEntry::is_empty")]
+  write: PolicyEntry,
+  #[serde(skip_serializing_if = "PolicyEntry::is_empty")]
+  net: PolicyEntry,
+  #[serde(skip_serializing_if = "PolicyEntry::is_empty")]
+  env: PolicyEntry,
+  #[serde(skip_serializing_if = "PolicyEntry::is_empty")]
+  run: PolicyEntry,
+  #[serde(skip_serializing_if = "is_false")]
+  plugin: bool,
+  #[serde(skip_serializing_if = "is_false")]
+  hrtime: bool,
+}
+
+impl PolicyRules {
+  /// Adds a path, host, variable or command to a permission, or the whole
+  /// permission if `target` is `None`.
+  fn add(&mut self, permission: &str, target: Option<String>) {
+    let entry = match permission {
+      "read" => &mut self.read,
+      "write" => &mut self.write,
+      "net" => &mut self.net,
+      "env" => &mut self.env,
+      "run" => &mut self.run,
+      "plugin" => {
+        self.plugin = true;
+        return;
+      }
+      "hrtime" => {
+        self.hrtime = true;
+        return;
+      }
+      _ => unreachable!(),
+    };
+    match target {
+      Some(target) => entry.add(target),
+      None => *entry = PolicyEntry::All(true),
+    }
+  }
+}
+
+/// Permissions loaded from the JSON file passed with `--permissions`, for
+/// example:
+///
+/// ```json
+/// {
+///   "allow": { "read": ["./data"], "net": ["deno.land"], "hrtime": true },
+///   "deny": { "read": ["./data/secrets"] }
+/// }
+/// ```
+///
+/// Files with a `.toml` extension are read as TOML, with the same tables.
+/// Relative paths are resolved from the directory of the policy file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionPolicy {
+  allow: PolicyRules,
+  deny: PolicyRules,
+  #[serde(skip)]
+  base_dir: PathBuf,
+}
+
+impl PermissionPolicy {
+  pub fn load(file_path: &str) -> Result<Self, AnyError> {
+    let resolved_path = resolve_from_cwd(Path::new(file_path))?;
+    debug!(
+      "Attempt to load permission policy: {}",
+      resolved_path.display()
+    );
+    let policy_string = fs::read_to_string(&resolved_path).map_err(|err| {
+      generic_error(format!(
+        "Error retrieving permission policy file at \"{}\": {}",
+        resolved_path.display(),
+        err
+      ))
+    })?;
+    let base_dir = resolved_path.parent().unwrap().to_path_buf();
+    if resolved_path.extension() == Some(OsStr::new("toml")) {
+      Self::from_toml(&policy_string, base_dir)
+    } else {
+      Self::from_json(&policy_string, base_dir)
+    }
+  }
+
+  pub fn from_toml(
+    toml_string: &str,
+    base_dir: PathBuf,
+  ) -> Result<Self, AnyError> {
+    let mut policy: Self = toml::from_str(toml_string).map_err(|err| {
+      generic_error(format!("Invalid permission policy: {}", err))
+    })?;
+    policy.base_dir = base_dir;
+    Ok(policy)
+  }
+
+  pub fn from_json(
+    json_string: &str,
+    base_dir: PathBuf,
+  ) -> Result<Self, AnyError> {
+    let mut policy: Self =
+      serde_json::from_str(json_string).map_err(|err| {
+        generic_error(format!("Invalid permission policy: {}", err))
+      })?;
+    policy.base_dir = base_dir;
+    Ok(policy)
+  }
+
+  /// Returns the permissions described by the policy. A permission that is
+  /// both allowed and denied is denied.
+  pub fn to_permissions(&self) -> Result<Permissions, AnyError> {
+    let path = |path: &str| -> Result<Vec<PathBuf>, AnyError> {
+      Ok(vec![normalize_path(&self.base_dir.join(path))])
+    };
+    let net = |host: &str| -> Result<Vec<String>, AnyError> {
+      flags_allow_net::validator(host.to_string()).map_err(generic_error)?;
+      Ok(flags_allow_net::parse(vec![host.to_string()]).unwrap())
+    };
+    let var =
+      |var: &str| -> Result<Vec<String>, AnyError> { Ok(vec![env_key(var)]) };
+    let cmd = |cmd: &str| -> Result<Vec<String>, AnyError> {
+      // Like paths, executables are relative to the policy file.
+      if is_command_path(cmd) {
+        let path = normalize_path(&self.base_dir.join(cmd));
+        return Ok(vec![path.display().to_string()]);
+      }
+      Ok(vec![cmd.to_string()])
+    };
+    // Denied paths have their symlinks resolved, like those of --deny-read.
+    let resolve_denied = |unary: UnaryPermission<PathBuf>| UnaryPermission {
+      denied_list: unary
+        .denied_list
+        .iter()
+        .map(|path| canonicalize_path(path))
+        .collect(),
+      ..unary
+    };
+    Ok(Permissions {
+      read: resolve_denied(to_unary(&self.allow.read, &self.deny.read, path)?),
+      write: resolve_denied(to_unary(
+        &self.allow.write,
+        &self.deny.write,
+        path,
+      )?),
+      net: to_unary(&self.allow.net, &self.deny.net, net)?,
+      env: to_unary(&self.allow.env, &self.deny.env, var)?,
+      run: to_unary(&self.allow.run, &self.deny.run, cmd)?,
+      plugin: to_state(self.allow.plugin, self.deny.plugin),
+      hrtime: to_state(self.allow.hrtime, self.deny.hrtime),
+    })
+  }
+}
+
+lazy_static! {
+  /// The policy file given with `--save-permissions` and the answers saved so
+  /// far.
+  static ref SAVED_ANSWERS: Mutex<Option<(PathBuf, PermissionPolicy)>> =
+    Mutex::new(None);
+}
+
+/// Starts saving the answers to permission prompts to the policy file at
+/// `maybe_path`. An existing file is extended.
+pub fn init_saved_answers(maybe_path: Option<&str>) -> Result<(), AnyError> {
+  let path = match maybe_path {
+    Some(path) => resolve_from_cwd(Path::new(path))?,
+    None => return Ok(()),
+  };
+  let policy = if path.exists() {
+    PermissionPolicy::load(&path.display().to_string())?
+  } else {
+    PermissionPolicy::default()
+  };
+  *SAVED_ANSWERS.lock().unwrap() = Some((path, policy));
+  Ok(())
+}
+
+/// Saves the answer to a permission prompt, if `--save-permissions` was
+/// given. `target` is `None` when the whole permission was granted or
+/// denied. Paths must be absolute.
+pub fn save_answer(
+  permission: &'static str,
+  target: Option<String>,
+  granted: bool,
+) {
+  let mut guard = SAVED_ANSWERS.lock().unwrap();
+  let (path, policy) = match guard.as_mut() {
+    Some(saved) => saved,
+    None => return,
+  };
+  if granted {
+    policy.allow.add(permission, target);
+  } else {
+    policy.deny.add(permission, target);
+  }
+  // The file is written after every answer so that it is complete however
+  // the program exits.
+  let contents = if path.extension() == Some(OsStr::new("toml")) {
+    toml::to_string_pretty(&policy).unwrap()
+  } else {
+    serde_json::to_string_pretty(&policy).unwrap() + "\n"
+  };
+  if let Err(err) = fs::write(&path, contents) {
+    error!(
+      "Unable to save the permission policy \"{}\": {}",
+      path.display(),
+      err
+    );
+  }
+}
+
+fn to_state(allow: bool, deny: bool) -> PermissionState {
+  if deny {
+    PermissionState::Denied
+  } else {
+    PermissionState::from(allow)
+  }
+}
+
+fn to_unary<T, F>(
+  allow: &PolicyEntry,
+  deny: &PolicyEntry,
+  parse: F,
+) -> Result<UnaryPermission<T>, AnyError>
+where
+  T: Eq + Hash,
+  F: Fn(&str) -> Result<Vec<T>, AnyError>,
+{
+  let to_list = |entry: &PolicyEntry| -> Result<HashSet<T>, AnyError> {
+    let mut list = HashSet::new();
+    if let PolicyEntry::List(items) = entry {
+      for item in items {
+        list.extend(parse(item)?);
+      }
+    }
+    Ok(list)
+  };
+  if *deny == PolicyEntry::All(true) {
+    return Ok(UnaryPermission {
+      global_state: PermissionState::Denied,
+      ..Default::default()
+    });
+  }
+  Ok(UnaryPermission {
+    global_state: PermissionState::from(*allow == PolicyEntry::All(true)),
+    granted_list: to_list(allow)?,
+    denied_list: to_list(deny)?,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_to_permissions() {
+    let base_dir = if cfg!(windows) {
+      PathBuf::from("C:\\project")
+    } else {
+      PathBuf::from("/project")
+    };
+    let policy = PermissionPolicy::from_json(
+      r#"{
+        "allow": {
+          "read": ["./data", "../shared"],
+          "write": true,
+          "net": ["deno.land", ":8000"],
+          "env": ["HOME"],
+          "hrtime": true
+        },
+        "deny": {
+          "read": ["data/secrets"],
+          "net": ["169.254.169.254"],
+          "run": true
+        }
+      }"#,
+      base_dir.clone(),
+    )
+    .unwrap();
+    let perms = policy.to_permissions().unwrap();
+    assert_eq!(perms.read.global_state, PermissionState::Prompt);
+    assert!(perms.read.granted_list.contains(&base_dir.join("data")));
+    assert!(perms
+      .read
+      .granted_list
+      .contains(&base_dir.parent().unwrap().join("shared")));
+    assert!(perms
+      .read
+      .denied_list
+      .contains(&base_dir.join("data").join("secrets")));
+    assert_eq!(perms.write.global_state, PermissionState::Granted);
+    assert!(perms.net.granted_list.contains("deno.land"));
+    assert!(perms.net.granted_list.contains("localhost:8000"));
+    assert!(perms.net.denied_list.contains("169.254.169.254"));
+    assert!(perms.env.granted_list.contains("HOME"));
+    assert_eq!(perms.run.global_state, PermissionState::Denied);
+    assert_eq!(perms.plugin, PermissionState::Prompt);
+    assert_eq!(perms.hrtime, PermissionState::Granted);
+  }
+
+  #[test]
+  fn test_toml_policy() {
+    let base_dir = if cfg!(windows) {
+      PathBuf::from("C:\\project")
+    } else {
+      PathBuf::from("/project")
+    };
+    let policy = PermissionPolicy::from_toml(
+      r#"
+        [allow]
+        read = ["./data"]
+        net = ["deno.land"]
+        hrtime = true
+
+        [deny]
+        read = ["./data/secrets"]
+        run = true
+      "#,
+      base_dir.clone(),
+    )
+    .unwrap();
+    let perms = policy.to_permissions().unwrap();
+    assert!(perms.read.granted_list.contains(&base_dir.join("data")));
+    assert!(perms
+      .read
+      .denied_list
+      .contains(&base_dir.join("data").join("secrets")));
+    assert!(perms.net.granted_list.contains("deno.land"));
+    assert_eq!(perms.run.global_state, PermissionState::Denied);
+    assert_eq!(perms.hrtime, PermissionState::Granted);
+    assert!(
+      PermissionPolicy::from_toml("[allow]\ndisk = true\n", base_dir).is_err()
+    );
+  }
+
+  #[test]
+  fn test_invalid_policy() {
+    let base_dir = PathBuf::from("/project");
+    assert!(PermissionPolicy::from_json(
+      r#"{ "allow": { "disk": true } }"#,
+      base_dir.clone()
+    )
+    .is_err());
+    let policy = PermissionPolicy::from_json(
+      r#"{ "allow": { "net": ["not a host"] } }"#,
+      base_dir,
+    )
+    .unwrap();
+    assert!(policy.to_permissions().is_err());
+  }
+
+  #[test]
+  fn test_save_answers() {
+    let mut policy = PermissionPolicy::default();
+    policy.allow.add("read", Some("/project/data".to_string()));
+    policy.allow.add("read", Some("/project/data".to_string()));
+    policy.allow.add("env", None);
+    policy.allow.add("env", Some("HOME".to_string()));
+    policy.allow.add("hrtime", None);
+    policy.deny.add("net", Some("deno.land:443".to_string()));
+    assert_eq!(
+      serde_json::to_string(&policy).unwrap(),
+      r#"{"allow":{"read":["/project/data"],"env":true,"hrtime":true},"deny":{"net":["deno.land:443"]}}"#
+    );
+  }
+
+  #[test]
+  fn load_nonexistent() {
+    assert!(PermissionPolicy::load("nonexistent_policy.json").is_err());
+  }
+}
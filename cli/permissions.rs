@@ -2,24 +2,28 @@
 
 use crate::colors;
 use crate::flags::Flags;
+use crate::flags_allow_net;
+use crate::fs::canonicalize_path;
+use crate::fs::normalize_path;
 use crate::fs::resolve_from_cwd;
+use crate::permission_audit;
+use crate::permission_policy::save_answer;
 use deno_core::error::custom_error;
 use deno_core::error::uri_error;
 use deno_core::error::AnyError;
 use deno_core::url;
 use serde::Deserialize;
 use std::collections::HashSet;
+use std::env;
 use std::env::current_dir;
 use std::fmt;
 use std::hash::Hash;
 #[cfg(not(test))]
 use std::io;
+use std::net::IpAddr;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 #[cfg(test)]
-use std::sync::atomic::AtomicBool;
-#[cfg(test)]
-use std::sync::atomic::Ordering;
-#[cfg(test)]
 use std::sync::Mutex;
 
 const PERMISSION_EMOJI: &str = "⚠️";
@@ -43,6 +47,12 @@ impl PermissionState {
     Err(custom_error("PermissionDenied", message))
   }
 
+  /// Record the result of a check in the permission audit.
+  fn audit(self, permission: &'static str, target: Option<String>) -> Self {
+    permission_audit::record(permission, target, self);
+    self
+  }
+
   /// Check that the permissions represented by `other` don't escalate ours.
   fn check_fork(self, other: &Self) -> Result<(), AnyError> {
     if self == PermissionState::Denied && other != &PermissionState::Denied
@@ -52,6 +62,19 @@ impl PermissionState {
     }
     Ok(())
   }
+
+  /// Combine two states: a denial always wins over a grant.
+  fn merge(self, other: Self) -> Self {
+    match (self, other) {
+      (PermissionState::Denied, _) | (_, PermissionState::Denied) => {
+        PermissionState::Denied
+      }
+      (PermissionState::Granted, _) | (_, PermissionState::Granted) => {
+        PermissionState::Granted
+      }
+      _ => PermissionState::Prompt,
+    }
+  }
 }
 
 impl From<usize> for PermissionState {
@@ -100,9 +123,14 @@ pub struct UnaryPermission<T: Eq + Hash> {
 
 impl<T: Eq + Hash> UnaryPermission<T> {
   /// Check that the permissions represented by `other` don't escalate ours.
-  fn check_fork(&self, other: &Self) -> Result<(), AnyError> {
+  /// `is_granted` tells whether we grant an entry of `other.granted_list`.
+  fn check_fork(
+    &self,
+    other: &Self,
+    is_granted: impl Fn(&T) -> bool,
+  ) -> Result<(), AnyError> {
     self.global_state.check_fork(&other.global_state)?;
-    if !self.granted_list.is_superset(&other.granted_list) {
+    if !other.granted_list.iter().all(is_granted) {
       return Err(permission_escalation_error());
     }
     if !self.denied_list.is_subset(&other.denied_list) {
@@ -110,6 +138,91 @@ impl<T: Eq + Hash> UnaryPermission<T> {
     }
     Ok(())
   }
+
+  /// Combine two sets of permissions: the result grants what either of them
+  /// grants, except for what either of them denies. `Permissions::merge`
+  /// checks that this doesn't escalate the permissions of the policy.
+  fn merge(self, other: Self) -> Self {
+    let global_state = self.global_state.merge(other.global_state);
+    let granted_list = if global_state == PermissionState::Denied {
+      HashSet::new()
+    } else {
+      self
+        .granted_list
+        .into_iter()
+        .chain(other.granted_list)
+        .collect()
+    };
+    Self {
+      global_state,
+      granted_list,
+      denied_list: self
+        .denied_list
+        .into_iter()
+        .chain(other.denied_list)
+        .collect(),
+    }
+  }
+
+  /// Grant the whole permission for the rest of the session, after a prompt
+  /// was answered with "grant" or "grant all".
+  fn grant_all(&mut self, permission: &'static str) -> PermissionState {
+    save_answer(permission, None, true);
+    self.granted_list.clear();
+    self.global_state = PermissionState::Granted;
+    PermissionState::Granted
+  }
+}
+
+impl UnaryPermission<PathBuf> {
+  /// Whether `path`, which must be absolute, is denied. A denied path denies
+  /// everything inside it, whether it was denied with a flag, a policy file or
+  /// at a prompt: they share `denied_list`, and a denied directory must not be
+  /// readable file by file. The denied paths have their symlinks resolved, so
+  /// `path` is compared once its own are, for a symlink not to get around a
+  /// denial.
+  fn is_path_denied(&self, path: Option<&Path>) -> bool {
+    let path = match path {
+      None => return self.global_state == PermissionState::Denied,
+      Some(_) if self.denied_list.is_empty() => return false,
+      Some(path) => canonicalize_path(path),
+    };
+    self.global_state == PermissionState::Denied
+      && check_path_blocklist(&path, &self.denied_list)
+      || check_path_allowlist(&path, &self.denied_list)
+  }
+
+  /// Apply the answer to a prompt for `path`, which must be absolute.
+  fn answer_path_prompt(
+    &mut self,
+    permission: &'static str,
+    path: PathBuf,
+    response: PromptResponse,
+  ) -> PermissionState {
+    match response {
+      PromptResponse::Grant | PromptResponse::GrantScope => {
+        let path = match path.parent() {
+          Some(parent) if response == PromptResponse::GrantScope => {
+            parent.to_path_buf()
+          }
+          _ => path,
+        };
+        save_answer(permission, Some(path.display().to_string()), true);
+        self.granted_list.retain(|path_| !path_.starts_with(&path));
+        self.granted_list.insert(path);
+        PermissionState::Granted
+      }
+      PromptResponse::GrantAll => self.grant_all(permission),
+      PromptResponse::Deny => {
+        save_answer(permission, Some(path.display().to_string()), false);
+        let path = canonicalize_path(&path);
+        self.denied_list.retain(|path_| !path.starts_with(path_));
+        self.denied_list.insert(path);
+        self.global_state = PermissionState::Denied;
+        PermissionState::Denied
+      }
+    }
+  }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
@@ -117,8 +230,8 @@ pub struct Permissions {
   pub read: UnaryPermission<PathBuf>,
   pub write: UnaryPermission<PathBuf>,
   pub net: UnaryPermission<String>,
-  pub env: PermissionState,
-  pub run: PermissionState,
+  pub env: UnaryPermission<String>,
+  pub run: UnaryPermission<String>,
   pub plugin: PermissionState,
   pub hrtime: PermissionState,
 }
@@ -130,31 +243,81 @@ fn resolve_fs_allowlist(allowlist: &[PathBuf]) -> HashSet<PathBuf> {
     .collect()
 }
 
+/// Denied paths have their symlinks resolved, see
+/// `UnaryPermission::is_path_denied`.
+fn resolve_fs_denylist(denylist: &[PathBuf]) -> HashSet<PathBuf> {
+  denylist
+    .iter()
+    .map(|raw_path| {
+      canonicalize_path(&resolve_from_cwd(Path::new(&raw_path)).unwrap())
+    })
+    .collect()
+}
+
 impl Permissions {
   pub fn from_flags(flags: &Flags) -> Self {
     Self {
       read: UnaryPermission::<PathBuf> {
         global_state: PermissionState::from(flags.allow_read),
         granted_list: resolve_fs_allowlist(&flags.read_allowlist),
-        ..Default::default()
+        denied_list: resolve_fs_denylist(&flags.read_denylist),
       },
       write: UnaryPermission::<PathBuf> {
         global_state: PermissionState::from(flags.allow_write),
         granted_list: resolve_fs_allowlist(&flags.write_allowlist),
-        ..Default::default()
+        denied_list: resolve_fs_denylist(&flags.write_denylist),
       },
       net: UnaryPermission::<String> {
         global_state: PermissionState::from(flags.allow_net),
         granted_list: flags.net_allowlist.iter().cloned().collect(),
-        ..Default::default()
+        denied_list: flags.net_denylist.iter().cloned().collect(),
+      },
+      env: UnaryPermission::<String> {
+        global_state: PermissionState::from(flags.allow_env),
+        granted_list: flags.env_allowlist.iter().map(|v| env_key(v)).collect(),
+        denied_list: flags.env_denylist.iter().map(|v| env_key(v)).collect(),
+      },
+      run: UnaryPermission::<String> {
+        global_state: PermissionState::from(flags.allow_run),
+        granted_list: flags
+          .run_allowlist
+          .iter()
+          .map(|entry| resolve_run_entry(entry))
+          .collect(),
+        denied_list: flags
+          .run_denylist
+          .iter()
+          .map(|entry| resolve_run_entry(entry))
+          .collect(),
       },
-      env: PermissionState::from(flags.allow_env),
-      run: PermissionState::from(flags.allow_run),
       plugin: PermissionState::from(flags.allow_plugin),
       hrtime: PermissionState::from(flags.allow_hrtime),
     }
   }
 
+  /// Combine the permissions of a permission policy file with those given
+  /// with `flags`, under the rules of `fork`: the flags may deny more than the
+  /// policy, but not grant anything it doesn't.
+  pub fn merge(&self, flags: Self) -> Result<Self, AnyError> {
+    let this = self.clone();
+    self
+      .fork(
+        this.read.merge(flags.read),
+        this.write.merge(flags.write),
+        this.net.merge(flags.net),
+        this.env.merge(flags.env),
+        this.run.merge(flags.run),
+        this.plugin.merge(flags.plugin),
+        this.hrtime.merge(flags.hrtime),
+      )
+      .map_err(|_| {
+        custom_error(
+          "PermissionDenied",
+          "Permission flags escalate the permissions of the policy file",
+        )
+      })
+  }
+
   /// Arbitrary helper. Resolves the path from CWD, and also gets a path that
   /// can be displayed without leaking the CWD when not allowed.
   fn resolved_and_display_path(&self, path: &Path) -> (PathBuf, PathBuf) {
@@ -187,8 +350,14 @@ impl Permissions {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       plugin: PermissionState::Granted,
       hrtime: PermissionState::Granted,
     }
@@ -196,12 +365,7 @@ impl Permissions {
 
   pub fn query_read(&self, path: &Option<&Path>) -> PermissionState {
     let path = path.map(|p| resolve_from_cwd(p).unwrap());
-    if self.read.global_state == PermissionState::Denied
-      && match path.as_ref() {
-        None => true,
-        Some(path) => check_path_blocklist(path, &self.read.denied_list),
-      }
-    {
+    if self.read.is_path_denied(path.as_deref()) {
       return PermissionState::Denied;
     }
     if self.read.global_state == PermissionState::Granted
@@ -217,12 +381,7 @@ impl Permissions {
 
   pub fn query_write(&self, path: &Option<&Path>) -> PermissionState {
     let path = path.map(|p| resolve_from_cwd(p).unwrap());
-    if self.write.global_state == PermissionState::Denied
-      && match path.as_ref() {
-        None => true,
-        Some(path) => check_path_blocklist(path, &self.write.denied_list),
-      }
-    {
+    if self.write.is_path_denied(path.as_deref()) {
       return PermissionState::Denied;
     }
     if self.write.global_state == PermissionState::Granted
@@ -274,12 +433,55 @@ impl Permissions {
     ))
   }
 
-  pub fn query_env(&self) -> PermissionState {
-    self.env
+  pub fn query_env(&self, var: &Option<&str>) -> PermissionState {
+    let var = var.map(env_key);
+    if self.env.global_state == PermissionState::Denied
+      || match var.as_ref() {
+        None => false,
+        Some(var) => self.env.denied_list.contains(var),
+      }
+    {
+      return PermissionState::Denied;
+    }
+    if self.env.global_state == PermissionState::Granted
+      || match var.as_ref() {
+        None => false,
+        Some(var) => self.env.granted_list.contains(var),
+      }
+    {
+      return PermissionState::Granted;
+    }
+    PermissionState::Prompt
+  }
+
+  pub fn query_run(&self, cmd: &Option<&str>) -> PermissionState {
+    self.query_run_in(cmd, None)
   }
 
-  pub fn query_run(&self) -> PermissionState {
-    self.run
+  /// Queries the permission to run `cmd` in `cwd`, the working directory of
+  /// the subprocess, from which a command given as a path is resolved.
+  fn query_run_in(
+    &self,
+    cmd: &Option<&str>,
+    cwd: Option<&Path>,
+  ) -> PermissionState {
+    if self.run.global_state == PermissionState::Denied
+      || match cmd {
+        None => false,
+        Some(cmd) => check_run_list(cmd, cwd, &self.run.denied_list),
+      }
+    {
+      return PermissionState::Denied;
+    }
+    if self.run.global_state == PermissionState::Granted
+      || match cmd {
+        None => false,
+        Some(cmd) => check_run_list(cmd, cwd, &self.run.granted_list),
+      }
+    {
+      return PermissionState::Granted;
+    }
+    PermissionState::Prompt
   }
 
   pub fn query_plugin(&self) -> PermissionState {
@@ -295,35 +497,25 @@ impl Permissions {
       let (resolved_path, display_path) = self.resolved_and_display_path(path);
       let state = self.query_read(&Some(&resolved_path));
       if state == PermissionState::Prompt {
-        if permission_prompt(&format!(
-          "Deno requests read access to \"{}\"",
-          display_path.display()
-        )) {
-          self
-            .read
-            .granted_list
-            .retain(|path| !path.starts_with(&resolved_path));
-          self.read.granted_list.insert(resolved_path);
-          return PermissionState::Granted;
-        } else {
-          self
-            .read
-            .denied_list
-            .retain(|path| !resolved_path.starts_with(path));
-          self.read.denied_list.insert(resolved_path);
-          self.read.global_state = PermissionState::Denied;
-          return PermissionState::Denied;
-        }
+        let response = permission_prompt(
+          &format!(
+            "Deno requests read access to \"{}\"",
+            display_path.display()
+          ),
+          &[GRANT_PARENT_DIR, GRANT_ALL],
+        );
+        return self
+          .read
+          .answer_path_prompt("read", resolved_path, response);
       }
       state
     } else {
       let state = self.query_read(&None);
       if state == PermissionState::Prompt {
-        if permission_prompt("Deno requests read access") {
-          self.read.granted_list.clear();
-          self.read.global_state = PermissionState::Granted;
-          return PermissionState::Granted;
+        if permission_prompt("Deno requests read access", &[]).is_granted() {
+          return self.read.grant_all("read");
         } else {
+          save_answer("read", None, false);
           self.read.global_state = PermissionState::Denied;
           return PermissionState::Denied;
         }
@@ -337,35 +529,25 @@ impl Permissions {
       let (resolved_path, display_path) = self.resolved_and_display_path(path);
       let state = self.query_write(&Some(&resolved_path));
       if state == PermissionState::Prompt {
-        if permission_prompt(&format!(
-          "Deno requests write access to \"{}\"",
-          display_path.display()
-        )) {
-          self
-            .write
-            .granted_list
-            .retain(|path| !path.starts_with(&resolved_path));
-          self.write.granted_list.insert(resolved_path);
-          return PermissionState::Granted;
-        } else {
-          self
-            .write
-            .denied_list
-            .retain(|path| !resolved_path.starts_with(path));
-          self.write.denied_list.insert(resolved_path);
-          self.write.global_state = PermissionState::Denied;
-          return PermissionState::Denied;
-        }
+        let response = permission_prompt(
+          &format!(
+            "Deno requests write access to \"{}\"",
+            display_path.display()
+          ),
+          &[GRANT_PARENT_DIR, GRANT_ALL],
+        );
+        return self
+          .write
+          .answer_path_prompt("write", resolved_path, response);
       }
       state
     } else {
       let state = self.query_write(&None);
       if state == PermissionState::Prompt {
-        if permission_prompt("Deno requests write access") {
-          self.write.granted_list.clear();
-          self.write.global_state = PermissionState::Granted;
-          return PermissionState::Granted;
+        if permission_prompt("Deno requests write access", &[]).is_granted() {
+          return self.write.grant_all("write");
         } else {
+          save_answer("write", None, false);
           self.write.global_state = PermissionState::Denied;
           return PermissionState::Denied;
         }
@@ -381,27 +563,44 @@ impl Permissions {
     if let Some(url) = url {
       let state = self.query_net_url(&Some(url))?;
       if state == PermissionState::Prompt {
-        if permission_prompt(&format!(
-          "Deno requests network access to \"{}\"",
-          url
-        )) {
-          self.net.granted_list.insert(url.to_string());
-          return Ok(PermissionState::Granted);
-        } else {
-          self.net.denied_list.insert(url.to_string());
-          self.net.global_state = PermissionState::Denied;
-          return Ok(PermissionState::Denied);
+        // `query_net_url` made sure that the URL has a host.
+        let parsed = url::Url::parse(url)?;
+        let host = parsed.host().unwrap().to_string();
+        let host_and_port = match parsed.port_or_known_default() {
+          Some(port) => format!("{}:{}", host, port),
+          None => host.clone(),
+        };
+        let response = permission_prompt(
+          &format!("Deno requests network access to \"{}\"", url),
+          &[GRANT_HOST, GRANT_ALL],
+        );
+        match response {
+          PromptResponse::Grant => {
+            save_answer("net", Some(host_and_port), true);
+            self.net.granted_list.insert(url.to_string());
+          }
+          PromptResponse::GrantScope => {
+            save_answer("net", Some(host.clone()), true);
+            self.net.granted_list.insert(host);
+          }
+          PromptResponse::GrantAll => return Ok(self.net.grant_all("net")),
+          PromptResponse::Deny => {
+            save_answer("net", Some(host_and_port), false);
+            self.net.denied_list.insert(url.to_string());
+            self.net.global_state = PermissionState::Denied;
+            return Ok(PermissionState::Denied);
+          }
         }
+        return Ok(PermissionState::Granted);
       }
       Ok(state)
     } else {
       let state = self.query_net_url(&None)?;
       if state == PermissionState::Prompt {
-        if permission_prompt("Deno requests network access") {
-          self.net.granted_list.clear();
-          self.net.global_state = PermissionState::Granted;
-          return Ok(PermissionState::Granted);
+        if permission_prompt("Deno requests network access", &[]).is_granted() {
+          return Ok(self.net.grant_all("net"));
         } else {
+          save_answer("net", None, false);
           self.net.global_state = PermissionState::Denied;
           return Ok(PermissionState::Denied);
         }
@@ -410,33 +609,103 @@ impl Permissions {
     }
   }
 
-  pub fn request_env(&mut self) -> PermissionState {
-    if self.env == PermissionState::Prompt {
-      if permission_prompt("Deno requests access to environment variables") {
-        self.env = PermissionState::Granted;
-      } else {
-        self.env = PermissionState::Denied;
+  pub fn request_env(&mut self, var: &Option<&str>) -> PermissionState {
+    if let Some(var) = var {
+      let state = self.query_env(&Some(var));
+      if state == PermissionState::Prompt {
+        let response = permission_prompt(
+          &format!(
+            "Deno requests access to the \"{}\" environment variable",
+            var
+          ),
+          &[GRANT_ALL],
+        );
+        match response {
+          PromptResponse::Grant | PromptResponse::GrantScope => {
+            save_answer("env", Some(env_key(var)), true);
+            self.env.granted_list.insert(env_key(var));
+            return PermissionState::Granted;
+          }
+          PromptResponse::GrantAll => return self.env.grant_all("env"),
+          PromptResponse::Deny => {
+            save_answer("env", Some(env_key(var)), false);
+            self.env.denied_list.insert(env_key(var));
+            self.env.global_state = PermissionState::Denied;
+            return PermissionState::Denied;
+          }
+        }
       }
+      state
+    } else {
+      let state = self.query_env(&None);
+      if state == PermissionState::Prompt {
+        if permission_prompt(
+          "Deno requests access to environment variables",
+          &[],
+        )
+        .is_granted()
+        {
+          return self.env.grant_all("env");
+        } else {
+          save_answer("env", None, false);
+          self.env.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
+      }
+      state
     }
-    self.env
   }
 
-  pub fn request_run(&mut self) -> PermissionState {
-    if self.run == PermissionState::Prompt {
-      if permission_prompt("Deno requests to access to run a subprocess") {
-        self.run = PermissionState::Granted;
-      } else {
-        self.run = PermissionState::Denied;
+  pub fn request_run(&mut self, cmd: &Option<&str>) -> PermissionState {
+    if let Some(cmd) = cmd {
+      let state = self.query_run(&Some(cmd));
+      if state == PermissionState::Prompt {
+        let response = permission_prompt(
+          &format!("Deno requests to run \"{}\"", cmd),
+          &[GRANT_ALL],
+        );
+        match response {
+          PromptResponse::Grant | PromptResponse::GrantScope => {
+            let entry = resolve_run_entry(cmd);
+            save_answer("run", Some(entry.clone()), true);
+            self.run.granted_list.insert(entry);
+            return PermissionState::Granted;
+          }
+          PromptResponse::GrantAll => return self.run.grant_all("run"),
+          PromptResponse::Deny => {
+            let entry = resolve_run_entry(cmd);
+            save_answer("run", Some(entry.clone()), false);
+            self.run.denied_list.insert(entry);
+            self.run.global_state = PermissionState::Denied;
+            return PermissionState::Denied;
+          }
+        }
       }
+      state
+    } else {
+      let state = self.query_run(&None);
+      if state == PermissionState::Prompt {
+        if permission_prompt("Deno requests to access to run a subprocess", &[])
+          .is_granted()
+        {
+          return self.run.grant_all("run");
+        } else {
+          save_answer("run", None, false);
+          self.run.global_state = PermissionState::Denied;
+          return PermissionState::Denied;
+        }
+      }
+      state
     }
-    self.run
   }
 
   pub fn request_plugin(&mut self) -> PermissionState {
     if self.plugin == PermissionState::Prompt {
-      if permission_prompt("Deno requests to open plugins") {
+      if permission_prompt("Deno requests to open plugins", &[]).is_granted() {
+        save_answer("plugin", None, true);
         self.plugin = PermissionState::Granted;
       } else {
+        save_answer("plugin", None, false);
         self.plugin = PermissionState::Denied;
       }
     }
@@ -445,9 +714,13 @@ impl Permissions {
 
   pub fn request_hrtime(&mut self) -> PermissionState {
     if self.hrtime == PermissionState::Prompt {
-      if permission_prompt("Deno requests access to high precision time") {
+      if permission_prompt("Deno requests access to high precision time", &[])
+        .is_granted()
+      {
+        save_answer("hrtime", None, true);
         self.hrtime = PermissionState::Granted;
       } else {
+        save_answer("hrtime", None, false);
         self.hrtime = PermissionState::Denied;
       }
     }
@@ -501,18 +774,36 @@ impl Permissions {
     self.query_net_url(url)
   }
 
-  pub fn revoke_env(&mut self) -> PermissionState {
-    if self.env == PermissionState::Granted {
-      self.env = PermissionState::Prompt;
+  pub fn revoke_env(&mut self, var: &Option<&str>) -> PermissionState {
+    if let Some(var) = var {
+      self.env.granted_list.remove(&env_key(var));
+    } else {
+      self.env.granted_list.clear();
+      if self.env.global_state == PermissionState::Granted {
+        self.env.global_state = PermissionState::Prompt;
+      }
     }
-    self.env
+    self.query_env(var)
   }
 
-  pub fn revoke_run(&mut self) -> PermissionState {
-    if self.run == PermissionState::Granted {
-      self.run = PermissionState::Prompt;
+  pub fn revoke_run(&mut self, cmd: &Option<&str>) -> PermissionState {
+    if let Some(cmd) = cmd {
+      let granted_list = &mut self.run.granted_list;
+      let revoked: Vec<String> = granted_list
+        .iter()
+        .filter(|entry| run_entry_matches(entry, cmd, None))
+        .cloned()
+        .collect();
+      for entry in revoked {
+        granted_list.remove(&entry);
+      }
+    } else {
+      self.run.granted_list.clear();
+      if self.run.global_state == PermissionState::Granted {
+        self.run.global_state = PermissionState::Prompt;
+      }
     }
-    self.run
+    self.query_run(cmd)
   }
 
   pub fn revoke_plugin(&mut self) -> PermissionState {
@@ -531,10 +822,13 @@ impl Permissions {
 
   pub fn check_read(&self, path: &Path) -> Result<(), AnyError> {
     let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.query_read(&Some(&resolved_path)).check(
-      &format!("read access to \"{}\"", display_path.display()),
-      "--allow-read",
-    )
+    self
+      .query_read(&Some(&resolved_path))
+      .audit("read", Some(resolved_path.display().to_string()))
+      .check(
+        &format!("read access to \"{}\"", display_path.display()),
+        "--allow-read",
+      )
   }
 
   /// As `check_read()`, but permission error messages will anonymize the path
@@ -547,52 +841,172 @@ impl Permissions {
     let resolved_path = resolve_from_cwd(path).unwrap();
     self
       .query_read(&Some(&resolved_path))
+      .audit("read", Some(resolved_path.display().to_string()))
       .check(&format!("read access to <{}>", display), "--allow-read")
   }
 
   pub fn check_write(&self, path: &Path) -> Result<(), AnyError> {
     let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.query_write(&Some(&resolved_path)).check(
-      &format!("write access to \"{}\"", display_path.display()),
-      "--allow-write",
-    )
+    self
+      .query_write(&Some(&resolved_path))
+      .audit("write", Some(resolved_path.display().to_string()))
+      .check(
+        &format!("write access to \"{}\"", display_path.display()),
+        "--allow-write",
+      )
   }
 
   pub fn check_net(&self, hostname: &str, port: u16) -> Result<(), AnyError> {
-    self.query_net(hostname, Some(port)).check(
-      &format!("network access to \"{}:{}\"", hostname, port),
-      "--allow-net",
-    )
+    self
+      .query_net(hostname, Some(port))
+      .audit("net", Some(format!("{}:{}", hostname, port)))
+      .check(
+        &format!("network access to \"{}:{}\"", hostname, port),
+        "--allow-net",
+      )
+  }
+
+  /// Check access to `hostname` before it is resolved to the address that
+  /// `check_net_addr` checks. A host name that isn't granted itself passes if
+  /// IP address rules may grant the address.
+  pub fn check_net_lookup(
+    &self,
+    hostname: &str,
+    port: u16,
+  ) -> Result<(), AnyError> {
+    if self.query_net(hostname, Some(port)) == PermissionState::Prompt
+      && flags_allow_net::parse_ip(hostname).is_none()
+      && self.net.granted_list.iter().any(|e| is_ip_net_entry(e))
+    {
+      return Ok(());
+    }
+    self.check_net(hostname, port)
+  }
+
+  /// Check access to `addr`, which `hostname` resolved to, after
+  /// `check_net_lookup`. Both the host name and the address must not be
+  /// denied, so that a host name can't be used to reach a denied address,
+  /// and one of them must be granted.
+  pub fn check_net_addr(
+    &self,
+    hostname: &str,
+    addr: &SocketAddr,
+  ) -> Result<(), AnyError> {
+    if !self.has_ip_net_rules() {
+      // `check_net_lookup` already made the same decision.
+      return Ok(());
+    }
+    let ip = match flags_allow_net::ipv4_mapped(&addr.ip()) {
+      IpAddr::V4(ip) => ip.to_string(),
+      IpAddr::V6(ip) => format!("[{}]", ip),
+    };
+    let port = Some(addr.port());
+    let net = &self.net;
+    let state = if net.global_state == PermissionState::Denied
+      || check_host_and_port_list(hostname, port, &net.denied_list)
+      || check_host_and_port_list(&ip, port, &net.denied_list)
+    {
+      PermissionState::Denied
+    } else if net.global_state == PermissionState::Granted
+      || check_host_and_port_list(hostname, port, &net.granted_list)
+      || check_host_and_port_list(&ip, port, &net.granted_list)
+    {
+      PermissionState::Granted
+    } else {
+      PermissionState::Prompt
+    };
+    state
+      .audit("net", Some(format!("{}:{}", hostname, addr.port())))
+      .check(
+        &format!(
+          "network access to \"{}:{}\" ({})",
+          hostname,
+          addr.port(),
+          addr.ip()
+        ),
+        "--allow-net",
+      )
+  }
+
+  /// Whether `check_net_addr` may deny an address that `check_net_lookup`
+  /// let through, i.e. whether there are IP address rules.
+  pub fn has_ip_net_rules(&self) -> bool {
+    self
+      .net
+      .granted_list
+      .iter()
+      .chain(&self.net.denied_list)
+      .any(|entry| is_ip_net_entry(entry))
   }
 
   pub fn check_net_url(&self, url: &url::Url) -> Result<(), AnyError> {
     let host = url.host_str().ok_or_else(|| uri_error("missing host"))?;
+    let target = match url.port_or_known_default() {
+      Some(port) => format!("{}:{}", host, port),
+      None => host.to_string(),
+    };
     self
       .query_net(host, url.port_or_known_default())
+      .audit("net", Some(target))
       .check(&format!("network access to \"{}\"", url), "--allow-net")
   }
 
-  pub fn check_env(&self) -> Result<(), AnyError> {
+  pub fn check_env(&self, var: &str) -> Result<(), AnyError> {
     self
-      .env
+      .query_env(&Some(var))
+      .audit("env", Some(var.to_string()))
+      .check(
+        &format!("access to the \"{}\" environment variable", var),
+        "--allow-env",
+      )
+  }
+
+  /// Checks that all environment variables may be accessed, which is required
+  /// for operations that expose information about the whole system.
+  pub fn check_env_all(&self) -> Result<(), AnyError> {
+    self
+      .query_env(&None)
+      .audit("env", None)
       .check("access to environment variables", "--allow-env")
   }
 
-  pub fn check_run(&self) -> Result<(), AnyError> {
-    self.run.check("access to run a subprocess", "--allow-run")
+  /// Checks that `cmd` may be run in `cwd`, the working directory of the
+  /// subprocess, or the CWD when it is `None`.
+  pub fn check_run(
+    &self,
+    cmd: &str,
+    cwd: Option<&Path>,
+  ) -> Result<(), AnyError> {
+    self
+      .query_run_in(&Some(cmd), cwd)
+      .audit("run", Some(cmd.to_string()))
+      .check(&format!("access to run \"{}\"", cmd), "--allow-run")
+  }
+
+  /// Checks that any subprocess may be run, which is required for operations
+  /// that are not tied to a command, like sending signals to a process.
+  pub fn check_run_all(&self) -> Result<(), AnyError> {
+    self
+      .query_run(&None)
+      .audit("run", None)
+      .check("access to run a subprocess", "--allow-run")
   }
 
   pub fn check_plugin(&self, path: &Path) -> Result<(), AnyError> {
-    let (_, display_path) = self.resolved_and_display_path(path);
-    self.plugin.check(
-      &format!("access to open a plugin: {}", display_path.display()),
-      "--allow-plugin",
-    )
+    let (resolved_path, display_path) = self.resolved_and_display_path(path);
+    self
+      .plugin
+      .audit("plugin", Some(resolved_path.display().to_string()))
+      .check(
+        &format!("access to open a plugin: {}", display_path.display()),
+        "--allow-plugin",
+      )
   }
 
   pub fn check_hrtime(&self) -> Result<(), AnyError> {
     self
       .hrtime
+      .audit("hrtime", None)
       .check("access to high precision time", "--allow-hrtime")
   }
 
@@ -602,16 +1016,28 @@ impl Permissions {
     read: UnaryPermission<PathBuf>,
     write: UnaryPermission<PathBuf>,
     net: UnaryPermission<String>,
-    env: PermissionState,
-    run: PermissionState,
+    env: UnaryPermission<String>,
+    run: UnaryPermission<String>,
     plugin: PermissionState,
     hrtime: PermissionState,
   ) -> Result<Permissions, AnyError> {
-    self.read.check_fork(&read)?;
-    self.write.check_fork(&write)?;
-    self.net.check_fork(&net)?;
-    self.env.check_fork(&env)?;
-    self.run.check_fork(&run)?;
+    let granted = |state| state == PermissionState::Granted;
+    self
+      .read
+      .check_fork(&read, |path| granted(self.query_read(&Some(path))))?;
+    self
+      .write
+      .check_fork(&write, |path| granted(self.query_write(&Some(path))))?;
+    self.net.check_fork(&net, |entry| {
+      let (host, port) = split_net_entry(entry);
+      granted(self.query_net(host, port))
+    })?;
+    self
+      .env
+      .check_fork(&env, |var| granted(self.query_env(&Some(var))))?;
+    self
+      .run
+      .check_fork(&run, |cmd| granted(self.query_run(&Some(cmd))))?;
     self.plugin.check_fork(&plugin)?;
     self.hrtime.check_fork(&hrtime)?;
     Ok(Permissions {
@@ -628,25 +1054,96 @@ impl Permissions {
 
 impl deno_fetch::FetchPermissions for Permissions {
   fn check_net_url(&self, url: &url::Url) -> Result<(), AnyError> {
+    if self.has_ip_net_rules() {
+      let host = url.host_str().ok_or_else(|| uri_error("missing host"))?;
+      let port = url.port_or_known_default().unwrap_or(0);
+      return self.check_net_lookup(host, port);
+    }
     Permissions::check_net_url(self, url)
   }
 
   fn check_read(&self, p: &PathBuf) -> Result<(), AnyError> {
     Permissions::check_read(self, p)
   }
+
+  fn checks_net_addrs(&self) -> bool {
+    self.has_ip_net_rules()
+  }
+
+  fn check_net_addr(
+    &self,
+    url: &url::Url,
+    addr: &SocketAddr,
+  ) -> Result<(), AnyError> {
+    let host = url.host_str().ok_or_else(|| uri_error("missing host"))?;
+    Permissions::check_net_addr(self, host, addr)
+  }
 }
 
+/// An answer to a permission prompt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PromptResponse {
+  /// Grant the requested path, host, variable or command.
+  Grant,
+  /// Grant the parent directory of the requested path, or every port of the
+  /// requested host.
+  GrantScope,
+  /// Grant the whole permission for the rest of the session.
+  GrantAll,
+  Deny,
+}
+
+impl PromptResponse {
+  fn is_granted(self) -> bool {
+    self != PromptResponse::Deny
+  }
+}
+
+/// An answer offered by a permission prompt besides "grant" and "deny".
+struct PromptChoice {
+  key: char,
+  help: &'static str,
+  response: PromptResponse,
+}
+
+const GRANT_PARENT_DIR: PromptChoice = PromptChoice {
+  key: 'p',
+  help: "grant the parent directory",
+  response: PromptResponse::GrantScope,
+};
+
+const GRANT_HOST: PromptChoice = PromptChoice {
+  key: 'h',
+  help: "grant any port of the host",
+  response: PromptResponse::GrantScope,
+};
+
+const GRANT_ALL: PromptChoice = PromptChoice {
+  key: 'a',
+  help: "grant all",
+  response: PromptResponse::GrantAll,
+};
+
 /// Shows the permission prompt and returns the answer according to the user input.
 /// This loops until the user gives the proper input.
 #[cfg(not(test))]
-fn permission_prompt(message: &str) -> bool {
+fn permission_prompt(
+  message: &str,
+  choices: &[PromptChoice],
+) -> PromptResponse {
   if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
-    return false;
+    return PromptResponse::Deny;
   };
-  let msg = format!(
-    "️{}  {}. Grant? [g/d (g = grant, d = deny)] ",
-    PERMISSION_EMOJI, message
-  );
+  let mut keys = vec!["g".to_string()];
+  let mut help = vec!["g = grant".to_string()];
+  for choice in choices {
+    keys.push(choice.key.to_string());
+    help.push(format!("{} = {}", choice.key, choice.help));
+  }
+  keys.push("d".to_string());
+  help.push("d = deny".to_string());
+  let options = format!("[{} ({})]", keys.join("/"), help.join(", "));
+  let msg = format!("️{}  {}. Grant? {} ", PERMISSION_EMOJI, message, options);
   // print to stderr so that if deno is > to a file this is still displayed.
   eprint!("{}", colors::bold(&msg));
   loop {
@@ -654,16 +1151,18 @@ fn permission_prompt(message: &str) -> bool {
     let stdin = io::stdin();
     let result = stdin.read_line(&mut input);
     if result.is_err() {
-      return false;
+      return PromptResponse::Deny;
     };
     let ch = input.chars().next().unwrap();
     match ch.to_ascii_lowercase() {
-      'g' => return true,
-      'd' => return false,
-      _ => {
+      'g' => return PromptResponse::Grant,
+      'd' => return PromptResponse::Deny,
+      ch => {
+        if let Some(choice) = choices.iter().find(|choice| choice.key == ch) {
+          return choice.response;
+        }
         // If we don't get a recognized option try again.
-        let msg_again =
-          format!("Unrecognized option '{}' [g/d (g = grant, d = deny)] ", ch);
+        let msg_again = format!("Unrecognized option '{}' {} ", ch, options);
         eprint!("{}", colors::bold(&msg_again));
       }
     };
@@ -674,21 +1173,41 @@ fn permission_prompt(message: &str) -> bool {
 lazy_static! {
   /// Lock this when you use `set_prompt_result` in a test case.
   static ref PERMISSION_PROMPT_GUARD: Mutex<()> = Mutex::new(());
+  static ref STUB_PROMPT_RESPONSE: Mutex<PromptResponse> =
+    Mutex::new(PromptResponse::Grant);
 }
 
 #[cfg(test)]
-static STUB_PROMPT_VALUE: AtomicBool = AtomicBool::new(true);
+fn set_prompt_result(value: bool) {
+  set_prompt_response(if value {
+    PromptResponse::Grant
+  } else {
+    PromptResponse::Deny
+  });
+}
 
 #[cfg(test)]
-fn set_prompt_result(value: bool) {
-  STUB_PROMPT_VALUE.store(value, Ordering::SeqCst);
+fn set_prompt_response(response: PromptResponse) {
+  *STUB_PROMPT_RESPONSE.lock().unwrap() = response;
 }
 
-// When testing, permission prompt returns the value of STUB_PROMPT_VALUE
-// which we set from the test functions.
+// When testing, permission prompt returns the value of STUB_PROMPT_RESPONSE
+// which we set from the test functions. A choice the prompt doesn't offer
+// falls back to "grant".
 #[cfg(test)]
-fn permission_prompt(_message: &str) -> bool {
-  STUB_PROMPT_VALUE.load(Ordering::SeqCst)
+fn permission_prompt(
+  _message: &str,
+  choices: &[PromptChoice],
+) -> PromptResponse {
+  let response = *STUB_PROMPT_RESPONSE.lock().unwrap();
+  match response {
+    PromptResponse::GrantScope | PromptResponse::GrantAll
+      if !choices.iter().any(|choice| choice.response == response) =>
+    {
+      PromptResponse::Grant
+    }
+    _ => response,
+  }
 }
 
 fn log_perm_access(message: &str) {
@@ -721,9 +1240,161 @@ fn check_host_and_port_list(
   port: Option<u16>,
   allowlist: &HashSet<String>,
 ) -> bool {
+  let host = strip_trailing_dot(host);
   allowlist.contains(host)
     || (port.is_some()
       && allowlist.contains(&format!("{}:{}", host, port.unwrap())))
+    || allowlist.iter().any(|entry| {
+      let (entry_host, entry_port) = split_net_entry(entry);
+      strip_trailing_dot(entry_host) == host
+        && (entry_port.is_none() || entry_port == port)
+        || net_pattern_matches(entry, host, port)
+    })
+}
+
+/// A fully qualified host name may end with a dot, "example.com." is the same
+/// host as "example.com".
+fn strip_trailing_dot(host: &str) -> &str {
+  host.strip_suffix('.').unwrap_or(host)
+}
+
+/// Returns whether a `--allow-net` entry with a pattern, a wildcard subdomain
+/// like "*.example.com" or an IP address range like "10.0.0.0/8", matches
+/// the host and port. An IP address is a range of a single address, so that
+/// an IPv4-mapped IPv6 address matches the IPv4 address.
+fn net_pattern_matches(entry: &str, host: &str, port: Option<u16>) -> bool {
+  if let Some((range, entry_port)) =
+    flags_allow_net::parse_ip_range_entry(entry)
+  {
+    return (entry_port.is_none() || entry_port == port)
+      && match flags_allow_net::parse_ip(host) {
+        Some(ip) => range.contains(&ip),
+        None => false,
+      };
+  }
+  let (pattern, entry_port) = split_net_entry(entry);
+  let pattern = strip_trailing_dot(pattern);
+  let host = strip_trailing_dot(host);
+  if let Some(entry_ip) = flags_allow_net::parse_ip(pattern) {
+    return (entry_port.is_none() || entry_port == port)
+      && match flags_allow_net::parse_ip(host) {
+        Some(ip) => {
+          flags_allow_net::ipv4_mapped(&entry_ip)
+            == flags_allow_net::ipv4_mapped(&ip)
+        }
+        None => false,
+      };
+  }
+  match pattern.strip_prefix('*') {
+    Some(suffix) if entry_port.is_none() || entry_port == port => host
+      .to_ascii_lowercase()
+      .ends_with(&suffix.to_ascii_lowercase()),
+    _ => false,
+  }
+}
+
+/// Returns whether a `--allow-net` entry is about IP addresses, which
+/// resolved host names are checked against.
+fn is_ip_net_entry(entry: &str) -> bool {
+  flags_allow_net::parse_ip_range_entry(entry).is_some()
+    || flags_allow_net::parse_ip(split_net_entry(entry).0).is_some()
+}
+
+/// Splits a `--allow-net` entry into its host and optional port.
+fn split_net_entry(entry: &str) -> (&str, Option<u16>) {
+  if entry.parse::<IpAddr>().is_ok() {
+    return (entry, None);
+  }
+  match entry.rfind(':') {
+    Some(index) => match entry[index + 1..].parse::<u16>() {
+      Ok(port) => (&entry[..index], Some(port)),
+      Err(_) => (entry, None),
+    },
+    None => (entry, None),
+  }
+}
+
+/// Environment variable names are case insensitive on Windows.
+pub(crate) fn env_key(var: &str) -> String {
+  if cfg!(windows) {
+    var.to_uppercase()
+  } else {
+    var.to_string()
+  }
+}
+
+/// Whether a command is a path, rather than a name that is looked up in
+/// `PATH`.
+pub(crate) fn is_command_path(cmd: &str) -> bool {
+  let path = Path::new(cmd);
+  path.is_absolute() || path.components().count() > 1
+}
+
+/// Resolves a command given as a path from `cwd`, the working directory of the
+/// subprocess, or the CWD when it is `None`. A name is returned as is.
+pub(crate) fn resolve_command(cmd: &str, cwd: Option<&Path>) -> PathBuf {
+  if !is_command_path(cmd) {
+    return PathBuf::from(cmd);
+  }
+  let cwd = match cwd {
+    Some(cwd) => resolve_from_cwd(cwd).unwrap(),
+    None => current_dir().unwrap(),
+  };
+  normalize_path(&cwd.join(cmd))
+}
+
+/// Resolves a `--allow-run` or `--deny-run` entry given as a path from the
+/// CWD, like the entries of `--allow-read`. Names are kept as they are.
+fn resolve_run_entry(entry: &str) -> String {
+  resolve_command(entry, None).display().to_string()
+}
+
+/// Resolves the command of a subprocess to the executable that is run, with
+/// its symlinks resolved: a path is resolved from `cwd`, a name is looked up
+/// in `PATH`.
+fn resolve_executable(cmd: &str, cwd: Option<&Path>) -> Option<PathBuf> {
+  if is_command_path(cmd) {
+    return Some(canonicalize_path(&resolve_command(cmd, cwd)));
+  }
+  let paths = env::var_os("PATH")?;
+  env::split_paths(&paths).find_map(|dir| {
+    let candidate = resolve_from_cwd(&dir.join(cmd)).ok()?;
+    if candidate.is_file() {
+      return Some(canonicalize_path(&candidate));
+    }
+    #[cfg(windows)]
+    {
+      let candidate = candidate.with_extension("exe");
+      if candidate.is_file() {
+        return Some(canonicalize_path(&candidate));
+      }
+    }
+    None
+  })
+}
+
+/// Returns whether a `--allow-run` entry, a name or an absolute path, allows
+/// the command run in `cwd`: either both are the same name or they resolve to
+/// the same executable.
+fn run_entry_matches(entry: &str, cmd: &str, cwd: Option<&Path>) -> bool {
+  if !is_command_path(entry) && entry == cmd {
+    return true;
+  }
+  match (
+    resolve_executable(entry, None),
+    resolve_executable(cmd, cwd),
+  ) {
+    (Some(entry), Some(cmd)) => entry == cmd,
+    _ => false,
+  }
+}
+
+fn check_run_list(
+  cmd: &str,
+  cwd: Option<&Path>,
+  list: &HashSet<String>,
+) -> bool {
+  list.iter().any(|entry| run_entry_matches(entry, cmd, cwd))
 }
 
 fn permission_escalation_error() -> AnyError {
@@ -801,6 +1472,61 @@ mod tests {
     assert!(perms.check_write(Path::new("/a/b")).is_err());
   }
 
+  #[test]
+  fn check_denylists() {
+    let perms = Permissions::from_flags(&Flags {
+      allow_read: true,
+      allow_write: true,
+      allow_net: true,
+      allow_env: true,
+      allow_run: true,
+      read_denylist: vec![PathBuf::from("/etc/secrets")],
+      write_denylist: vec![PathBuf::from("/project/.git")],
+      net_denylist: svec!["169.254.169.254", "localhost:9229"],
+      env_denylist: svec!["AWS_SECRET_ACCESS_KEY"],
+      run_denylist: svec!["curl"],
+      ..Default::default()
+    });
+
+    assert!(perms.check_read(Path::new("/etc/hosts")).is_ok());
+    assert!(perms.check_read(Path::new("/etc/secrets")).is_err());
+    assert!(perms.check_read(Path::new("/etc/secrets/key.pem")).is_err());
+    assert!(perms.check_write(Path::new("/project/main.ts")).is_ok());
+    assert!(perms.check_write(Path::new("/project/.git/HEAD")).is_err());
+    assert!(perms.check_net("deno.land", 443).is_ok());
+    assert!(perms.check_net("169.254.169.254", 80).is_err());
+    assert!(perms.check_net("localhost", 8000).is_ok());
+    assert!(perms.check_net("localhost", 9229).is_err());
+    assert!(perms.check_env("HOME").is_ok());
+    assert!(perms.check_env("AWS_SECRET_ACCESS_KEY").is_err());
+    assert!(perms.check_run("git", None).is_ok());
+    assert!(perms.check_run("curl", None).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn check_denylists_symlink() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    std::fs::create_dir(dir.join("secrets")).unwrap();
+    std::os::unix::fs::symlink(dir.join("secrets"), dir.join("link")).unwrap();
+    let perms = Permissions::from_flags(&Flags {
+      allow_read: true,
+      allow_write: true,
+      read_denylist: vec![dir.join("secrets")],
+      write_denylist: vec![dir.join("link")],
+      ..Default::default()
+    });
+
+    assert!(perms.check_read(&dir.join("link").join("key.pem")).is_err());
+    assert!(perms.check_read(&dir.join("link")).is_err());
+    assert!(perms.check_read(&dir.join("other")).is_ok());
+    assert!(perms
+      .check_write(&dir.join("secrets").join("key.pem"))
+      .is_err());
+    assert!(perms.check_write(&dir.join("other")).is_ok());
+  }
+
   #[test]
   fn test_check_net() {
     let perms = Permissions::from_flags(&Flags {
@@ -887,6 +1613,258 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_check_net_patterns() {
+    let perms = Permissions::from_flags(&Flags {
+      net_allowlist: svec!["10.0.0.0/8", "*.internal.example.com:443"],
+      net_denylist: svec!["10.0.0.0/24"],
+      ..Default::default()
+    });
+
+    let domain_tests = vec![
+      ("10.1.2.3", 80, true),
+      ("10.255.0.1", 5432, true),
+      ("10.0.0.5", 80, false),
+      ("11.0.0.1", 80, false),
+      ("db.internal.example.com", 443, true),
+      ("a.b.internal.example.com", 443, true),
+      ("DB.Internal.Example.com", 443, true),
+      ("db.internal.example.com", 80, false),
+      ("internal.example.com", 443, false),
+      ("evilinternal.example.com", 443, false),
+    ];
+
+    for (host, port, is_ok) in domain_tests {
+      assert_eq!(is_ok, perms.check_net(host, port).is_ok(), "{}", host);
+    }
+  }
+
+  #[test]
+  fn test_check_net_trailing_dot() {
+    let perms = Permissions::from_flags(&Flags {
+      allow_net: true,
+      net_denylist: svec![
+        "*.internal.com",
+        "secret.example.com",
+        "evil.com.:443"
+      ],
+      ..Default::default()
+    });
+
+    let domain_tests = vec![
+      ("db.internal.com", 5432, false),
+      ("db.internal.com.", 5432, false),
+      ("secret.example.com", 443, false),
+      ("secret.example.com.", 443, false),
+      ("evil.com", 443, false),
+      ("evil.com.", 443, false),
+      ("evil.com", 80, true),
+      ("example.com.", 443, true),
+    ];
+
+    for (host, port, is_ok) in domain_tests {
+      assert_eq!(is_ok, perms.check_net(host, port).is_ok(), "{}", host);
+    }
+
+    let perms = Permissions::from_flags(&Flags {
+      net_allowlist: svec!["deno.land", "*.deno.dev."],
+      ..Default::default()
+    });
+    assert!(perms.check_net("deno.land.", 443).is_ok());
+    assert!(perms.check_net("app.deno.dev", 443).is_ok());
+    assert!(perms.check_net("app.deno.dev.", 443).is_ok());
+  }
+
+  #[test]
+  fn test_check_net_addr() {
+    let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+    let perms = Permissions::from_flags(&Flags {
+      allow_net: true,
+      net_denylist: svec!["169.254.169.254", "10.0.0.0/8"],
+      ..Default::default()
+    });
+    assert!(perms.check_net_lookup("evil.example.com", 80).is_ok());
+    assert!(perms
+      .check_net_addr("evil.example.com", &addr("93.184.216.34:80"))
+      .is_ok());
+    assert!(perms
+      .check_net_addr("evil.example.com", &addr("169.254.169.254:80"))
+      .is_err());
+    assert!(perms
+      .check_net_addr("evil.example.com", &addr("[::ffff:10.0.0.1]:80"))
+      .is_err());
+    assert!(perms
+      .check_net_addr("evil.example.com", &addr("[::ffff:169.254.169.254]:80"))
+      .is_err());
+    assert!(perms
+      .check_net_lookup("::ffff:169.254.169.254", 80)
+      .is_err());
+    assert!(perms
+      .check_net_addr("::ffff:169.254.169.254", &addr("93.184.216.34:80"))
+      .is_err());
+    assert!(perms.check_net_lookup("[::ffff:a9fe:a9fe]", 80).is_err());
+
+    let perms = Permissions::from_flags(&Flags {
+      net_allowlist: svec!["10.0.0.0/8"],
+      ..Default::default()
+    });
+    assert!(perms.check_net_lookup("db.local", 5432).is_ok());
+    assert!(perms
+      .check_net_addr("db.local", &addr("10.1.2.3:5432"))
+      .is_ok());
+    assert!(perms
+      .check_net_addr("db.local", &addr("192.168.1.1:5432"))
+      .is_err());
+    assert!(perms.check_net_lookup("192.168.1.1", 5432).is_err());
+
+    let perms = Permissions::from_flags(&Flags {
+      net_allowlist: svec!["deno.land"],
+      ..Default::default()
+    });
+    assert!(!perms.has_ip_net_rules());
+    assert!(perms.check_net_lookup("example.com", 443).is_err());
+  }
+
+  #[test]
+  fn test_check_run() {
+    let perms = Permissions::from_flags(&Flags {
+      run_allowlist: svec!["deno", "/usr/bin/git"],
+      ..Default::default()
+    });
+    assert!(perms.check_run("deno", None).is_ok());
+    assert!(perms.check_run("/usr/bin/git", None).is_ok());
+    assert!(perms.check_run("node", None).is_err());
+    assert!(perms.check_run("./deno", None).is_err());
+    assert!(perms.check_run_all().is_err());
+
+    let perms = Permissions::from_flags(&Flags {
+      allow_run: true,
+      ..Default::default()
+    });
+    assert!(perms.check_run("node", None).is_ok());
+    assert!(perms.check_run_all().is_ok());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_check_run_symlink() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(dir.join("tool"), "").unwrap();
+    std::os::unix::fs::symlink(dir.join("tool"), dir.join("link")).unwrap();
+    let perms = Permissions::from_flags(&Flags {
+      allow_run: true,
+      run_denylist: vec![dir.join("link").display().to_string()],
+      ..Default::default()
+    });
+    assert!(perms
+      .check_run(dir.join("tool").to_str().unwrap(), None)
+      .is_err());
+    assert!(perms.check_run("./tool", Some(dir)).is_err());
+    assert!(perms.check_run("./other", Some(dir)).is_ok());
+  }
+
+  #[test]
+  fn test_merge() {
+    let policy = Permissions {
+      read: UnaryPermission {
+        global_state: PermissionState::Granted,
+        denied_list: resolve_fs_denylist(&[PathBuf::from("/etc/secrets")]),
+        ..Default::default()
+      },
+      net: UnaryPermission {
+        granted_list: svec!["deno.land", "github.com"].into_iter().collect(),
+        ..Default::default()
+      },
+      hrtime: PermissionState::Denied,
+      ..Default::default()
+    };
+    let perms = policy
+      .merge(Permissions::from_flags(&Flags {
+        net_allowlist: svec!["deno.land"],
+        net_denylist: svec!["github.com"],
+        ..Default::default()
+      }))
+      .unwrap();
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_ok());
+    assert!(perms.check_read(Path::new("/etc/secrets")).is_err());
+    assert!(perms.check_read(Path::new("/etc/secrets/key")).is_err());
+    assert!(perms.check_net("deno.land", 443).is_ok());
+    assert!(perms.check_net("github.com", 443).is_err());
+    assert!(perms.check_net("example.com", 443).is_err());
+    assert!(perms.check_hrtime().is_err());
+
+    // The flags can't grant what the policy doesn't.
+    for flags in vec![
+      Flags {
+        allow_net: true,
+        ..Default::default()
+      },
+      Flags {
+        net_allowlist: svec!["example.com"],
+        ..Default::default()
+      },
+      Flags {
+        write_allowlist: vec![PathBuf::from("/tmp")],
+        ..Default::default()
+      },
+      Flags {
+        allow_hrtime: true,
+        ..Default::default()
+      },
+    ] {
+      assert!(policy.merge(Permissions::from_flags(&flags)).is_err());
+    }
+  }
+
+  #[test]
+  fn test_check_env() {
+    let perms = Permissions::from_flags(&Flags {
+      env_allowlist: svec!["HOME", "PATH"],
+      ..Default::default()
+    });
+    assert!(perms.check_env("HOME").is_ok());
+    assert!(perms.check_env("PATH").is_ok());
+    assert!(perms.check_env("SECRET").is_err());
+    assert!(perms.check_env_all().is_err());
+
+    let perms = Permissions::from_flags(&Flags {
+      allow_env: true,
+      ..Default::default()
+    });
+    assert!(perms.check_env("SECRET").is_ok());
+    assert!(perms.check_env_all().is_ok());
+  }
+
+  #[test]
+  fn test_run_entry_matches_resolved_executable() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let exe_path = temp_dir.path().join("some_exe");
+    std::fs::write(&exe_path, "").unwrap();
+    let exe_path_str = exe_path.to_str().unwrap();
+    assert!(run_entry_matches(exe_path_str, exe_path_str, None));
+    let with_dot = temp_dir.path().join(".").join("some_exe");
+    assert!(run_entry_matches(
+      exe_path_str,
+      with_dot.to_str().unwrap(),
+      None
+    ));
+    let other = temp_dir.path().join("other_exe");
+    assert!(!run_entry_matches(
+      exe_path_str,
+      other.to_str().unwrap(),
+      None
+    ));
+    // A relative path is resolved from the working directory of the
+    // subprocess.
+    assert!(run_entry_matches(
+      exe_path_str,
+      "./some_exe",
+      Some(temp_dir.path())
+    ));
+    assert!(!run_entry_matches(exe_path_str, "./some_exe", None));
+  }
+
   #[test]
   fn test_deserialize_perms() {
     let json_perms = r#"
@@ -906,8 +1884,16 @@ mod tests {
         "granted_list": [],
         "denied_list": []
       },
-      "env": "Granted",
-      "run": "Granted",
+      "env": {
+        "global_state": "Granted",
+        "granted_list": [],
+        "denied_list": []
+      },
+      "run": {
+        "global_state": "Granted",
+        "granted_list": [],
+        "denied_list": []
+      },
       "plugin": "Granted",
       "hrtime": "Granted"
     }
@@ -925,8 +1911,14 @@ mod tests {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       hrtime: PermissionState::Granted,
       plugin: PermissionState::Granted,
     };
@@ -952,8 +1944,14 @@ mod tests {
           global_state: PermissionState::Prompt,
           ..Default::default()
         },
-        PermissionState::Prompt,
-        PermissionState::Prompt,
+        UnaryPermission {
+          global_state: PermissionState::Prompt,
+          ..Default::default()
+        },
+        UnaryPermission {
+          global_state: PermissionState::Prompt,
+          ..Default::default()
+        },
         PermissionState::Denied,
         PermissionState::Denied,
       )
@@ -972,14 +1970,53 @@ mod tests {
           global_state: PermissionState::Granted,
           ..Default::default()
         },
-        PermissionState::Granted,
-        PermissionState::Granted,
+        UnaryPermission {
+          global_state: PermissionState::Granted,
+          ..Default::default()
+        },
+        UnaryPermission {
+          global_state: PermissionState::Granted,
+          ..Default::default()
+        },
         PermissionState::Denied,
         PermissionState::Denied,
       )
       .expect_err("Fork should fail.");
   }
 
+  #[test]
+  fn test_fork_allowlists() {
+    let perms0 = Permissions::from_flags(&Flags {
+      read_allowlist: vec![PathBuf::from("/foo")],
+      net_allowlist: svec!["deno.land"],
+      ..Default::default()
+    });
+    let fork = |read: &[&str], net: &[&str]| {
+      perms0.fork(
+        UnaryPermission {
+          granted_list: read
+            .iter()
+            .map(|p| resolve_from_cwd(Path::new(p)).unwrap())
+            .collect(),
+          ..Default::default()
+        },
+        Default::default(),
+        UnaryPermission {
+          granted_list: net.iter().map(|h| h.to_string()).collect(),
+          ..Default::default()
+        },
+        Default::default(),
+        Default::default(),
+        PermissionState::Prompt,
+        PermissionState::Prompt,
+      )
+    };
+    fork(&["/foo/bar"], &["deno.land", "deno.land:443"])
+      .expect("Fork should succeed.");
+    fork(&["/"], &[]).expect_err("Fork should fail.");
+    fork(&[], &["github.com"]).expect_err("Fork should fail.");
+  }
+
   #[test]
   fn test_query() {
     let perms1 = Permissions {
@@ -995,8 +2032,14 @@ mod tests {
         global_state: PermissionState::Granted,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Granted,
+        ..Default::default()
+      },
       plugin: PermissionState::Granted,
       hrtime: PermissionState::Granted,
     };
@@ -1016,8 +2059,16 @@ mod tests {
         granted_list: ["127.0.0.1:8000".to_string()].iter().cloned().collect(),
         ..Default::default()
       },
-      env: PermissionState::Prompt,
-      run: PermissionState::Prompt,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["HOME".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["deno".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Prompt,
     };
@@ -1037,10 +2088,16 @@ mod tests {
       assert_eq!(perms1.query_net_url(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
       assert_eq!(perms2.query_net_url(&None).unwrap(), PermissionState::Prompt);
       assert_eq!(perms2.query_net_url(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
-      assert_eq!(perms1.query_env(), PermissionState::Granted);
-      assert_eq!(perms2.query_env(), PermissionState::Prompt);
-      assert_eq!(perms1.query_run(), PermissionState::Granted);
-      assert_eq!(perms2.query_run(), PermissionState::Prompt);
+      assert_eq!(perms1.query_env(&None), PermissionState::Granted);
+      assert_eq!(perms1.query_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms2.query_env(&None), PermissionState::Prompt);
+      assert_eq!(perms2.query_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms2.query_env(&Some("SECRET")), PermissionState::Prompt);
+      assert_eq!(perms1.query_run(&None), PermissionState::Granted);
+      assert_eq!(perms1.query_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms2.query_run(&None), PermissionState::Prompt);
+      assert_eq!(perms2.query_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms2.query_run(&Some("node")), PermissionState::Prompt);
       assert_eq!(perms1.query_plugin(), PermissionState::Granted);
       assert_eq!(perms2.query_plugin(), PermissionState::Prompt);
       assert_eq!(perms1.query_hrtime(), PermissionState::Granted);
@@ -1063,8 +2120,14 @@ mod tests {
         global_state: PermissionState::Prompt,
         ..Default::default()
       },
-      env: PermissionState::Prompt,
-      run: PermissionState::Prompt,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Prompt,
     };
@@ -1078,7 +2141,9 @@ mod tests {
       assert_eq!(perms.request_read(&Some(&Path::new("/foo/bar"))), PermissionState::Granted);
       set_prompt_result(false);
       assert_eq!(perms.request_write(&Some(&Path::new("/foo"))), PermissionState::Denied);
-      assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Prompt);
+      // Denying a directory at a prompt denies what is inside it too.
+      assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Denied);
+      assert_eq!(perms.query_write(&Some(&Path::new("/baz"))), PermissionState::Prompt);
       set_prompt_result(true);
       assert_eq!(perms.request_write(&None), PermissionState::Denied);
       set_prompt_result(true);
@@ -1086,13 +2151,21 @@ mod tests {
       set_prompt_result(false);
       assert_eq!(perms.request_net(&Some("http://127.0.0.1:8000")).unwrap(), PermissionState::Granted);
       set_prompt_result(true);
-      assert_eq!(perms.request_env(), PermissionState::Granted);
+      assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms.query_env(&None), PermissionState::Prompt);
       set_prompt_result(false);
-      assert_eq!(perms.request_env(), PermissionState::Granted);
+      assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms.request_env(&None), PermissionState::Denied);
+      set_prompt_result(true);
+      assert_eq!(perms.request_env(&None), PermissionState::Denied);
+      set_prompt_result(true);
+      assert_eq!(perms.request_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms.query_run(&None), PermissionState::Prompt);
       set_prompt_result(false);
-      assert_eq!(perms.request_run(), PermissionState::Denied);
+      assert_eq!(perms.request_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms.request_run(&None), PermissionState::Denied);
       set_prompt_result(true);
-      assert_eq!(perms.request_run(), PermissionState::Denied);
+      assert_eq!(perms.request_run(&None), PermissionState::Denied);
       set_prompt_result(true);
       assert_eq!(perms.request_plugin(), PermissionState::Granted);
       set_prompt_result(false);
@@ -1104,6 +2177,29 @@ mod tests {
     };
   }
 
+  #[test]
+  fn test_request_scopes() {
+    let mut perms = Permissions::default();
+    #[rustfmt::skip]
+    {
+      let _guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+      set_prompt_response(PromptResponse::GrantScope);
+      assert_eq!(perms.request_read(&Some(&Path::new("/foo/bar.txt"))), PermissionState::Granted);
+      assert_eq!(perms.query_read(&Some(&Path::new("/foo/baz.txt"))), PermissionState::Granted);
+      assert_eq!(perms.query_read(&Some(&Path::new("/qux"))), PermissionState::Prompt);
+      assert_eq!(perms.request_net(&Some("https://deno.land")).unwrap(), PermissionState::Granted);
+      assert_eq!(perms.query_net("deno.land", Some(8000)), PermissionState::Granted);
+      assert_eq!(perms.query_net("example.com", Some(443)), PermissionState::Prompt);
+      assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Granted);
+      assert_eq!(perms.query_env(&None), PermissionState::Prompt);
+      set_prompt_response(PromptResponse::GrantAll);
+      assert_eq!(perms.request_write(&Some(&Path::new("/foo/bar.txt"))), PermissionState::Granted);
+      assert_eq!(perms.query_write(&None), PermissionState::Granted);
+      assert_eq!(perms.request_run(&Some("deno")), PermissionState::Granted);
+      assert_eq!(perms.query_run(&None), PermissionState::Granted);
+    };
+  }
+
   #[test]
   fn test_revoke() {
     let mut perms = Permissions {
@@ -1121,8 +2217,16 @@ mod tests {
         global_state: PermissionState::Denied,
         ..Default::default()
       },
-      env: PermissionState::Granted,
-      run: PermissionState::Granted,
+      env: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["HOME".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
+      run: UnaryPermission {
+        global_state: PermissionState::Prompt,
+        granted_list: ["deno".to_string()].iter().cloned().collect(),
+        ..Default::default()
+      },
       plugin: PermissionState::Prompt,
       hrtime: PermissionState::Denied,
     };
@@ -1135,8 +2239,10 @@ mod tests {
       assert_eq!(perms.revoke_write(&None), PermissionState::Prompt);
       assert_eq!(perms.query_write(&Some(&Path::new("/foo/bar"))), PermissionState::Prompt);
       assert_eq!(perms.revoke_net(&None).unwrap(), PermissionState::Denied);
-      assert_eq!(perms.revoke_env(), PermissionState::Prompt);
-      assert_eq!(perms.revoke_run(), PermissionState::Prompt);
+      assert_eq!(perms.revoke_env(&Some("HOME")), PermissionState::Prompt);
+      assert_eq!(perms.revoke_env(&None), PermissionState::Prompt);
+      assert_eq!(perms.revoke_run(&Some("deno")), PermissionState::Prompt);
+      assert_eq!(perms.revoke_run(&None), PermissionState::Prompt);
       assert_eq!(perms.revoke_plugin(), PermissionState::Prompt);
       assert_eq!(perms.revoke_hrtime(), PermissionState::Denied);
     };
@@ -10,6 +10,7 @@
     hasSourceCode,
     sourceCode,
     useDenoNamespace,
+    permissions,
     name,
   ) {
     return core.jsonOpSync("op_create_worker", {
@@ -18,6 +19,7 @@
       sourceCode,
       name,
       useDenoNamespace,
+      permissions,
     });
   }
 
@@ -33,6 +35,75 @@
     return core.jsonOpAsync("op_host_get_message", { id });
   }
 
+  function parseUnitPermission(value, permissionName) {
+    if (value !== "inherit" && typeof value !== "boolean") {
+      throw new TypeError(
+        `Expected 'boolean' or "inherit" for ${permissionName} permission, ${typeof value} received`,
+      );
+    }
+    return value === "inherit" ? undefined : value;
+  }
+
+  function parseArrayPermission(value, permissionName) {
+    if (value === "inherit") {
+      return undefined;
+    }
+    if (typeof value === "boolean") {
+      return value;
+    }
+    if (
+      !Array.isArray(value) ||
+      !value.every((entry) => typeof entry === "string")
+    ) {
+      throw new TypeError(
+        `Expected 'boolean', 'string[]' or "inherit" for ${permissionName} permission`,
+      );
+    }
+    return value;
+  }
+
+  /** Normalizes the `deno.permissions` option of a worker. Permissions that
+   * are left out, or set to "inherit", are inherited from the parent. */
+  function parsePermissions(permissions) {
+    if (permissions === "inherit") {
+      return undefined;
+    }
+    if (permissions === "none") {
+      return {
+        env: false,
+        hrtime: false,
+        net: false,
+        plugin: false,
+        read: false,
+        run: false,
+        write: false,
+      };
+    }
+    if (typeof permissions !== "object" || permissions === null) {
+      throw new TypeError(
+        `Expected 'object', "inherit" or "none" for permissions, ${typeof permissions} received`,
+      );
+    }
+    const {
+      env = "inherit",
+      hrtime = "inherit",
+      net = "inherit",
+      plugin = "inherit",
+      read = "inherit",
+      run = "inherit",
+      write = "inherit",
+    } = permissions;
+    return {
+      env: parseArrayPermission(env, "env"),
+      hrtime: parseUnitPermission(hrtime, "hrtime"),
+      net: parseArrayPermission(net, "net"),
+      plugin: parseUnitPermission(plugin, "plugin"),
+      read: parseArrayPermission(read, "read"),
+      run: parseArrayPermission(run, "run"),
+      write: parseArrayPermission(write, "write"),
+    };
+  }
+
   const encoder = new TextEncoder();
   const decoder = new TextDecoder();
 
@@ -65,13 +136,24 @@
       const hasSourceCode = false;
       const sourceCode = decoder.decode(new Uint8Array());
 
-      const useDenoNamespace = options ? !!options.deno : false;
+      const deno = options?.deno;
+      let useDenoNamespace = false;
+      let permissions;
+      if (typeof deno === "object" && deno !== null) {
+        useDenoNamespace = !!deno.namespace;
+        if (deno.permissions !== undefined) {
+          permissions = parsePermissions(deno.permissions);
+        }
+      } else {
+        useDenoNamespace = !!deno;
+      }
 
       const { id } = createWorker(
         specifier,
         hasSourceCode,
         sourceCode,
         useDenoNamespace,
+        permissions,
         options?.name,
       );
       this.#id = id;
@@ -6,8 +6,8 @@
   const { readAll } = window.__bootstrap.buffer;
   const { assert, pathFromURL } = window.__bootstrap.util;
 
-  function opKill(pid, signo) {
-    core.jsonOpSync("op_kill", { pid, signo });
+  function opKill(pid, signo, rid) {
+    core.jsonOpSync("op_kill", { pid, signo, rid });
   }
 
   function opRunStatus(rid) {
@@ -81,7 +81,7 @@
     }
 
     kill(signo) {
-      opKill(this.pid, signo);
+      opKill(this.pid, signo, this.rid);
     }
   }
 
@@ -93,9 +93,16 @@
     cmd,
     cwd = undefined,
     env = {},
+    clearEnv = false,
     stdout = "inherit",
     stderr = "inherit",
     stdin = "inherit",
+    uid = undefined,
+    gid = undefined,
+    pgid = undefined,
+    setsid = false,
+    rlimits = undefined,
+    timeout = undefined,
   }) {
     if (cmd[0] != null) {
       cmd[0] = pathFromURL(cmd[0]);
@@ -104,12 +111,19 @@
       cmd: cmd.map(String),
       cwd,
       env: Object.entries(env),
+      clearEnv,
       stdin: isRid(stdin) ? "" : stdin,
       stdout: isRid(stdout) ? "" : stdout,
       stderr: isRid(stderr) ? "" : stderr,
       stdinRid: isRid(stdin) ? stdin : 0,
       stdoutRid: isRid(stdout) ? stdout : 0,
       stderrRid: isRid(stderr) ? stderr : 0,
+      uid,
+      gid,
+      pgid,
+      setsid,
+      rlimits,
+      timeout,
     });
     return new Process(res);
   }
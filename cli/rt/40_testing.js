@@ -23,6 +23,31 @@
     return gray(italic(timeStr));
   }
 
+  // Returns the pending async ops and the open resources, with the location
+  // of the code that created them when it is known.
+  function sanitizerSnapshot() {
+    return core.jsonOpSync("op_sanitizer_snapshot");
+  }
+
+  // Capturing the call site of every op is costly, so it's only done while a
+  // test with sanitizers runs, for them to tell where leaks were created.
+  function trackOpCallSites(fn) {
+    return async function opCallSiteTracker() {
+      const wasEnabled = core.jsonOpSync("op_track_op_call_sites", {
+        enabled: true,
+      });
+      try {
+        await fn();
+      } finally {
+        core.jsonOpSync("op_track_op_call_sites", { enabled: wasEnabled });
+      }
+    };
+  }
+
+  function formatCallSite(callSite, verb) {
+    return callSite == null ? "" : `, ${verb} at ${callSite}`;
+  }
+
   // Wrap test function in additional assertion that makes sure
   // the test case does not leak async "ops" - ie. number of async
   // completed ops after the test is the same as number of dispatched
@@ -31,6 +56,7 @@
   function assertOps(fn) {
     return async function asyncOpSanitizer() {
       const pre = metrics();
+      const preOps = sanitizerSnapshot().ops;
       await fn();
       // Defer until next event loop turn - that way timeouts and intervals
       // cleared can actually be removed from resource table, otherwise
@@ -41,15 +67,20 @@
       // that will be a pending async op before test starts.
       const dispatchedDiff = post.opsDispatchedAsync - pre.opsDispatchedAsync;
       const completedDiff = post.opsCompletedAsync - pre.opsCompletedAsync;
+      if (dispatchedDiff === completedDiff) {
+        return;
+      }
+      // The ops dispatched during the test that are still pending.
+      const preIds = new Set(preOps.map(({ id }) => id));
+      const leaked = sanitizerSnapshot().ops
+        .filter(({ id }) => !preIds.has(id))
+        .map(({ name, callSite }) =>
+          `  - ${name}${formatCallSite(callSite, "dispatched")}`
+        );
       assert(
-        dispatchedDiff === completedDiff,
-        `Test case is leaking async ops.
-Before:
-  - dispatched: ${pre.opsDispatchedAsync}
-  - completed: ${pre.opsCompletedAsync}
-After:
-  - dispatched: ${post.opsDispatchedAsync}
-  - completed: ${post.opsCompletedAsync}
+        false,
+        `Test case is leaking ${dispatchedDiff - completedDiff} async ops:
+${leaked.join("\n")}
 
 Make sure to await all promises returned from Deno APIs before
 finishing test case.`,
@@ -64,19 +95,32 @@ finishing test case.`,
     fn,
   ) {
     return async function resourceSanitizer() {
-      const pre = core.resources();
+      const pre = sanitizerSnapshot().resources;
       await fn();
-      const post = core.resources();
-
-      const preStr = JSON.stringify(pre, null, 2);
-      const postStr = JSON.stringify(post, null, 2);
-      const msg = `Test case is leaking resources.
-Before: ${preStr}
-After: ${postStr}
+      const post = sanitizerSnapshot().resources;
+
+      const leaked = Object.keys(post)
+        .filter((rid) => !(rid in pre))
+        .map((rid) => {
+          const { name, callSite } = post[rid];
+          return `  - ${name} (rid ${rid})${formatCallSite(callSite, "opened")}`;
+        });
+      const closed = Object.keys(pre)
+        .filter((rid) => !(rid in post))
+        .map((rid) => `  - ${pre[rid].name} (rid ${rid})`);
+      let msg = "";
+      if (leaked.length > 0) {
+        msg += `Test case is leaking resources:
+${leaked.join("\n")}
 
 Make sure to close all open resource handles returned from Deno APIs before
 finishing test case.`;
-      assert(preStr === postStr, msg);
+      }
+      if (closed.length > 0) {
+        msg += `${msg ? "\n\n" : ""}Test case closed resources that were ` +
+          `open before it started:\n${closed.join("\n")}`;
+      }
+      assert(msg === "", msg);
     };
   }
 
@@ -122,6 +166,10 @@ finishing test case.`;
       testDef.fn = assertResources(testDef.fn);
     }
 
+    if (testDef.sanitizeOps || testDef.sanitizeResources) {
+      testDef.fn = trackOpCallSites(testDef.fn);
+    }
+
     TEST_REGISTRY.push(testDef);
   }
 
@@ -194,6 +242,176 @@ finishing test case.`;
 
   exposeForTest("reportToConsole", reportToConsole);
 
+  function serializeError(error) {
+    if (error instanceof Error) {
+      return { name: error.name, message: error.message, stack: error.stack };
+    }
+    return { name: "Error", message: inspectArgs([error]), stack: null };
+  }
+
+  // Reports every message as soon as it is received, as a JSON object on a
+  // single line.
+  function reportAsJsonLines(message) {
+    let event;
+    if (message.start != null) {
+      const names = message.start.tests.map(({ name }) => name);
+      event = { type: "plan", count: names.length, tests: names };
+    } else if (message.testStart != null) {
+      event = { type: "testStart", name: message.testStart.name };
+    } else if (message.testEnd != null) {
+      const { name, status, duration, error } = message.testEnd;
+      event = { type: "testEnd", name, status, duration };
+      if (error != null) {
+        event.error = serializeError(error);
+      }
+    } else if (message.end != null) {
+      const {
+        passed,
+        failed,
+        ignored,
+        measured,
+        filtered,
+        usedOnly,
+        duration,
+      } = message.end;
+      event = {
+        type: "end",
+        passed,
+        failed,
+        ignored,
+        measured,
+        filtered,
+        usedOnly,
+        duration,
+      };
+    }
+    log(JSON.stringify(event));
+  }
+
+  exposeForTest("reportAsJsonLines", reportAsJsonLines);
+
+  function escapeXml(str) {
+    return String(str)
+      .replace(/&/g, "&amp;")
+      .replace(/</g, "&lt;")
+      .replace(/>/g, "&gt;")
+      .replace(/"/g, "&quot;")
+      .replace(/'/g, "&apos;");
+  }
+
+  function formatSeconds(ms = 0) {
+    return (ms / 1000).toFixed(3);
+  }
+
+  // Reports all results as a JUnit XML document once the run has ended.
+  function reportAsJUnit(message) {
+    if (message.end == null) {
+      return;
+    }
+
+    const { results, failed, ignored, duration } = message.end;
+    const attributes = `tests="${results.length}" failures="${failed}" ` +
+      `skipped="${ignored}" time="${formatSeconds(duration)}"`;
+    const lines = [
+      `<?xml version="1.0" encoding="UTF-8"?>`,
+      `<testsuites name="deno test" ${attributes}>`,
+      `  <testsuite name="deno test" ${attributes}>`,
+    ];
+    for (const { name, status, duration, error } of results) {
+      const testcase = `    <testcase name="${escapeXml(name)}" ` +
+        `time="${formatSeconds(duration)}"`;
+      if (status === "failed") {
+        const { name: type, message, stack } = serializeError(error);
+        lines.push(`${testcase}>`);
+        lines.push(
+          `      <failure message="${escapeXml(message)}" ` +
+            `type="${escapeXml(type)}">${escapeXml(stack ?? message)}` +
+            `</failure>`,
+        );
+        lines.push(`    </testcase>`);
+      } else if (status === "ignored") {
+        lines.push(`${testcase}>`);
+        lines.push(`      <skipped/>`);
+        lines.push(`    </testcase>`);
+      } else {
+        lines.push(`${testcase}/>`);
+      }
+    }
+    lines.push(`  </testsuite>`);
+    lines.push(`</testsuites>`);
+    log(lines.join("\n"));
+  }
+
+  exposeForTest("reportAsJUnit", reportAsJUnit);
+
+  const reporters = {
+    pretty: reportToConsole,
+    junit: reportAsJUnit,
+    jsonl: reportAsJsonLines,
+  };
+
+  // Converts a message into plain JSON so it can be posted from an isolate
+  // running a single test module to the isolate reporting the whole run.
+  function serializeTestMessage(message) {
+    const serializeResult = ({ name, status, duration, error }) => {
+      const result = { name, status, duration };
+      if (error != null) {
+        result.error = serializeError(error);
+      }
+      return result;
+    };
+
+    if (message.start != null) {
+      const tests = message.start.tests.map(({ name, ignore, only }) => ({
+        name,
+        ignore,
+        only,
+      }));
+      return { start: { tests, usedOnly: message.start.usedOnly } };
+    } else if (message.testStart != null) {
+      const { name, ignore } = message.testStart;
+      return { testStart: { name, ignore } };
+    } else if (message.testEnd != null) {
+      return { testEnd: serializeResult(message.testEnd) };
+    } else if (message.end != null) {
+      const results = message.end.results.map(serializeResult);
+      return { end: { ...message.end, results } };
+    }
+  }
+
+  function deserializeTestMessage(message) {
+    const deserializeResult = (result) => {
+      if (result.error == null) {
+        return result;
+      }
+      const { name, message, stack } = result.error;
+      const error = new Error(message);
+      error.name = name;
+      error.stack = stack ?? `${name}: ${message}`;
+      return { ...result, error };
+    };
+
+    if (message.testEnd != null) {
+      return { testEnd: deserializeResult(message.testEnd) };
+    } else if (message.end != null) {
+      const results = message.end.results.map(deserializeResult);
+      return { end: { ...message.end, results } };
+    }
+    return message;
+  }
+
+  function postTestMessage(message) {
+    core.jsonOpSync("op_post_test_message", serializeTestMessage(message));
+  }
+
+  exposeForTest("postTestMessage", postTestMessage);
+
+  function reportTestMessage(reporter, message) {
+    reporters[reporter](deserializeTestMessage(message));
+  }
+
+  exposeForTest("reportTestMessage", reportTestMessage);
+
   // TODO: already implements AsyncGenerator<RunTestsMessage>, but add as "implements to class"
   // TODO: implements PromiseLike<RunTestsEndResult>
   class TestRunner {
@@ -221,7 +439,7 @@ finishing test case.`;
     }
 
     async *[Symbol.asyncIterator]() {
-      yield { start: { tests: this.testsToRun } };
+      yield { start: { tests: this.testsToRun, usedOnly: this.#usedOnly } };
 
       const results = [];
       const suiteStart = +new Date();
@@ -301,8 +519,14 @@ finishing test case.`;
     skip = undefined,
     disableLog = false,
     reportToConsole: reportToConsole_ = true,
+    reporter = "pretty",
     onMessage = undefined,
   } = {}) {
+    const report = reporters[reporter];
+    if (report == null) {
+      throw new TypeError(`Unknown test reporter: ${reporter}`);
+    }
+
     const filterFn = createFilterFn(filter, skip);
     const testRunner = new TestRunner(TEST_REGISTRY, filterFn, failFast);
 
@@ -319,7 +543,7 @@ finishing test case.`;
         await onMessage(message);
       }
       if (reportToConsole_) {
-        reportToConsole(message);
+        report(message);
       }
       if (message.end != null) {
         endMsg = message.end;
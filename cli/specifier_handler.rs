@@ -30,6 +30,8 @@ pub type FetchFuture =
 pub struct CachedModule {
   pub emits: EmitMap,
   pub maybe_dependencies: Option<DependencyMap>,
+  /// The URL the module was redirected to, if it was.
+  pub maybe_redirect: Option<ModuleSpecifier>,
   pub maybe_types: Option<String>,
   pub maybe_version: Option<String>,
   pub media_type: MediaType,
@@ -43,6 +45,7 @@ impl Default for CachedModule {
     CachedModule {
       emits: HashMap::new(),
       maybe_dependencies: None,
+      maybe_redirect: None,
       maybe_types: None,
       maybe_version: None,
       media_type: MediaType::Unknown,
@@ -256,9 +259,16 @@ impl SpecifierHandler for FetchHandler {
         emits.insert(EmitType::Cli, (String::from_utf8(code)?, maybe_map));
       };
 
+      let maybe_redirect = if url != *specifier.as_url() {
+        Some(ModuleSpecifier::from(url))
+      } else {
+        None
+      };
+
       Ok(CachedModule {
         emits,
         maybe_dependencies: None,
+        maybe_redirect,
         maybe_types: source_file.types_header,
         maybe_version,
         media_type: source_file.media_type,
@@ -386,6 +396,7 @@ pub mod tests {
       false,
       false,
       None,
+      None,
     )
     .expect("could not setup");
     let disk_cache = deno_dir.gen_cache;
@@ -2,15 +2,20 @@
 
 use crate::global_state::GlobalState;
 use crate::import_map::ImportMap;
+use crate::media_type::MediaType;
 use crate::permissions::Permissions;
 use crate::tsc::TargetLib;
+use deno_core::error::custom_error;
 use deno_core::error::AnyError;
 use deno_core::futures::future::FutureExt;
 use deno_core::futures::Future;
+use deno_core::serde_json;
+use deno_core::ModuleCode;
 use deno_core::ModuleLoadId;
 use deno_core::ModuleLoader;
 use deno_core::ModuleSpecifier;
 use deno_core::OpState;
+use deno_core::SyntheticExport;
 use std::cell::RefCell;
 use std::pin::Pin;
 use std::rc::Rc;
@@ -43,6 +48,26 @@ impl CliModuleLoader {
   }
 }
 
+/// Put in the `OpState` of a worker whose main module and its dependencies
+/// were already fetched and type checked, like the test modules of a parallel
+/// test run, to load it without preparing it again.
+pub struct PreparedMainModule;
+
+/// Returns a synthetic module whose default export is the value of a JSON
+/// module.
+fn json_module_code(name: &str, source: &str) -> Result<ModuleCode, AnyError> {
+  let value = serde_json::from_str(source).map_err(|err| {
+    custom_error(
+      "SyntaxError",
+      format!("Unable to parse JSON module \"{}\": {}", name, err),
+    )
+  })?;
+  Ok(ModuleCode::Synthetic(vec![(
+    "default".to_string(),
+    SyntheticExport::Json(value),
+  )]))
+}
+
 impl ModuleLoader for CliModuleLoader {
   fn resolve(
     &self,
@@ -97,10 +122,16 @@ impl ModuleLoader for CliModuleLoader {
       let compiled_module = global_state
         .fetch_compiled_module(module_specifier, maybe_referrer)
         .await?;
+      let code = match compiled_module.media_type {
+        MediaType::Json => {
+          json_module_code(&compiled_module.name, &compiled_module.code)?
+        }
+        _ => ModuleCode::Js(compiled_module.code),
+      };
       Ok(deno_core::ModuleSource {
         // Real module name, might be different from initial specifier
         // due to redirections.
-        code: compiled_module.code,
+        code,
         module_url_specified,
         module_url_found: compiled_module.name,
       })
@@ -131,7 +162,12 @@ impl ModuleLoader for CliModuleLoader {
       state.borrow::<Permissions>().clone()
     };
     let global_state = state.borrow::<Arc<GlobalState>>().clone();
+    let is_prepared =
+      self.is_main && !is_dyn_import && state.has::<PreparedMainModule>();
     drop(state);
+    if is_prepared {
+      return async { Ok(()) }.boxed_local();
+    }
 
     // TODO(bartlomieju): I'm not sure if it's correct to ignore
     // bad referrer - this is the case for `Deno.core.evalContext()` where
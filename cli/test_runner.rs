@@ -1,12 +1,32 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
+use crate::file_fetcher::SourceFile;
 use crate::fs as deno_fs;
+use crate::global_state::GlobalState;
 use crate::installer::is_remote_url;
+use crate::media_type::MediaType;
+use crate::ops::testing::TestMessageSender;
+use crate::state::PreparedMainModule;
+use crate::tokio_util;
+use crate::worker::MainWorker;
 use deno_core::error::AnyError;
+use deno_core::futures::channel::mpsc;
+use deno_core::futures::stream::StreamExt;
+use deno_core::serde_json;
 use deno_core::serde_json::json;
+use deno_core::serde_json::Value;
 use deno_core::url::Url;
+use deno_core::ModuleSpecifier;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
 pub(crate) fn is_supported(p: &Path) -> bool {
   use std::path::Component;
@@ -73,6 +93,7 @@ pub fn render_test_file(
   fail_fast: bool,
   quiet: bool,
   filter: Option<String>,
+  reporter: &str,
 ) -> String {
   let mut test_file = "".to_string();
 
@@ -80,10 +101,12 @@ pub fn render_test_file(
     test_file.push_str(&format!("import \"{}\";\n", module.to_string()));
   }
 
+  // Machine readable reports are written even when quiet.
+  let report_to_console = !quiet || reporter != "pretty";
   let options = if let Some(filter) = filter {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet, "filter": filter })
+    json!({ "failFast": fail_fast, "reportToConsole": report_to_console, "disableLog": quiet, "reporter": reporter, "filter": filter })
   } else {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet })
+    json!({ "failFast": fail_fast, "reportToConsole": report_to_console, "disableLog": quiet, "reporter": reporter })
   };
 
   let run_tests_cmd = format!(
@@ -95,6 +118,351 @@ pub fn render_test_file(
   test_file
 }
 
+/// Renders the module that runs the tests of a single test module in its own
+/// isolate and posts the test runner messages back to the reporting isolate.
+pub fn render_test_job_file(
+  module: &Url,
+  fail_fast: bool,
+  quiet: bool,
+  filter: Option<String>,
+) -> String {
+  let options = if let Some(filter) = filter {
+    json!({ "exitOnFail": false, "failFast": fail_fast, "reportToConsole": false, "disableLog": quiet, "filter": filter })
+  } else {
+    json!({ "exitOnFail": false, "failFast": fail_fast, "reportToConsole": false, "disableLog": quiet })
+  };
+
+  format!(
+    "import \"{}\";\nDeno[Deno.internal].runTests({{ ...{}, onMessage: Deno[Deno.internal].postTestMessage }});\n",
+    module.to_string(),
+    options
+  )
+}
+
+/// Events sent from the threads of a parallel test run to the thread
+/// reporting it. Every event carries the index of its test module.
+pub enum TestJobEvent {
+  Message(usize, Value),
+  Done(usize, Result<(), String>),
+}
+
+/// The results of a test run, as found in the "end" message of the test
+/// runner.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TestRunSummary {
+  passed: usize,
+  failed: usize,
+  ignored: usize,
+  measured: usize,
+  filtered: usize,
+  used_only: bool,
+  duration: u64,
+  results: Vec<Value>,
+}
+
+impl TestRunSummary {
+  fn add(&mut self, other: TestRunSummary) {
+    self.passed += other.passed;
+    self.failed += other.failed;
+    self.ignored += other.ignored;
+    self.measured += other.measured;
+    self.filtered += other.filtered;
+    self.used_only |= other.used_only;
+    self.results.extend(other.results);
+  }
+}
+
+async fn run_test_job(
+  global_state: &Arc<GlobalState>,
+  index: usize,
+  test_file: String,
+  sender: mpsc::UnboundedSender<TestJobEvent>,
+) -> Result<(), AnyError> {
+  let cwd = std::env::current_dir().expect("No current directory");
+  let test_file_url =
+    Url::from_file_path(cwd.join(format!("$deno$test_job_{}.js", index)))
+      .expect("Should be valid file url");
+  let main_module =
+    ModuleSpecifier::resolve_url(&test_file_url.to_string()).unwrap();
+  // The entry point is only kept in memory, it has no emit to load when
+  // JavaScript is type checked too.
+  let source_file = SourceFile {
+    filename: test_file_url.to_file_path().unwrap(),
+    url: test_file_url,
+    types_header: None,
+    media_type: MediaType::JavaScript,
+    source_code: test_file,
+  };
+  global_state
+    .file_fetcher
+    .save_source_file_in_cache(&main_module, source_file);
+
+  let mut worker = MainWorker::new(global_state, main_module.clone());
+  {
+    let op_state = worker.op_state();
+    let mut op_state = op_state.borrow_mut();
+    op_state.put(TestMessageSender { index, sender });
+    // The test modules were fetched and type checked before the jobs started,
+    // load them from the cache instead of checking them again on every thread.
+    if !global_state.ts_compiler.compile_js {
+      op_state.put(PreparedMainModule);
+    }
+  }
+  worker.execute_module(&main_module).await?;
+  worker.execute("window.dispatchEvent(new Event('load'))")?;
+  (&mut *worker).await?;
+  worker.execute("window.dispatchEvent(new Event('unload'))")?;
+  (&mut *worker).await?;
+  Ok(())
+}
+
+/// Returns a "start" message listing the tests of all modules that are not
+/// excluded, in the order of the modules.
+fn plan_message(plans: &[Option<Vec<Value>>], excluded: &[bool]) -> Value {
+  let tests: Vec<&Value> = plans
+    .iter()
+    .zip(excluded)
+    .filter(|(_, excluded)| !**excluded)
+    .flat_map(|(plan, _)| plan.iter().flatten())
+    .collect();
+  json!({ "start": { "tests": tests } })
+}
+
+/// Returns which modules are left out of the report of a parallel run. When
+/// a module uses the "only" option, a serial run only runs those tests, so
+/// the modules that don't use it are left out. Modules that failed to run
+/// are always reported.
+fn excluded_modules(used_only: &[bool], errored: &[bool]) -> Vec<bool> {
+  let any_used_only = used_only.iter().any(|used_only| *used_only);
+  used_only
+    .iter()
+    .zip(errored)
+    .map(|(used_only, errored)| any_used_only && !used_only && !errored)
+    .collect()
+}
+
+/// Returns whether a module that is not excluded has failed tests.
+fn has_failures(summaries: &[TestRunSummary], excluded: &[bool]) -> bool {
+  summaries
+    .iter()
+    .zip(excluded)
+    .any(|(summary, excluded)| !excluded && summary.failed > 0)
+}
+
+/// Runs every test module in its own isolate, spread over `jobs` threads.
+/// Each isolate gets its own copy of the permissions. The messages of the
+/// test runners are reported by `worker` in the order of `test_modules`, so
+/// the report does not depend on which module finishes first. As in a serial
+/// run, the "only" option applies to the whole run: when a module uses it,
+/// the modules that don't are left out of the report.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_tests_in_parallel(
+  global_state: &Arc<GlobalState>,
+  worker: &mut MainWorker,
+  test_modules: Vec<Url>,
+  jobs: usize,
+  fail_fast: bool,
+  quiet: bool,
+  filter: Option<String>,
+  reporter: &str,
+) -> Result<(), AnyError> {
+  let start = Instant::now();
+  let module_count = test_modules.len();
+  let queue = Arc::new(Mutex::new(
+    test_modules
+      .iter()
+      .cloned()
+      .enumerate()
+      .collect::<VecDeque<_>>(),
+  ));
+  let cancelled = Arc::new(AtomicBool::new(false));
+  let (sender, mut receiver) = mpsc::unbounded::<TestJobEvent>();
+
+  let mut join_handles = vec![];
+  for job in 0..jobs.min(module_count) {
+    let global_state = global_state.clone();
+    let queue = queue.clone();
+    let cancelled = cancelled.clone();
+    let sender = sender.clone();
+    let filter = filter.clone();
+    let join_handle = std::thread::Builder::new()
+      .name(format!("deno-test-{}", job))
+      .spawn(move || loop {
+        if cancelled.load(Ordering::SeqCst) {
+          break;
+        }
+        let (index, module) = match queue.lock().unwrap().pop_front() {
+          Some(next) => next,
+          None => break,
+        };
+        let test_file =
+          render_test_job_file(&module, fail_fast, quiet, filter.clone());
+        let result = tokio_util::run_basic(run_test_job(
+          &global_state,
+          index,
+          test_file,
+          sender.clone(),
+        ));
+        let result = result.map_err(|err| err.to_string());
+        if sender
+          .unbounded_send(TestJobEvent::Done(index, result))
+          .is_err()
+        {
+          break;
+        }
+      })?;
+    join_handles.push(join_handle);
+  }
+  drop(sender);
+
+  // Machine readable reports are written even when quiet.
+  let report_to_console = !quiet || reporter != "pretty";
+  let mut report = |message: &Value| -> Result<(), AnyError> {
+    if report_to_console {
+      worker.execute(&format!(
+        "Deno[Deno.internal].reportTestMessage({}, {})",
+        json!(reporter),
+        message
+      ))?;
+    }
+    Ok(())
+  };
+
+  let mut summaries: Vec<TestRunSummary> = (0..module_count)
+    .map(|_| TestRunSummary::default())
+    .collect();
+  let mut used_only = vec![false; module_count];
+  let mut errored = vec![false; module_count];
+  // Decided once the plan is reported, the messages of excluded modules are
+  // dropped.
+  let mut excluded = vec![false; module_count];
+  let mut buffered: Vec<Vec<Value>> = vec![vec![]; module_count];
+  let mut done = vec![false; module_count];
+  // The tests of every module, from its "start" message. They are reported
+  // as a single "start" message, like a serial run does, once every module
+  // has started or is done. Nothing else is reported before it.
+  let mut plans: Vec<Option<Vec<Value>>> = vec![None; module_count];
+  let mut plan_reported = false;
+  // Index of the module whose messages are reported as they arrive, all
+  // other messages are buffered until the modules before them are done.
+  let mut current = 0;
+
+  while let Some(event) = receiver.next().await {
+    let (index, messages) = match event {
+      TestJobEvent::Message(index, message) => (index, vec![message]),
+      TestJobEvent::Done(index, result) => {
+        done[index] = true;
+        let mut messages = vec![];
+        // Report a module that failed to load or threw an uncaught error
+        // as a failed test named after the module.
+        if let Err(message) = result {
+          errored[index] = true;
+          excluded[index] = false;
+          let name = test_modules[index].to_string();
+          if plans[index].is_none() {
+            messages.push(json!({ "start": { "tests": [
+              { "name": name, "ignore": false, "only": false }
+            ] } }));
+          }
+          let error =
+            json!({ "name": "Error", "message": message, "stack": message });
+          let result = json!({ "name": name, "status": "failed", "duration": 0, "error": error });
+          messages
+            .push(json!({ "testStart": { "name": name, "ignore": false } }));
+          messages.push(json!({ "testEnd": result }));
+          messages.push(json!({ "end": { "failed": 1, "duration": 0, "results": [result] } }));
+        }
+        (index, messages)
+      }
+    };
+
+    for message in messages {
+      if let Some(end) = message.get("end") {
+        let end: TestRunSummary = serde_json::from_value(end.clone())?;
+        // Failures of modules that may still be excluded only cancel the
+        // run once the plan is reported.
+        if end.failed > 0 && fail_fast && plan_reported && !excluded[index] {
+          cancelled.store(true, Ordering::SeqCst);
+        }
+        summaries[index].add(end);
+      } else if let Some(start) = message.get("start") {
+        let tests = start["tests"].as_array().cloned().unwrap_or_default();
+        plans[index].get_or_insert_with(Vec::new).extend(tests);
+        used_only[index] |= start["usedOnly"].as_bool().unwrap_or(false);
+      } else if plan_reported && index == current {
+        if !excluded[index] {
+          report(&message)?;
+        }
+      } else {
+        buffered[index].push(message);
+      }
+    }
+
+    if !plan_reported
+      && plans
+        .iter()
+        .zip(&done)
+        .all(|(plan, done)| plan.is_some() || *done)
+    {
+      excluded = excluded_modules(&used_only, &errored);
+      if fail_fast && has_failures(&summaries, &excluded) {
+        cancelled.store(true, Ordering::SeqCst);
+      }
+      report(&plan_message(&plans, &excluded))?;
+      plan_reported = true;
+    }
+
+    if plan_reported {
+      while current < module_count {
+        for message in buffered[current].drain(..) {
+          if !excluded[current] {
+            report(&message)?;
+          }
+        }
+        if !done[current] {
+          break;
+        }
+        current += 1;
+      }
+    }
+  }
+
+  // Modules skipped after a failure with --failfast never start or finish,
+  // report the plan of the others and what they have sent.
+  if !plan_reported {
+    excluded = excluded_modules(&used_only, &errored);
+    report(&plan_message(&plans, &excluded))?;
+  }
+  for (messages, excluded) in buffered.iter_mut().zip(&excluded) {
+    for message in messages.drain(..) {
+      if !excluded {
+        report(&message)?;
+      }
+    }
+  }
+
+  for join_handle in join_handles {
+    join_handle.join().expect("Test job thread panicked");
+  }
+
+  let mut summary = TestRunSummary::default();
+  for (module_summary, excluded) in summaries.into_iter().zip(&excluded) {
+    if !excluded {
+      summary.add(module_summary);
+    }
+  }
+  summary.duration = start.elapsed().as_millis() as u64;
+  let end = json!({ "end": summary });
+  report(&end)?;
+
+  if summary.failed > 0 || summary.used_only {
+    std::process::exit(1);
+  }
+
+  Ok(())
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -131,6 +499,22 @@ mod tests {
     assert_eq!(matched_urls, expected);
   }
 
+  #[test]
+  fn test_excluded_modules() {
+    assert_eq!(
+      excluded_modules(&[false, false], &[false, false]),
+      vec![false, false]
+    );
+    assert_eq!(
+      excluded_modules(&[true, false, false], &[false, false, true]),
+      vec![false, true, false]
+    );
+    assert_eq!(
+      excluded_modules(&[true, true], &[false, false]),
+      vec![false, false]
+    );
+  }
+
   #[test]
   fn test_is_supported() {
     assert!(is_supported(Path::new("tests/subdir/foo_test.ts")));
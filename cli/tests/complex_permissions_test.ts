@@ -43,6 +43,35 @@ const test: { [key: string]: (...args: any[]) => void | Promise<void> } = {
       listener.close();
     }
   },
+  env(vars: string[]): void {
+    vars.forEach((key) => Deno.env.get(key));
+  },
+  async run(cmds: string[]): Promise<void> {
+    for (const cmd of cmds) {
+      const p = Deno.run({ cmd: [cmd, "--version"], stdout: "null" });
+      await p.status();
+      p.close();
+    }
+  },
+  async runWithPath(cmds: string[]): Promise<void> {
+    for (const cmd of cmds) {
+      const p = Deno.run({
+        cmd: [cmd, "--version"],
+        env: { PATH: "." },
+        stdout: "null",
+      });
+      await p.status();
+      p.close();
+    }
+  },
+  async runKill(cmds: string[]): Promise<void> {
+    for (const cmd of cmds) {
+      const p = Deno.run({ cmd: [cmd, "eval", "setTimeout(() => {}, 60000)"] });
+      p.kill(Deno.Signal.SIGTERM);
+      await p.status();
+      p.close();
+    }
+  },
 };
 
 if (!test[name]) {
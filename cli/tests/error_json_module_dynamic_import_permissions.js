@@ -0,0 +1,3 @@
+(async () => {
+  await import("http://localhost:4545/cli/tests/subdir/config.json");
+})();
@@ -0,0 +1,3 @@
+import config from "./subdir/json_invalid.json";
+
+console.log(config);
@@ -0,0 +1,4 @@
+import config from "./subdir/config.json";
+
+const bar: string = config.foo.bar;
+console.log(bar);
@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use deno_core::futures;
 use deno_core::futures::prelude::*;
+use deno_core::serde_json;
 use deno_core::url;
 use std::io::{BufRead, Write};
 use std::process::Command;
@@ -1584,8 +1585,31 @@ itest!(_019_media_types {
 });
 
 itest!(_020_json_modules {
-  args: "run --reload 020_json_modules.ts",
+  args: "run --quiet --reload 020_json_modules.ts",
   output: "020_json_modules.ts.out",
+});
+
+itest!(_020_json_modules_no_check {
+  args: "run --quiet --reload --no-check 020_json_modules.ts",
+  output: "020_json_modules.ts.out",
+});
+
+itest!(_020_json_modules_remote {
+  args:
+    "run --quiet --reload http://127.0.0.1:4545/cli/tests/020_json_modules.ts",
+  output: "020_json_modules.ts.out",
+  http_server: true,
+});
+
+itest!(error_json_module_type {
+  args: "run --reload error_json_module_type.ts",
+  output: "error_json_module_type.ts.out",
+  exit_code: 1,
+});
+
+itest!(error_json_module_invalid {
+  args: "run --reload error_json_module_invalid.js",
+  output: "error_json_module_invalid.js.out",
   exit_code: 1,
 });
 
@@ -1640,6 +1664,12 @@ itest!(deno_test_only {
   output: "deno_test_only.ts.out",
 });
 
+itest!(deno_test_sanitizer {
+  args: "test --allow-net --allow-read test_sanitizer.ts",
+  exit_code: 1,
+  output: "test_sanitizer.out",
+});
+
 itest!(deno_test_no_check {
   args: "test --no-check test_runner_test.ts",
   exit_code: 1,
@@ -1955,6 +1985,13 @@ itest!(lock_dynamic_imports {
   http_server: true,
 });
 
+itest!(lock_check_err_json_module {
+  args: "run --lock=lock_check_err_json_module.json http://127.0.0.1:4545/cli/tests/020_json_modules.ts",
+  output: "lock_check_err_json_module.out",
+  exit_code: 10,
+  http_server: true,
+});
+
 itest!(lock_check_err {
   args: "run --lock=lock_check_err.json http://127.0.0.1:4545/cli/tests/003_relative_import.ts",
   output: "lock_check_err.out",
@@ -1976,6 +2013,266 @@ itest!(lock_check_err_with_bundle {
   http_server: true,
 });
 
+#[test]
+fn vendor_remote_modules() {
+  let _g = util::http_server();
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let vendor_dir = temp_dir.path().join("vendor");
+
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", temp_dir.path().join("deno_dir"))
+    .arg("vendor")
+    .arg("--unstable")
+    .arg(format!("--output={}", vendor_dir.to_str().unwrap()))
+    .arg("006_url_imports.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(status.success());
+  assert!(vendor_dir
+    .join("localhost_4545/cli/tests/subdir/mod2.ts")
+    .is_file());
+  assert!(vendor_dir
+    .join("localhost_4545/cli/tests/subdir/print_hello.ts")
+    .is_file());
+
+  // With an empty DENO_DIR and remote modules disallowed, the program can
+  // only run from the vendored copies.
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", temp_dir.path().join("empty_deno_dir"))
+    .arg("run")
+    .arg("--unstable")
+    .arg("--no-remote")
+    .arg(format!(
+      "--importmap={}",
+      vendor_dir.join("import_map.json").to_str().unwrap()
+    ))
+    .arg("006_url_imports.ts")
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(stdout, "Hello\nsuccess\n");
+
+  // Vendoring again requires --force, as the output directory is not empty.
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", temp_dir.path().join("deno_dir"))
+    .arg("vendor")
+    .arg("--unstable")
+    .arg(format!("--output={}", vendor_dir.to_str().unwrap()))
+    .arg("006_url_imports.ts")
+    .stderr(std::process::Stdio::null())
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(!status.success());
+
+  // With --force, the output directory is kept when a module fails to load.
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", temp_dir.path().join("deno_dir"))
+    .arg("vendor")
+    .arg("--unstable")
+    .arg("--force")
+    .arg(format!("--output={}", vendor_dir.to_str().unwrap()))
+    .arg("does_not_exist.ts")
+    .stderr(std::process::Stdio::null())
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(!status.success());
+  assert!(vendor_dir.join("import_map.json").is_file());
+
+  // A directory without an import map is never replaced.
+  let other_dir = temp_dir.path().join("other");
+  std::fs::create_dir(&other_dir).unwrap();
+  std::fs::write(other_dir.join("keep.txt"), "keep").unwrap();
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", temp_dir.path().join("deno_dir"))
+    .arg("vendor")
+    .arg("--unstable")
+    .arg("--force")
+    .arg(format!("--output={}", other_dir.to_str().unwrap()))
+    .arg("006_url_imports.ts")
+    .stderr(std::process::Stdio::null())
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(!status.success());
+  assert!(other_dir.join("keep.txt").is_file());
+}
+
+#[test]
+fn dir_list_and_prune() {
+  let _g = util::http_server();
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let deno_dir = temp_dir.path().join("deno_dir");
+  let dir_list = || {
+    let output = util::deno_cmd()
+      .current_dir(util::tests_path())
+      .env("DENO_DIR", &deno_dir)
+      .arg("dir")
+      .arg("list")
+      .arg("--unstable")
+      .arg("--json")
+      .output()
+      .expect("failed to spawn script");
+    assert!(output.status.success());
+    let modules: serde_json::Value =
+      serde_json::from_slice(&output.stdout).unwrap();
+    modules
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|m| m["url"].as_str().unwrap().to_string())
+      .collect::<Vec<_>>()
+  };
+
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", &deno_dir)
+    .arg("cache")
+    .arg("006_url_imports.ts")
+    .arg("http://localhost:4545/cli/tests/subdir/mod1.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(status.success());
+  let urls = dir_list();
+  assert!(urls
+    .contains(&"http://localhost:4545/cli/tests/subdir/mod1.ts".to_string()));
+  assert!(urls
+    .contains(&"http://localhost:4545/cli/tests/subdir/mod2.ts".to_string()));
+
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", &deno_dir)
+    .arg("dir")
+    .arg("headers")
+    .arg("--unstable")
+    .arg("http://localhost:4545/cli/tests/subdir/mod2.ts")
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert!(stdout.contains("content-type: "));
+
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", &deno_dir)
+    .arg("dir")
+    .arg("prune")
+    .arg("--unstable")
+    .arg("006_url_imports.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(status.success());
+  assert_eq!(
+    dir_list(),
+    vec![
+      "http://localhost:4545/cli/tests/subdir/mod2.ts".to_string(),
+      "http://localhost:4545/cli/tests/subdir/print_hello.ts".to_string(),
+    ]
+  );
+}
+
+#[test]
+fn auth_tokens() {
+  let _g = util::http_server();
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .env("DENO_DIR", temp_dir.path())
+    .arg("run")
+    .arg("http://127.0.0.1:4545/auth_script.ts")
+    .stderr(std::process::Stdio::piped())
+    .output()
+    .expect("failed to spawn script");
+  assert!(!output.status.success());
+  let stderr = std::str::from_utf8(&output.stderr).unwrap();
+  assert!(stderr.contains("401 Unauthorized"));
+
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .env("DENO_DIR", temp_dir.path())
+    .env("DENO_AUTH_TOKENS", "abcdef123456@127.0.0.1:4545")
+    .arg("run")
+    .arg("http://127.0.0.1:4545/auth_script.ts")
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(stdout, "authorized\n");
+
+  // The credentials must not be cached with the module.
+  for entry in walkdir::WalkDir::new(temp_dir.path().join("deps")) {
+    let entry = entry.unwrap();
+    if entry.file_type().is_file() {
+      let contents = std::fs::read_to_string(entry.path()).unwrap();
+      assert!(!contents.contains("abcdef123456"));
+    }
+  }
+}
+
+#[test]
+fn lock_write_prunes_and_locks_local_modules() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let lockfile = temp_dir.path().join("lock.json");
+  std::fs::write(
+    &lockfile,
+    r#"{ "https://deno.land/x/stale/mod.ts": "bad" }"#,
+  )
+  .unwrap();
+
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("cache")
+    .arg(format!("--lock={}", lockfile.to_str().unwrap()))
+    .arg("--lock-write")
+    .arg("--lock-local")
+    .arg("subdir/mod1.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(status.success());
+
+  let lockfile_json: serde_json::Value =
+    serde_json::from_str(&std::fs::read_to_string(&lockfile).unwrap()).unwrap();
+  assert_eq!(lockfile_json["version"], "2");
+  assert_eq!(lockfile_json["remote"], serde_json::json!({}));
+  // Local modules are keyed by their path relative to the lock file.
+  let local = lockfile_json["local"].as_object().unwrap();
+  assert!(local
+    .keys()
+    .any(|key| key.starts_with("../") && key.ends_with("/subdir/mod1.ts")));
+  assert_eq!(local.len(), 3);
+}
+
+itest!(lock_check_redirect_ok {
+  args: "run --quiet --reload --lock=lock_check_redirect_ok.json 027_redirect_typescript.ts",
+  output: "027_redirect_typescript.ts.out",
+  http_server: true,
+});
+
+itest!(lock_check_redirect_err {
+  args: "run --reload --lock=lock_check_redirect_err.json 027_redirect_typescript.ts",
+  output: "lock_check_redirect_err.out",
+  exit_code: 10,
+  http_server: true,
+});
+
 itest!(async_error {
   exit_code: 1,
   args: "run --reload async_error.ts",
@@ -2134,6 +2431,13 @@ itest!(error_015_dynamic_import_permissions {
   http_server: true,
 });
 
+itest!(error_json_module_dynamic_import_permissions {
+  args: "run --reload --quiet error_json_module_dynamic_import_permissions.js",
+  output: "error_json_module_dynamic_import_permissions.out",
+  exit_code: 1,
+  http_server: true,
+});
+
 // We have an allow-net flag but not allow-read, it should still result in error.
 itest!(error_016_dynamic_import_permissions2 {
   args: "run --reload --allow-net error_016_dynamic_import_permissions2.js",
@@ -2559,12 +2863,145 @@ itest!(proto_exploit {
   output: "proto_exploit.js.out",
 });
 
+itest!(deno_test_reporter_jsonl {
+  args: "test --reporter=jsonl test_reporter.ts",
+  output: "test_reporter_jsonl.out",
+  exit_code: 1,
+});
+
+itest!(deno_test_reporter_junit {
+  args: "test --reporter=junit test_reporter.ts",
+  output: "test_reporter_junit.out",
+  exit_code: 1,
+});
+
+itest!(deno_test_jobs {
+  args:
+    "test --jobs=2 --reporter=jsonl test_jobs/a_test.ts test_jobs/b_test.ts",
+  output: "test_jobs.out",
+  exit_code: 1,
+});
+
+itest!(deno_test_jobs_only {
+  args: "test --jobs=2 --reporter=jsonl test_jobs_only/a_test.ts test_jobs_only/b_test.ts",
+  output: "test_jobs_only.out",
+  exit_code: 1,
+});
+
+#[test]
+fn deno_test_jobs_type_checks_once() {
+  let deno_dir = TempDir::new().expect("tempdir fail");
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .env("DENO_DIR", deno_dir.path())
+    .arg("test")
+    .arg("--jobs=2")
+    .arg("test_jobs/a_test.ts")
+    .arg("test_jobs/b_test.ts")
+    .output()
+    .expect("failed to spawn script");
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  let stderr = std::str::from_utf8(&output.stderr).unwrap();
+  let checks =
+    stdout.matches("Check ").count() + stderr.matches("Check ").count();
+  assert_eq!(checks, 1);
+}
+
 itest!(deno_test_coverage {
   args: "test --coverage --unstable test_coverage.ts",
   output: "test_coverage.out",
   exit_code: 0,
 });
 
+#[test]
+fn deno_test_coverage_dir() {
+  let coverage_dir = TempDir::new().expect("tempdir fail");
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("test")
+    .arg("--unstable")
+    .arg("--quiet")
+    .arg(format!(
+      "--coverage={}",
+      coverage_dir.path().to_str().unwrap()
+    ))
+    .arg("test_coverage.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for child process");
+  assert!(status.success());
+
+  let tracefiles = std::fs::read_dir(coverage_dir.path())
+    .unwrap()
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .filter(|path| path.extension().unwrap() == "info")
+    .collect::<Vec<_>>();
+  assert_eq!(tracefiles.len(), 1);
+  let lcov = std::fs::read_to_string(&tracefiles[0]).unwrap();
+  let mod1_path = util::tests_path().join("subdir").join("mod1.ts");
+  assert!(lcov.contains(&format!("SF:{}", mod1_path.to_str().unwrap())));
+  // `return "Hi";` is on line 4 of the TypeScript source.
+  assert!(lcov.contains("DA:4,1"));
+  assert!(lcov.contains("end_of_record"));
+
+  let raw_profiles = std::fs::read_dir(coverage_dir.path())
+    .unwrap()
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| entry.path().extension().unwrap() == "json")
+    .count();
+  assert_eq!(raw_profiles, 3);
+}
+
+#[test]
+fn deno_coverage_merges_profiles() {
+  let coverage_dir = TempDir::new().expect("tempdir fail");
+  // Run the same tests twice to simulate two shards saving their profiles
+  // into one directory.
+  for _ in 0..2 {
+    let status = util::deno_cmd()
+      .current_dir(util::tests_path())
+      .arg("test")
+      .arg("--unstable")
+      .arg("--quiet")
+      .arg(format!(
+        "--coverage={}",
+        coverage_dir.path().to_str().unwrap()
+      ))
+      .arg("test_coverage.ts")
+      .spawn()
+      .expect("failed to spawn script")
+      .wait()
+      .expect("failed to wait for child process");
+    assert!(status.success());
+  }
+
+  // Neither run overwrites the tracefile of the other.
+  let tracefiles = std::fs::read_dir(coverage_dir.path())
+    .unwrap()
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| entry.path().extension().unwrap() == "info")
+    .count();
+  assert_eq!(tracefiles, 2);
+
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("coverage")
+    .arg("--unstable")
+    .arg("--lcov")
+    .arg(coverage_dir.path())
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let lcov = std::str::from_utf8(&output.stdout).unwrap();
+  let mod1_path = util::tests_path().join("subdir").join("mod1.ts");
+  let mod1_record = format!("SF:{}", mod1_path.to_str().unwrap());
+  // Both profiles of a module are merged into a single record.
+  assert_eq!(lcov.matches(&mod1_record).count(), 1);
+  assert!(lcov.contains("DA:4,2"));
+}
+
 itest!(deno_lint {
   args: "lint --unstable lint/file1.js lint/file2.ts lint/ignored_file.ts",
   output: "lint/expected.out",
@@ -3054,6 +3491,30 @@ fn test_permissions_net_fetch_localhost() {
   assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
 }
 
+#[test]
+fn test_permissions_net_fetch_allow_ip_range() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+			"run --allow-net=127.0.0.0/8 complex_permissions_test.ts netFetch http://127.0.0.1:4545/",
+			None,
+			None,
+			true,
+		);
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_net_fetch_host_name_with_ip_range_fail() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+			"run --allow-net=127.0.0.0/8,localhost complex_permissions_test.ts netFetch http://localhost:4545/",
+			None,
+			None,
+			true,
+		);
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
 #[test]
 fn test_permissions_net_connect_allow_localhost_ip_4555() {
   let (_, err) = util::run_and_collect_output(
@@ -3152,6 +3613,253 @@ fn test_permissions_net_listen_allow_localhost() {
   assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
 }
 
+#[test]
+fn test_permissions_deny_read_fail() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-read --deny-read=subdir complex_permissions_test.ts read subdir/mod1.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_deny_read_other_path() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --allow-read --deny-read=subdir complex_permissions_test.ts read complex_permissions_test.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_policy_allow_read() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --permissions=permission_policy.json complex_permissions_test.ts read complex_permissions_test.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_policy_deny_read_fail() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --permissions=permission_policy.json complex_permissions_test.ts read subdir/mod1.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_policy_toml() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --permissions=permission_policy.toml complex_permissions_test.ts read complex_permissions_test.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --permissions=permission_policy.toml complex_permissions_test.ts read subdir/mod1.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_policy_escalation() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-read --permissions=permission_policy.json complex_permissions_test.ts read complex_permissions_test.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains("escalate the permissions of the policy file"));
+}
+
+#[test]
+fn test_permissions_audit() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let audit_path = temp_dir.path().join("audit.jsonl");
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("--allow-read")
+    .arg(format!("--permission-audit={}", audit_path.display()))
+    .arg("--permission-audit-summary")
+    .arg("complex_permissions_test.ts")
+    .arg("read")
+    .arg("complex_permissions_test.ts")
+    .stderr(std::process::Stdio::piped())
+    .spawn()
+    .unwrap()
+    .wait_with_output()
+    .unwrap();
+  assert!(output.status.success());
+  let stderr = std::str::from_utf8(&output.stderr).unwrap();
+  assert!(stderr.contains("--allow-read="));
+  let audit = std::fs::read_to_string(&audit_path).unwrap();
+  let record = audit
+    .lines()
+    .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+    .find(|record| {
+      record["permission"] == "read"
+        && record["target"]
+          .as_str()
+          .unwrap()
+          .ends_with("complex_permissions_test.ts")
+    })
+    .expect("read check not recorded");
+  assert_eq!(record["result"], "granted");
+  assert!(record["location"]
+    .as_str()
+    .unwrap()
+    .contains("complex_permissions_test.ts"));
+}
+
+#[test]
+fn trace_ops() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let trace_path = temp_dir.path().join("trace.json");
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("--allow-read")
+    .arg(format!("--trace-ops={}", trace_path.display()))
+    .arg("complex_permissions_test.ts")
+    .arg("read")
+    .arg("complex_permissions_test.ts")
+    .spawn()
+    .unwrap()
+    .wait()
+    .unwrap();
+  assert!(status.success());
+  // The events are written as they happen, so the array isn't closed.
+  let trace = std::fs::read_to_string(&trace_path).unwrap();
+  let events: Vec<serde_json::Value> =
+    serde_json::from_str(&format!("{}{{}}]", trace)).unwrap();
+  assert!(events
+    .iter()
+    .any(|event| event["name"] == "op_open_sync" && event["ph"] == "X"));
+}
+
+#[test]
+fn test_permissions_env_allow_variable() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --allow-env=PATH,HOME complex_permissions_test.ts env PATH HOME",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_env_allow_other_variable_fail() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-env=PATH complex_permissions_test.ts env HOME",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_run_allow_executable() {
+  let deno_exe = util::deno_exe_path()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    true,
+    &format!(
+      "run --allow-run={0} complex_permissions_test.ts run {0}",
+      deno_exe
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_run_allow_other_executable_fail() {
+  let deno_exe = util::deno_exe_path()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    false,
+    &format!(
+      "run --allow-run=git complex_permissions_test.ts run {}",
+      deno_exe
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_run_allow_name_with_other_path_fail() {
+  let deno_dir = util::deno_exe_path()
+    .parent()
+    .unwrap()
+    .to_path_buf()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-run=deno complex_permissions_test.ts runWithPath deno",
+    None,
+    Some(vec![("PATH".to_string(), deno_dir)]),
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_permissions_run_allow_executable_kill() {
+  let deno_exe = util::deno_exe_path()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    true,
+    &format!(
+      "run --unstable --allow-run={0} complex_permissions_test.ts runKill {0}",
+      deno_exe
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
 fn inspect_flag_with_unique_port(flag_prefix: &str) -> String {
   use std::sync::atomic::{AtomicU16, Ordering};
   static PORT: AtomicU16 = AtomicU16::new(9229);
@@ -0,0 +1,4 @@
+onmessage = async function (e): Promise<void> {
+  const { state } = await Deno.permissions.query({ name: e.data });
+  postMessage(state);
+};
@@ -0,0 +1,10 @@
+onmessage = async function (e): Promise<void> {
+  try {
+    await Deno.readTextFile(e.data);
+    postMessage("granted");
+  } catch (err) {
+    postMessage(
+      err instanceof Deno.errors.PermissionDenied ? "denied" : "error",
+    );
+  }
+};
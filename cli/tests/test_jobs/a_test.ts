@@ -0,0 +1,3 @@
+Deno.test("slow", async function () {
+  await new Promise((resolve) => setTimeout(resolve, 200));
+});
@@ -0,0 +1,5 @@
+Deno.test("fast", function () {});
+
+Deno.test("fails", function () {
+  throw new Error("boom");
+});
@@ -0,0 +1,7 @@
+Deno.test({
+  name: "only",
+  only: true,
+  fn() {},
+});
+
+Deno.test("not only", function () {});
@@ -0,0 +1 @@
+Deno.test("other module", function () {});
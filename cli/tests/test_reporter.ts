@@ -0,0 +1,11 @@
+Deno.test("passes", function () {});
+
+Deno.test("fails", function () {
+  throw new Error("boom");
+});
+
+Deno.test({
+  name: "ignored",
+  ignore: true,
+  fn() {},
+});
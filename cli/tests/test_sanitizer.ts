@@ -0,0 +1,10 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+Deno.test("leakingOp", function () {
+  const listener = Deno.listen({ port: 4599 });
+  listener.accept();
+});
+
+Deno.test("leakingResource", function () {
+  Deno.openSync("test_sanitizer.ts");
+});
@@ -1,5 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { assert, unitTest } from "./test_util.ts";
+import { assert, assertEquals, unitTest } from "./test_util.ts";
 
 unitTest(async function metrics(): Promise<void> {
   const m1 = Deno.metrics();
@@ -56,3 +56,28 @@ unitTest(
     assert(metrics.opsDispatchedAsync === metrics.opsCompletedAsync);
   },
 );
+
+unitTest(
+  { perms: { write: true } },
+  async function metricsPerOp(): Promise<void> {
+    const filename = Deno.makeTempDirSync() + "/test.txt";
+    const before = Deno.metrics().ops["op_open_async"];
+
+    const file = await Deno.open(filename, { create: true, write: true });
+    file.close();
+
+    const m = Deno.metrics();
+    const op = m.ops["op_open_async"];
+    assertEquals(op.opsDispatched, (before?.opsDispatched ?? 0) + 1);
+    assertEquals(op.opsDispatchedAsync, op.opsDispatched);
+    assertEquals(op.opsCompleted, op.opsDispatched);
+    assertEquals(op.opsInFlight, 0);
+    assertEquals(op.latencyHistogram.length, m.latencyBucketsMs.length + 1);
+    assertEquals(
+      op.latencyHistogram.reduce((sum, count) => sum + count, 0),
+      op.opsCompleted,
+    );
+    assert(op.latencyMaxMs <= op.latencyTotalMs);
+    assert(m.ops["op_metrics"].opsDispatchedSync > 0);
+  },
+);
@@ -336,6 +336,92 @@ unitTest(
   },
 );
 
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true } },
+  async function runStdinChildStdout(): Promise<void> {
+    const p1 = Deno.run({
+      cmd: ["python", "-c", "import sys; sys.stdout.write('hello')"],
+      stdout: "piped",
+    });
+    const p2 = Deno.run({
+      cmd: [
+        "python",
+        "-c",
+        "import sys; sys.stdout.write(sys.stdin.read().upper())",
+      ],
+      stdin: p1.stdout.rid,
+      stdout: "piped",
+    });
+
+    // The pipe was moved to the second process.
+    assertThrows(() => p1.stdout.close(), Deno.errors.BadResource);
+    const output = await p2.output();
+    assertEquals(new TextDecoder().decode(output), "HELLO");
+    assertEquals((await p1.status()).code, 0);
+    assertEquals((await p2.status()).code, 0);
+    p1.close();
+    p2.close();
+  },
+);
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true, net: true } },
+  async function runStdoutStderrTcpStream(): Promise<void> {
+    const listener = Deno.listen({ hostname: "127.0.0.1", port: 3500 });
+    const acceptPromise = listener.accept();
+    const conn = await Deno.connect({ hostname: "127.0.0.1", port: 3500 });
+    const serverConn = await acceptPromise;
+
+    const p = Deno.run({
+      cmd: [
+        "python",
+        "-c",
+        "import sys; sys.stderr.write('error\\n'); sys.stdout.write('output\\n');",
+      ],
+      stdout: conn.rid,
+      stderr: conn.rid,
+    });
+
+    // The socket was moved to the process.
+    assertThrows(() => conn.close(), Deno.errors.BadResource);
+    assertEquals((await p.status()).code, 0);
+    p.close();
+
+    const text = new TextDecoder().decode(await Deno.readAll(serverConn));
+    assertStringContains(text, "error");
+    assertStringContains(text, "output");
+    serverConn.close();
+    listener.close();
+  },
+);
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true, net: true } },
+  async function runNotFoundKeepsTcpStream(): Promise<void> {
+    const listener = Deno.listen({ hostname: "127.0.0.1", port: 3500 });
+    const acceptPromise = listener.accept();
+    const conn = await Deno.connect({ hostname: "127.0.0.1", port: 3500 });
+    const serverConn = await acceptPromise;
+
+    assertThrows(
+      () =>
+        Deno.run({
+          cmd: ["this file hopefully doesn't exist"],
+          stdout: conn.rid,
+        }),
+      Deno.errors.NotFound,
+    );
+
+    // The socket is still usable, without blocking.
+    await conn.write(new TextEncoder().encode("hello"));
+    conn.close();
+    const text = new TextDecoder().decode(await Deno.readAll(serverConn));
+    assertEquals(text, "hello");
+    serverConn.close();
+    listener.close();
+  },
+);
+
 unitTest({ perms: { run: true } }, async function runEnv(): Promise<void> {
   const p = Deno.run({
     cmd: [
@@ -355,6 +441,62 @@ unitTest({ perms: { run: true } }, async function runEnv(): Promise<void> {
   p.close();
 });
 
+unitTest(
+  { perms: { run: true, env: true } },
+  async function runClearEnv(): Promise<void> {
+    const p = Deno.run({
+      cmd: [
+        "python",
+        "-c",
+        "import os, sys; sys.stdout.write(os.environ.get('FOO', '') + str('HOME' in os.environ))",
+      ],
+      clearEnv: true,
+      env: {
+        FOO: "0123",
+        PATH: Deno.env.get("PATH")!,
+      },
+      stdout: "piped",
+    });
+    const output = await p.output();
+    const s = new TextDecoder().decode(output);
+    assertEquals(s, "0123False");
+    p.close();
+  },
+);
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true } },
+  async function runRlimits(): Promise<void> {
+    const p = Deno.run({
+      cmd: [
+        "python",
+        "-c",
+        "import resource, sys; sys.stdout.write(str(resource.getrlimit(resource.RLIMIT_NOFILE)))",
+      ],
+      rlimits: { openFiles: 64 },
+      stdout: "piped",
+    });
+    const output = await p.output();
+    const s = new TextDecoder().decode(output);
+    assertEquals(s, "(64, 64)");
+    p.close();
+  },
+);
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true } },
+  async function runTimeout(): Promise<void> {
+    const p = Deno.run({
+      cmd: ["python", "-c", "from time import sleep; sleep(10000)"],
+      timeout: 100,
+    });
+    const status = await p.status();
+    assertEquals(status.success, false);
+    assertEquals(status.signal, Deno.Signal.SIGKILL);
+    p.close();
+  },
+);
+
 unitTest({ perms: { run: true } }, async function runClose(): Promise<void> {
   const p = Deno.run({
     cmd: [
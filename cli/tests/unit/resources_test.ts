@@ -55,3 +55,18 @@ unitTest({ perms: { read: true } }, async function resourcesFile(): Promise<
   })!;
   assertEquals(resourcesAfter[newRid], "fsFile");
 });
+
+unitTest({ perms: { read: true } }, function resourcesDetails(): void {
+  const before = Date.now();
+  const f = Deno.openSync("cli/tests/hello.txt");
+  const buf = new Uint8Array(5);
+  const nread = Deno.readSync(f.rid, buf);
+  const info = Deno.resources({ details: true })[f.rid];
+  f.close();
+
+  assertEquals(info.name, "fsFile");
+  assertEquals(info.bytesRead, nread);
+  assertEquals(info.bytesWritten, 0);
+  assert(info.createdAt >= before - 1000);
+  assert(info.createdAt <= Date.now() + 1000);
+});
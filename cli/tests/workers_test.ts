@@ -7,7 +7,11 @@
 // yet implemented. Once it gets implemented this file should be
 // again moved to `cli/js/` as an unit test file.
 
-import { assert, assertEquals } from "../../std/testing/asserts.ts";
+import {
+  assert,
+  assertEquals,
+  assertThrows,
+} from "../../std/testing/asserts.ts";
 
 export interface ResolvableMethods<T> {
   resolve: (value?: T | PromiseLike<T>) => void;
@@ -299,6 +303,63 @@ Deno.test({
   },
 });
 
+Deno.test({
+  name: "worker with restricted permissions",
+  fn: async function (): Promise<void> {
+    const promise = createResolvable();
+    const w = new Worker(
+      new URL("subdir/read_permission_worker.ts", import.meta.url).href,
+      {
+        type: "module",
+        deno: { namespace: true, permissions: { read: false } },
+      },
+    );
+    w.onmessage = (e): void => {
+      assertEquals(e.data, "denied");
+      promise.resolve();
+    };
+    w.postMessage("workers_test.ts");
+    await promise;
+    w.terminate();
+  },
+});
+
+Deno.test({
+  name: "worker permissions set to none are denied",
+  fn: async function (): Promise<void> {
+    const promise = createResolvable();
+    const w = new Worker(
+      new URL("subdir/permission_state_worker.ts", import.meta.url).href,
+      {
+        type: "module",
+        deno: { namespace: true, permissions: "none" },
+      },
+    );
+    w.onmessage = (e): void => {
+      assertEquals(e.data, "denied");
+      promise.resolve();
+    };
+    w.postMessage("read");
+    await promise;
+    w.terminate();
+  },
+});
+
+Deno.test({
+  name: "worker permissions can't escalate",
+  fn: function (): void {
+    assertThrows(() => {
+      new Worker(
+        new URL("subdir/read_permission_worker.ts", import.meta.url).href,
+        {
+          type: "module",
+          deno: { namespace: true, permissions: { write: true } },
+        },
+      );
+    }, Deno.errors.PermissionDenied);
+  },
+});
+
 Deno.test({
   name: "worker with crypto in scope",
   fn: async function (): Promise<void> {
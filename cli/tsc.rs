@@ -115,6 +115,8 @@ pub const AVAILABLE_LIBS: &[&str] = &[
 pub struct CompiledModule {
   pub code: String,
   pub name: String,
+  /// `MediaType::Json` for a JSON module, which is not compiled.
+  pub media_type: MediaType,
 }
 
 lazy_static! {
@@ -578,18 +580,17 @@ impl TsCompiler {
     // Check integrity of every file in module graph
     if let Some(ref lockfile) = global_state.lockfile {
       let mut g = lockfile.lock().unwrap();
+      let modules = module_graph_files.iter().map(|graph_file| {
+        (
+          graph_file.url.as_str(),
+          graph_file.redirect.as_deref(),
+          graph_file.source_code.as_str(),
+        )
+      });
 
-      for graph_file in &module_graph_files {
-        let check_passed =
-          g.check_or_insert(&graph_file.url, &graph_file.source_code);
-
-        if !check_passed {
-          eprintln!(
-            "Subresource integrity check failed --lock={}\n{}",
-            g.filename, graph_file.url
-          );
-          std::process::exit(10);
-        }
+      if let Err(err) = g.check_or_insert_modules(modules) {
+        eprintln!("{}", err);
+        std::process::exit(10);
       }
     }
     if let Some(ref lockfile) = global_state.lockfile {
@@ -716,6 +717,11 @@ impl TsCompiler {
         continue;
       }
 
+      // JSON modules are loaded from their source.
+      if source_file.media_type == MediaType::Json {
+        continue;
+      }
+
       if emitted_name.ends_with(".map") {
         self.cache_source_map(&specifier, &source.contents)?;
       } else if emitted_name.ends_with(".js") {
@@ -737,6 +743,7 @@ impl TsCompiler {
     let compiled_module = CompiledModule {
       code: compiled_source_file.source_code,
       name: module_url.to_string(),
+      media_type: MediaType::JavaScript,
     };
 
     Ok(compiled_module)
@@ -1544,6 +1551,7 @@ mod tests {
       false,
       false,
       None,
+      None,
     )
     .unwrap();
 
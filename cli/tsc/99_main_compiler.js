@@ -183,6 +183,8 @@ delete Object.prototype.__proto__;
         return fileName.endsWith(".d.ts") ? ts.Extension.Dts : ts.Extension.Ts;
       case MediaType.TSX:
         return ts.Extension.Tsx;
+      case MediaType.Json:
+        return ts.Extension.Json;
       case MediaType.Wasm:
         // Custom marker for Wasm type.
         return ts.Extension.Js;
@@ -331,10 +333,16 @@ delete Object.prototype.__proto__;
               ? sourceFile.filename
               : specifier;
 
+            // JSON modules may be served without a `.json` extension.
+            const scriptKind = sourceFile.mediaType === MediaType.Json
+              ? ts.ScriptKind.JSON
+              : undefined;
             sourceFile.tsSourceFile = ts.createSourceFile(
               tsSourceFileName,
               sourceFile.sourceCode,
               languageVersion,
+              false,
+              scriptKind,
             );
             sourceFile.tsSourceFile.version = sourceFile.versionHash;
             delete sourceFile.sourceCode;
@@ -139,7 +139,7 @@ fn download_package(
   let url = url.clone();
   let version = version.clone();
   let fut = async move {
-    match fetch_once(client.clone(), &url, None).await {
+    match fetch_once(client.clone(), &url, None, None).await {
       Ok(result) => {
         println!(
           "Version has been found\nDeno is upgrading to version {}",
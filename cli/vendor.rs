@@ -0,0 +1,177 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::media_type::MediaType;
+use crate::module_graph2::Graph2;
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+use deno_core::serde_json::json;
+use deno_core::url::Url;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The file, relative to the vendor directory, the import map is written to.
+pub const IMPORT_MAP_FILE: &str = "import_map.json";
+
+fn extension(media_type: MediaType) -> &'static str {
+  match media_type {
+    MediaType::JavaScript => ".js",
+    MediaType::JSX => ".jsx",
+    MediaType::TypeScript => ".ts",
+    MediaType::Dts => ".d.ts",
+    MediaType::TSX => ".tsx",
+    MediaType::Json => ".json",
+    MediaType::Wasm => ".wasm",
+    MediaType::BuildInfo | MediaType::Unknown => "",
+  }
+}
+
+fn has_matching_extension(file_name: &str, media_type: MediaType) -> bool {
+  match media_type {
+    MediaType::Dts => file_name.ends_with(".d.ts"),
+    MediaType::BuildInfo | MediaType::Unknown => true,
+    media_type => MediaType::from(Path::new(file_name)) == media_type,
+  }
+}
+
+/// Returns the path, relative to the vendor directory, that a remote module is
+/// written to, using `/` as separator: the host of the module, followed by the
+/// port if there is one, and the path of the module.
+///
+/// Deno determines the media type of local modules from their extension, so
+/// one is added when the extension of the path does not match the media type
+/// the module was served with. A query is folded into the file name.
+pub fn vendor_path(specifier: &Url, media_type: MediaType) -> String {
+  let host = specifier.host_str().unwrap_or("");
+  let host_dir = match specifier.port() {
+    Some(port) => format!("{}_{}", host, port),
+    None => host.to_string(),
+  };
+  let mut segments: Vec<String> = specifier
+    .path_segments()
+    .map(|segments| {
+      segments
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+        .collect()
+    })
+    .unwrap_or_default();
+  let mut file_name = segments.pop().unwrap_or_else(|| "index".to_string());
+
+  if let Some(query) = specifier.query() {
+    let hash = crate::checksum::gen(&[query.as_bytes()]);
+    file_name = match file_name.rfind('.') {
+      Some(index) if index > 0 => format!(
+        "{}_{}{}",
+        &file_name[..index],
+        &hash[..8],
+        &file_name[index..]
+      ),
+      _ => format!("{}_{}", file_name, &hash[..8]),
+    };
+  }
+  if !has_matching_extension(&file_name, media_type) {
+    file_name.push_str(extension(media_type));
+  }
+
+  let mut path = vec![host_dir];
+  path.extend(segments);
+  path.push(file_name);
+  path.join("/")
+}
+
+/// Writes every remote module of the graph into `output_dir` and generates an
+/// import map next to them, which maps the URLs of the modules to the written
+/// files. Returns the number of modules written.
+pub fn vendor(graph: &Graph2, output_dir: &Path) -> Result<usize, AnyError> {
+  let mut imports = BTreeMap::new();
+  let mut count = 0;
+
+  for (specifier, media_type, source) in graph.get_sources() {
+    let url = specifier.as_url();
+    if url.scheme() != "http" && url.scheme() != "https" {
+      continue;
+    }
+
+    let path = vendor_path(url, media_type);
+    let file_path = output_dir.join(&path);
+    fs::create_dir_all(file_path.parent().unwrap())?;
+    fs::write(&file_path, source)?;
+    count += 1;
+
+    // All modules of an origin are mapped by a single prefix, so relative
+    // imports between vendored modules keep resolving to the copies.
+    let host_dir = path.split('/').next().unwrap();
+    imports.insert(
+      format!("{}/", url.origin().ascii_serialization()),
+      format!("./{}/", host_dir),
+    );
+
+    // Modules whose file name was changed need an entry of their own, both for
+    // their URL and for the path a relative import from another vendored
+    // module resolves to.
+    let path_by_prefix = format!("{}{}", host_dir, url.path());
+    if url.query().is_some() || path_by_prefix != path {
+      let address = format!("./{}", path);
+      let relative = match url.query() {
+        Some(query) => format!("./{}?{}", path_by_prefix, query),
+        None => format!("./{}", path_by_prefix),
+      };
+      let mut exact = url.clone();
+      exact.set_fragment(None);
+      imports.insert(exact.to_string(), address.clone());
+      imports.insert(relative, address);
+    }
+  }
+
+  let import_map = json!({ "imports": imports });
+  fs::write(
+    output_dir.join(IMPORT_MAP_FILE),
+    serde_json::to_string_pretty(&import_map)?,
+  )?;
+
+  Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vendor_path_of(url: &str, media_type: MediaType) -> String {
+    vendor_path(&Url::parse(url).unwrap(), media_type)
+  }
+
+  #[test]
+  fn test_vendor_path() {
+    assert_eq!(
+      vendor_path_of(
+        "https://deno.land/std@0.74.0/fs/mod.ts",
+        MediaType::TypeScript
+      ),
+      "deno.land/std@0.74.0/fs/mod.ts"
+    );
+    assert_eq!(
+      vendor_path_of(
+        "http://localhost:4545/cli/tests/subdir/mod1.ts",
+        MediaType::TypeScript
+      ),
+      "localhost_4545/cli/tests/subdir/mod1.ts"
+    );
+    assert_eq!(
+      vendor_path_of("https://esm.sh/react", MediaType::JavaScript),
+      "esm.sh/react.js"
+    );
+    assert_eq!(
+      vendor_path_of("https://example.com/types.d.ts", MediaType::Dts),
+      "example.com/types.d.ts"
+    );
+    assert_eq!(
+      vendor_path_of("https://example.com/mod.ts?v=1", MediaType::TypeScript),
+      "example.com/mod_a798de8e.ts"
+    );
+    assert_eq!(
+      vendor_path_of("https://example.com/", MediaType::JavaScript),
+      "example.com/index.js"
+    );
+  }
+}
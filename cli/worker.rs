@@ -19,6 +19,7 @@ use deno_core::v8;
 use deno_core::JsRuntime;
 use deno_core::ModuleId;
 use deno_core::ModuleSpecifier;
+use deno_core::OpTracer;
 use deno_core::RuntimeOptions;
 use deno_core::Snapshot;
 use std::env;
@@ -126,6 +127,11 @@ impl Worker {
       let op_state = js_runtime.op_state();
       let mut op_state = op_state.borrow_mut();
       op_state.get_error_class_fn = &crate::errors::get_error_class_name;
+      // The sanitizers of `deno test` turn it on while their tests run.
+      op_state.track_op_call_sites =
+        global_state.flags.permission_audit.is_some();
+      op_state.op_tracer =
+        global_state.maybe_op_tracer.as_ref().map(OpTracer::fork);
     }
 
     let inspector =
@@ -311,6 +317,7 @@ impl MainWorker {
       ops::process::init(&mut worker);
       ops::runtime_compiler::init(&mut worker);
       ops::signal::init(&mut worker);
+      ops::testing::init(&mut worker);
       ops::tls::init(&mut worker);
       ops::tty::init(&mut worker);
       ops::websocket::init(&mut worker);
@@ -2,6 +2,9 @@
 
 use crate::error::AnyError;
 use crate::error::JsError;
+use crate::modules::SyntheticExport;
+use crate::ops::track_op_call_site;
+use crate::ops::with_op_call_site;
 use crate::runtime::JsRuntimeState;
 use crate::JsRuntime;
 use crate::Op;
@@ -427,7 +430,14 @@ fn send<'s>(
     }
   };
 
-  let op = OpTable::route_op(op_id, state.op_state.clone(), bufs);
+  let call_site = if state.op_state.borrow().track_op_call_sites {
+    current_call_site(scope)
+  } else {
+    None
+  };
+  let op = with_op_call_site(&call_site, || {
+    OpTable::route_op(op_id, state.op_state.clone(), bufs)
+  });
   assert_eq!(state.shared.size(), 0);
   match op {
     Op::Sync(buf) if !buf.is_empty() => {
@@ -435,11 +445,13 @@ fn send<'s>(
     }
     Op::Sync(_) => {}
     Op::Async(fut) => {
+      let fut = track_op_call_site(fut, call_site);
       let fut2 = fut.map(move |buf| (op_id, buf));
       state.pending_ops.push(fut2.boxed_local());
       state.have_unpolled_ops.set(true);
     }
     Op::AsyncUnref(fut) => {
+      let fut = track_op_call_site(fut, call_site);
       let fut2 = fut.map(move |buf| (op_id, buf));
       state.pending_unref_ops.push(fut2.boxed_local());
       state.have_unpolled_ops.set(true);
@@ -453,6 +465,29 @@ fn send<'s>(
   }
 }
 
+/// Returns the location of the innermost frame of the current stack trace that
+/// is not part of the runtime itself.
+fn current_call_site(scope: &mut v8::HandleScope) -> Option<String> {
+  let stack_trace = v8::StackTrace::current_stack_trace(scope, 32)?;
+  for index in 0..stack_trace.get_frame_count() {
+    let frame = stack_trace.get_frame(scope, index)?;
+    let script_name = match frame.get_script_name(scope) {
+      Some(script_name) => script_name.to_rust_string_lossy(scope),
+      None => continue,
+    };
+    if script_name.starts_with("deno:") {
+      continue;
+    }
+    return Some(format!(
+      "{}:{}:{}",
+      script_name,
+      frame.get_line_number(),
+      frame.get_column()
+    ));
+  }
+  None
+}
+
 fn set_macrotask_callback(
   scope: &mut v8::HandleScope,
   args: v8::FunctionCallbackArguments,
@@ -777,6 +812,40 @@ pub fn module_resolve_callback<'s>(
   None
 }
 
+/// Sets the exports of a synthetic module created by
+/// `JsRuntime::mod_new_synthetic()`.
+pub fn synthetic_module_evaluation_steps<'s>(
+  context: v8::Local<'s, v8::Context>,
+  module: v8::Local<'s, v8::Module>,
+) -> Option<v8::Local<'s, v8::Value>> {
+  let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+  let state_rc = JsRuntime::state(scope);
+  let exports = state_rc
+    .borrow_mut()
+    .synthetic_module_exports
+    .remove(&module.get_identity_hash())
+    .expect("Synthetic module exports not found");
+
+  for (name, export) in exports {
+    let name = v8::String::new(scope, &name).unwrap();
+    let value = match export {
+      SyntheticExport::Json(value) => {
+        let json = v8::String::new(scope, &value.to_string()).unwrap();
+        v8::json::parse(scope, json)?
+      }
+      SyntheticExport::Value(create_value) => create_value(scope),
+    };
+    module.set_synthetic_module_export(scope, name, value)?;
+  }
+
+  // With top-level await, the evaluation of a module results in a promise.
+  let resolver = v8::PromiseResolver::new(scope).unwrap();
+  let undefined = v8::undefined(scope);
+  resolver.resolve(scope, undefined.into());
+  Some(resolver.get_promise(scope).into())
+}
+
 // Returns promise details or throw TypeError, if argument passed isn't a Promise.
 // Promise details is a js_two elements array.
 // promise_details = [State, Result]
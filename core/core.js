@@ -258,8 +258,8 @@ SharedQueue Binary Layout
     promise.resolve(res);
   }
 
-  function resources() {
-    return jsonOpSync("op_resources");
+  function resources({ details = false } = {}) {
+    return jsonOpSync("op_resources", { details });
   }
 
   function close(rid) {
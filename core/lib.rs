@@ -12,6 +12,7 @@ mod gotham_state;
 mod module_specifier;
 mod modules;
 mod normalize_path;
+mod op_metrics;
 mod ops;
 pub mod plugin_api;
 mod resources;
@@ -29,15 +30,23 @@ pub use url;
 pub use crate::flags::v8_set_flags;
 pub use crate::module_specifier::ModuleResolutionError;
 pub use crate::module_specifier::ModuleSpecifier;
+pub use crate::modules::ModuleCode;
 pub use crate::modules::ModuleId;
 pub use crate::modules::ModuleLoadId;
 pub use crate::modules::ModuleLoader;
 pub use crate::modules::ModuleSource;
 pub use crate::modules::ModuleSourceFuture;
 pub use crate::modules::RecursiveModuleLoad;
+pub use crate::modules::SyntheticExport;
+pub use crate::modules::SyntheticExportFn;
 pub use crate::normalize_path::normalize_path;
+pub use crate::op_metrics::LatencyHistogram;
+pub use crate::op_metrics::OpMetrics;
+pub use crate::op_metrics::OpTracer;
+pub use crate::op_metrics::LATENCY_BUCKETS_US;
 pub use crate::ops::json_op_async;
 pub use crate::ops::json_op_sync;
+pub use crate::ops::op_call_site;
 pub use crate::ops::op_close;
 pub use crate::ops::op_resources;
 pub use crate::ops::Op;
@@ -46,6 +55,9 @@ pub use crate::ops::OpFn;
 pub use crate::ops::OpId;
 pub use crate::ops::OpState;
 pub use crate::ops::OpTable;
+pub use crate::ops::PendingOp;
+pub use crate::resources::Resource;
+pub use crate::resources::ResourceInfo;
 pub use crate::resources::ResourceTable;
 pub use crate::runtime::GetErrorClassFn;
 pub use crate::runtime::HeapLimits;
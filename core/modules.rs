@@ -13,6 +13,7 @@ use futures::stream::TryStreamExt;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
@@ -28,7 +29,7 @@ lazy_static! {
 pub type ModuleId = i32;
 pub type ModuleLoadId = i32;
 
-/// EsModule source code that will be loaded into V8.
+/// EsModule source code, or synthetic module, that will be loaded into V8.
 ///
 /// Users can implement `Into<ModuleInfo>` for different file types that
 /// can be transpiled to valid EsModule.
@@ -43,13 +44,55 @@ pub type ModuleLoadId = i32;
 // that happened; not only first and final target. It would simplify a lot
 // of things throughout the codebase otherwise we may end up requesting
 // intermediate redirects from file loader.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 pub struct ModuleSource {
-  pub code: String,
+  pub code: ModuleCode,
   pub module_url_specified: String,
   pub module_url_found: String,
 }
 
+/// The code of a module returned by `ModuleLoader::load()`.
+#[derive(Debug)]
+pub enum ModuleCode {
+  /// JavaScript source text of an ES module.
+  Js(String),
+  /// A synthetic module, which has no source text and no imports. Its
+  /// exports are provided by the embedder and set when it is evaluated.
+  Synthetic(Vec<(String, SyntheticExport)>),
+}
+
+impl From<String> for ModuleCode {
+  fn from(code: String) -> Self {
+    ModuleCode::Js(code)
+  }
+}
+
+/// Creates the value of an export of a synthetic module.
+pub type SyntheticExportFn =
+  dyn for<'s> Fn(&mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Value>;
+
+/// The value of an export of a synthetic module.
+#[derive(Clone)]
+pub enum SyntheticExport {
+  /// A value that is deserialized from JSON, eg. the default export of a JSON
+  /// module.
+  Json(serde_json::Value),
+  /// A value created by the embedder when the module is evaluated, eg. a
+  /// function that wraps an op.
+  Value(Rc<SyntheticExportFn>),
+}
+
+impl fmt::Debug for SyntheticExport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SyntheticExport::Json(value) => {
+        f.debug_tuple("Json").field(value).finish()
+      }
+      SyntheticExport::Value(_) => f.write_str("Value(..)"),
+    }
+  }
+}
+
 pub type PrepareLoadFuture =
   dyn Future<Output = (ModuleLoadId, Result<RecursiveModuleLoad, AnyError>)>;
 pub type ModuleSourceFuture =
@@ -133,6 +176,7 @@ impl ModuleLoader for NoopModuleLoader {
 #[derive(Debug, Eq, PartialEq)]
 enum Kind {
   Main,
+  Side,
   DynamicImport,
 }
 
@@ -172,6 +216,19 @@ impl RecursiveModuleLoad {
     Self::new(op_state, kind, state, loader)
   }
 
+  /// Starts a new parallel load of the given URL of a side module, which is
+  /// loaded like the main module but is not marked as main.
+  pub fn side(
+    op_state: Rc<RefCell<OpState>>,
+    specifier: &str,
+    code: Option<String>,
+    loader: Rc<dyn ModuleLoader>,
+  ) -> Self {
+    let kind = Kind::Side;
+    let state = LoadState::ResolveMain(specifier.to_owned(), code);
+    Self::new(op_state, kind, state, loader)
+  }
+
   pub fn dynamic_import(
     op_state: Rc<RefCell<OpState>>,
     specifier: &str,
@@ -184,8 +241,12 @@ impl RecursiveModuleLoad {
     Self::new(op_state, kind, state, loader)
   }
 
+  pub fn is_main(&self) -> bool {
+    self.kind == Kind::Main
+  }
+
   pub fn is_dynamic_import(&self) -> bool {
-    self.kind != Kind::Main
+    self.kind == Kind::DynamicImport
   }
 
   fn new(
@@ -268,11 +329,11 @@ impl RecursiveModuleLoad {
     let load_fut = match &self.state {
       LoadState::ResolveMain(_, Some(code)) => {
         futures::future::ok(ModuleSource {
-          code: code.to_owned(),
+          code: ModuleCode::Js(code.to_owned()),
           module_url_specified: module_specifier.to_string(),
           module_url_found: module_specifier.to_string(),
         })
-        .boxed()
+        .boxed_local()
       }
       _ => self
         .loader
@@ -528,6 +589,8 @@ mod tests {
       "/bad_import.js" => Some((BAD_IMPORT_SRC, "file:///bad_import.js")),
       // deliberately empty code.
       "/main_with_code.js" => Some(("", "file:///main_with_code.js")),
+      "/side.js" => Some((SIDE_SRC, "file:///side.js")),
+      "/data.json" => Some((DATA_JSON, "file:///data.json")),
       _ => None,
     }
   }
@@ -571,8 +634,19 @@ mod tests {
         return Poll::Pending;
       }
       match mock_source_code(&inner.url) {
+        Some(src) if inner.url.ends_with(".json") => {
+          let value = serde_json::from_str(src.0).unwrap();
+          Poll::Ready(Ok(ModuleSource {
+            code: ModuleCode::Synthetic(vec![(
+              "default".to_string(),
+              SyntheticExport::Json(value),
+            )]),
+            module_url_specified: inner.url.clone(),
+            module_url_found: src.1.to_owned(),
+          }))
+        }
         Some(src) => Poll::Ready(Ok(ModuleSource {
-          code: src.0.to_owned(),
+          code: ModuleCode::Js(src.0.to_owned()),
           module_url_specified: inner.url.clone(),
           module_url_found: src.1.to_owned(),
         })),
@@ -603,6 +677,11 @@ mod tests {
           Err(..) => return Err(MockError::ResolveErr.into()),
         };
 
+      // Modules registered with `JsRuntime::register_synthetic_module()`.
+      if output_specifier.as_url().scheme() == "internal" {
+        return Ok(output_specifier);
+      }
+
       if mock_source_code(&output_specifier.to_string()).is_some() {
         Ok(output_specifier)
       } else {
@@ -995,4 +1074,146 @@ mod tests {
     );
     assert_eq!(modules.get_children(d_id), Some(&vec![]));
   }
+
+  const SIDE_SRC: &str = r#"
+    import { b } from "/b.js";
+    if (b() != 'b') throw Error();
+    if (import.meta.main) throw Error();
+    if (import.meta.url != 'file:///side.js') throw Error();
+  "#;
+
+  #[test]
+  fn test_side_modules() {
+    let loader = MockLoader::new();
+    let loads = loader.loads.clone();
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+      module_loader: Some(loader),
+      ..Default::default()
+    });
+
+    let side_spec = ModuleSpecifier::resolve_url("file:///side.js").unwrap();
+    let side_id_fut = runtime.load_side_module(&side_spec, None);
+    let side_id =
+      futures::executor::block_on(side_id_fut).expect("Failed to load");
+    runtime.mod_evaluate(side_id).unwrap();
+
+    // A side module with code, which imports the first side module.
+    let side_with_code_spec =
+      ModuleSpecifier::resolve_url("file:///main_with_code.js").unwrap();
+    let side_with_code_id_fut = runtime.load_side_module(
+      &side_with_code_spec,
+      Some(
+        r#"
+        import "/side.js";
+        if (import.meta.main) throw Error();
+        "#
+        .to_owned(),
+      ),
+    );
+    let side_with_code_id = futures::executor::block_on(side_with_code_id_fut)
+      .expect("Failed to load");
+    runtime.mod_evaluate(side_with_code_id).unwrap();
+
+    let main_spec = ModuleSpecifier::resolve_url("file:///a.js").unwrap();
+    let main_id_fut = runtime.load_module(&main_spec, None);
+    let main_id =
+      futures::executor::block_on(main_id_fut).expect("Failed to load");
+    runtime.mod_evaluate(main_id).unwrap();
+
+    // Modules loaded by a side module are not loaded again.
+    let l = loads.lock().unwrap();
+    assert_eq!(
+      l.to_vec(),
+      vec![
+        "file:///side.js",
+        "file:///b.js",
+        "file:///c.js",
+        "file:///d.js",
+        "file:///a.js"
+      ]
+    );
+
+    let state_rc = JsRuntime::state(runtime.v8_isolate());
+    let state = state_rc.borrow();
+    let modules = &state.modules;
+    assert!(!modules.get_info(side_id).unwrap().main);
+    assert!(!modules.get_info(side_with_code_id).unwrap().main);
+    assert!(modules.get_info(main_id).unwrap().main);
+  }
+
+  const DATA_JSON: &str = r#"{ "answer": 42, "list": [1, "two"] }"#;
+
+  const SYNTHETIC_SRC: &str = r#"
+    import { add, version } from "internal:math";
+    import data from "/data.json";
+    if (add(1, 2) !== 3) throw Error();
+    if (version !== "1.0") throw Error();
+    if (data.answer !== 42) throw Error();
+    if (data.list[1] !== "two") throw Error();
+  "#;
+
+  fn add<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    args: v8::FunctionCallbackArguments<'s>,
+    mut rv: v8::ReturnValue,
+  ) {
+    let a = args.get(0).number_value(scope).unwrap();
+    let b = args.get(1).number_value(scope).unwrap();
+    rv.set(v8::Number::new(scope, a + b).into());
+  }
+
+  fn create_add<'s>(
+    scope: &mut v8::HandleScope<'s>,
+  ) -> v8::Local<'s, v8::Value> {
+    v8::Function::new(scope, add).unwrap().into()
+  }
+
+  #[test]
+  fn test_synthetic_modules() {
+    let loader = MockLoader::new();
+    let loads = loader.loads.clone();
+    let mut runtime = JsRuntime::new(RuntimeOptions {
+      module_loader: Some(loader),
+      ..Default::default()
+    });
+
+    let math_spec = ModuleSpecifier::resolve_url("internal:math").unwrap();
+    runtime
+      .register_synthetic_module(
+        &math_spec,
+        vec![
+          (
+            "add".to_string(),
+            SyntheticExport::Value(Rc::new(create_add)),
+          ),
+          (
+            "version".to_string(),
+            SyntheticExport::Json(serde_json::json!("1.0")),
+          ),
+        ],
+      )
+      .unwrap();
+    assert!(runtime
+      .register_synthetic_module(&math_spec, vec![])
+      .is_err());
+
+    let spec =
+      ModuleSpecifier::resolve_url("file:///main_with_code.js").unwrap();
+    let main_id_fut = runtime
+      .load_module(&spec, Some(SYNTHETIC_SRC.to_owned()))
+      .boxed_local();
+    let main_id =
+      futures::executor::block_on(main_id_fut).expect("Failed to load");
+    runtime.mod_evaluate(main_id).unwrap();
+
+    // Registered modules are not loaded by the loader.
+    let l = loads.lock().unwrap();
+    assert_eq!(l.to_vec(), vec!["file:///data.json"]);
+
+    let state_rc = JsRuntime::state(runtime.v8_isolate());
+    let state = state_rc.borrow();
+    let data_id = state.modules.get_id("file:///data.json").unwrap();
+    assert_eq!(state.modules.get_children(data_id), Some(&vec![]));
+    assert!(state.synthetic_module_exports.is_empty());
+  }
 }
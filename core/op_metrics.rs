@@ -0,0 +1,208 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+use crate::error::generic_error;
+use crate::error::AnyError;
+use serde_json::json;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Upper bounds, in microseconds, of the buckets of a `LatencyHistogram`.
+/// Calls slower than the last bound are counted in an extra bucket.
+pub const LATENCY_BUCKETS_US: [u64; 7] =
+  [10, 100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// The distribution of the time between the dispatch and the completion of
+/// the calls of an op.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyHistogram {
+  /// The number of calls in each bucket of `LATENCY_BUCKETS_US`.
+  pub counts: [u64; LATENCY_BUCKETS_US.len() + 1],
+  pub total: Duration,
+  pub max: Duration,
+}
+
+impl LatencyHistogram {
+  pub fn record(&mut self, latency: Duration) {
+    let micros = latency.as_micros();
+    let bucket = LATENCY_BUCKETS_US
+      .iter()
+      .position(|bound| micros <= *bound as u128)
+      .unwrap_or(LATENCY_BUCKETS_US.len());
+    self.counts[bucket] += 1;
+    self.total += latency;
+    self.max = self.max.max(latency);
+  }
+
+  /// The number of calls recorded.
+  pub fn count(&self) -> u64 {
+    self.counts.iter().sum()
+  }
+}
+
+/// Metrics of the calls of one op, kept in `OpState` and returned by
+/// `OpState::op_metrics()`. Unref async ops are counted as async ops.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpMetrics {
+  pub ops_dispatched_sync: u64,
+  pub ops_dispatched_async: u64,
+  pub ops_completed_async: u64,
+  /// Latencies of the completed calls, sync and async.
+  pub latency: LatencyHistogram,
+}
+
+impl OpMetrics {
+  pub fn ops_dispatched(&self) -> u64 {
+    self.ops_dispatched_sync + self.ops_dispatched_async
+  }
+
+  pub fn ops_completed(&self) -> u64 {
+    self.ops_dispatched_sync + self.ops_completed_async
+  }
+
+  /// The number of async calls that have not completed yet.
+  pub fn ops_in_flight(&self) -> u64 {
+    self.ops_dispatched_async - self.ops_completed_async
+  }
+}
+
+struct TraceFile {
+  file: File,
+  next_async_id: u64,
+  next_tid: u64,
+}
+
+/// Writes a Chrome trace event (see `chrome://tracing`) for every op call to
+/// a JSON file, when set as `OpState::op_tracer`. A sync call is a complete
+/// event, an async call a pair of async events that are written when it is
+/// dispatched and when it completes, so that calls that never complete show
+/// up in the trace.
+pub struct OpTracer {
+  trace_file: Arc<Mutex<TraceFile>>,
+  start: Instant,
+  tid: u64,
+}
+
+impl OpTracer {
+  pub fn create(path: &Path) -> Result<Self, AnyError> {
+    let mut file = File::create(path).map_err(|err| {
+      generic_error(format!(
+        "Unable to create op trace file \"{}\": {}",
+        path.display(),
+        err
+      ))
+    })?;
+    // The closing bracket of the array is optional in the trace event format,
+    // which lets the events be written as they happen.
+    file.write_all(b"[\n")?;
+    Ok(Self {
+      trace_file: Arc::new(Mutex::new(TraceFile {
+        file,
+        next_async_id: 0,
+        next_tid: 1,
+      })),
+      start: Instant::now(),
+      tid: 0,
+    })
+  }
+
+  /// Returns a tracer that writes to the same file, for another runtime. Its
+  /// events are shown on a track of their own.
+  pub fn fork(&self) -> Self {
+    let mut trace_file = self.trace_file.lock().unwrap();
+    let tid = trace_file.next_tid;
+    trace_file.next_tid += 1;
+    Self {
+      trace_file: self.trace_file.clone(),
+      start: self.start,
+      tid,
+    }
+  }
+
+  pub(crate) fn sync_op(&self, name: &str, start: Instant, end: Instant) {
+    self.write_event(json!({
+      "name": name,
+      "cat": "op",
+      "ph": "X",
+      "ts": self.timestamp(start),
+      "dur": micros(end - start),
+      "pid": std::process::id(),
+      "tid": self.tid,
+    }));
+  }
+
+  /// Returns the id that identifies the call in `async_op_end()`.
+  pub(crate) fn async_op_begin(&self, name: &str, start: Instant) -> u64 {
+    let id = {
+      let mut trace_file = self.trace_file.lock().unwrap();
+      trace_file.next_async_id += 1;
+      trace_file.next_async_id
+    };
+    self.write_event(self.async_event(name, "b", id, start));
+    id
+  }
+
+  pub(crate) fn async_op_end(&self, name: &str, id: u64, end: Instant) {
+    self.write_event(self.async_event(name, "e", id, end));
+  }
+
+  fn async_event(
+    &self,
+    name: &str,
+    phase: &str,
+    id: u64,
+    at: Instant,
+  ) -> Value {
+    json!({
+      "name": name,
+      "cat": "op",
+      "ph": phase,
+      "id": id,
+      "ts": self.timestamp(at),
+      "pid": std::process::id(),
+      "tid": self.tid,
+    })
+  }
+
+  fn timestamp(&self, at: Instant) -> f64 {
+    micros(at.saturating_duration_since(self.start))
+  }
+
+  fn write_event(&self, event: Value) {
+    let mut trace_file = self.trace_file.lock().unwrap();
+    if let Err(err) = writeln!(trace_file.file, "{},", event) {
+      error!("Unable to write to the op trace file: {}", err);
+    }
+  }
+}
+
+fn micros(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn latency_histogram() {
+    let mut histogram = LatencyHistogram::default();
+    histogram.record(Duration::from_micros(5));
+    histogram.record(Duration::from_micros(10));
+    histogram.record(Duration::from_micros(11));
+    histogram.record(Duration::from_millis(50));
+    histogram.record(Duration::from_secs(60));
+    assert_eq!(histogram.counts, [2, 1, 0, 0, 1, 0, 0, 1]);
+    assert_eq!(histogram.count(), 5);
+    assert_eq!(histogram.max, Duration::from_secs(60));
+    assert_eq!(
+      histogram.total,
+      Duration::from_micros(26) + Duration::from_millis(60_050)
+    );
+  }
+}
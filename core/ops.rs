@@ -4,19 +4,26 @@ use crate::error::bad_resource_id;
 use crate::error::type_error;
 use crate::error::AnyError;
 use crate::gotham_state::GothamState;
+use crate::op_metrics::OpMetrics;
+use crate::op_metrics::OpTracer;
 use crate::BufVec;
 use crate::ZeroCopyBuf;
+use futures::future::poll_fn;
+use futures::future::FutureExt;
 use futures::Future;
 use indexmap::IndexMap;
 use serde_json::json;
 use serde_json::Value;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::iter::once;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::pin::Pin;
 use std::rc::Rc;
+use std::time::Instant;
+use std::time::UNIX_EPOCH;
 
 pub type OpAsyncFuture = Pin<Box<dyn Future<Output = Box<[u8]>>>>;
 pub type OpFn = dyn Fn(Rc<RefCell<OpState>>, BufVec) -> Op + 'static;
@@ -31,10 +38,41 @@ pub enum Op {
   NotFound,
 }
 
+/// An async op that has been dispatched and hasn't completed yet.
+#[derive(Debug, PartialEq)]
+pub struct PendingOp<'a> {
+  /// Identifies the call among the pending ops of the `OpState`.
+  pub id: u64,
+  pub name: &'a str,
+  /// Whether the op is an `Op::AsyncUnref`, which doesn't keep the event loop
+  /// alive.
+  pub is_unref: bool,
+  /// The location of the JavaScript code that dispatched the op, when
+  /// `OpState::track_op_call_sites` is set.
+  pub call_site: Option<&'a str>,
+}
+
+struct PendingOpEntry {
+  op_id: OpId,
+  is_unref: bool,
+  call_site: Option<String>,
+  maybe_trace_id: Option<u64>,
+}
+
 pub struct OpState {
   pub resource_table: crate::ResourceTable,
   pub op_table: OpTable,
   pub get_error_class_fn: crate::runtime::GetErrorClassFn,
+  /// Makes `op_call_site()` return the location of the JavaScript code that
+  /// dispatched an op. Off by default, as it captures a stack trace for every
+  /// op call.
+  pub track_op_call_sites: bool,
+  /// Writes a trace event for every op call, if set.
+  pub op_tracer: Option<OpTracer>,
+  /// The metrics of each op, by `OpId`.
+  op_metrics: Vec<OpMetrics>,
+  pending_ops: BTreeMap<u64, PendingOpEntry>,
+  next_pending_op_id: u64,
   gotham_state: GothamState,
 }
 
@@ -47,11 +85,174 @@ impl Default for OpState {
       resource_table: crate::ResourceTable::default(),
       op_table: OpTable::default(),
       get_error_class_fn: &|_| "Error",
+      track_op_call_sites: false,
+      op_tracer: None,
+      op_metrics: Vec::new(),
+      pending_ops: BTreeMap::new(),
+      next_pending_op_id: 0,
       gotham_state: GothamState::default(),
     }
   }
 }
 
+impl OpState {
+  /// Returns the metrics of the ops that have been called, by op name.
+  pub fn op_metrics(&self) -> Vec<(&str, &OpMetrics)> {
+    self
+      .op_metrics
+      .iter()
+      .enumerate()
+      .filter(|(_, metrics)| metrics.ops_dispatched() > 0)
+      .map(|(op_id, metrics)| (self.op_table.op_name(op_id), metrics))
+      .collect()
+  }
+
+  /// Returns the async ops that have been dispatched and haven't completed
+  /// yet, in the order they were dispatched.
+  pub fn pending_ops(&self) -> Vec<PendingOp> {
+    self
+      .pending_ops
+      .iter()
+      .map(|(id, entry)| PendingOp {
+        id: *id,
+        name: self.op_table.op_name(entry.op_id),
+        is_unref: entry.is_unref,
+        call_site: entry.call_site.as_deref(),
+      })
+      .collect()
+  }
+
+  fn op_metrics_mut(&mut self, op_id: OpId) -> &mut OpMetrics {
+    if self.op_metrics.len() <= op_id {
+      self.op_metrics.resize_with(op_id + 1, Default::default);
+    }
+    &mut self.op_metrics[op_id]
+  }
+
+  fn sync_op_completed(&mut self, op_id: OpId, start: Instant) {
+    let end = Instant::now();
+    let metrics = self.op_metrics_mut(op_id);
+    metrics.ops_dispatched_sync += 1;
+    metrics.latency.record(end - start);
+    if let Some(tracer) = &self.op_tracer {
+      tracer.sync_op(self.op_table.op_name(op_id), start, end);
+    }
+  }
+
+  /// Returns the id of the pending op.
+  fn async_op_dispatched(
+    &mut self,
+    op_id: OpId,
+    is_unref: bool,
+    start: Instant,
+  ) -> u64 {
+    self.op_metrics_mut(op_id).ops_dispatched_async += 1;
+    let name = self.op_table.op_name(op_id);
+    let maybe_trace_id = self
+      .op_tracer
+      .as_ref()
+      .map(|tracer| tracer.async_op_begin(name, start));
+    let id = self.next_pending_op_id;
+    self.next_pending_op_id += 1;
+    self.pending_ops.insert(
+      id,
+      PendingOpEntry {
+        op_id,
+        is_unref,
+        call_site: op_call_site(),
+        maybe_trace_id,
+      },
+    );
+    id
+  }
+
+  /// Takes an async op whose future was dropped before it completed off the
+  /// pending ops, without recording a latency for it.
+  fn async_op_dropped(&mut self, id: u64) {
+    let entry = match self.pending_ops.remove(&id) {
+      Some(entry) => entry,
+      None => return,
+    };
+    self.op_metrics_mut(entry.op_id).ops_completed_async += 1;
+    if let (Some(tracer), Some(trace_id)) =
+      (&self.op_tracer, entry.maybe_trace_id)
+    {
+      let name = self.op_table.op_name(entry.op_id);
+      tracer.async_op_end(name, trace_id, Instant::now());
+    }
+  }
+
+  fn async_op_completed(&mut self, id: u64, start: Instant) {
+    let end = Instant::now();
+    let entry = self.pending_ops.remove(&id).unwrap();
+    let metrics = self.op_metrics_mut(entry.op_id);
+    metrics.ops_completed_async += 1;
+    metrics.latency.record(end - start);
+    if let (Some(tracer), Some(trace_id)) =
+      (&self.op_tracer, entry.maybe_trace_id)
+    {
+      tracer.async_op_end(self.op_table.op_name(entry.op_id), trace_id, end);
+    }
+  }
+}
+
+/// Takes an async op off the pending ops of `state` if its future is dropped
+/// before it completes, like when the runtime is dropped.
+struct PendingOpGuard {
+  state: Rc<RefCell<OpState>>,
+  id: u64,
+  completed: bool,
+}
+
+impl Drop for PendingOpGuard {
+  fn drop(&mut self) {
+    if self.completed {
+      return;
+    }
+    if let Ok(mut state) = self.state.try_borrow_mut() {
+      state.async_op_dropped(self.id);
+    }
+  }
+}
+
+/// Records the call of an op in the metrics of `state`. Async ops are
+/// recorded again when they complete.
+fn track_op(
+  state: Rc<RefCell<OpState>>,
+  op_id: OpId,
+  start: Instant,
+  op: Op,
+) -> Op {
+  let (fut, is_unref) = match op {
+    Op::Sync(buf) => {
+      state.borrow_mut().sync_op_completed(op_id, start);
+      return Op::Sync(buf);
+    }
+    Op::Async(fut) => (fut, false),
+    Op::AsyncUnref(fut) => (fut, true),
+    Op::NotFound => return Op::NotFound,
+  };
+  let id = state
+    .borrow_mut()
+    .async_op_dispatched(op_id, is_unref, start);
+  let mut guard = PendingOpGuard {
+    state,
+    id,
+    completed: false,
+  };
+  let fut = fut
+    .inspect(move |_| {
+      guard.state.borrow_mut().async_op_completed(guard.id, start);
+      guard.completed = true;
+    })
+    .boxed_local();
+  if is_unref {
+    Op::AsyncUnref(fut)
+  } else {
+    Op::Async(fut)
+  }
+}
+
 impl Deref for OpState {
   type Target = GothamState;
 
@@ -66,6 +267,42 @@ impl DerefMut for OpState {
   }
 }
 
+thread_local! {
+  static OP_CALL_SITE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Returns the location, as `script:line:column`, of the JavaScript code that
+/// dispatched the op being run, when `OpState::track_op_call_sites` is set.
+/// Frames of the runtime's own scripts, named `deno:...`, are skipped.
+pub fn op_call_site() -> Option<String> {
+  OP_CALL_SITE.with(|op_call_site| op_call_site.borrow().clone())
+}
+
+pub(crate) fn with_op_call_site<R>(
+  call_site: &Option<String>,
+  f: impl FnOnce() -> R,
+) -> R {
+  OP_CALL_SITE
+    .with(|op_call_site| *op_call_site.borrow_mut() = call_site.clone());
+  let result = f();
+  OP_CALL_SITE.with(|op_call_site| *op_call_site.borrow_mut() = None);
+  result
+}
+
+/// Keeps the call site of an async op available whenever its future is
+/// polled.
+pub(crate) fn track_op_call_site(
+  fut: OpAsyncFuture,
+  call_site: Option<String>,
+) -> OpAsyncFuture {
+  if call_site.is_none() {
+    return fut;
+  }
+  let mut fut = fut;
+  poll_fn(move |cx| with_op_call_site(&call_site, || fut.poll_unpin(cx)))
+    .boxed_local()
+}
+
 /// Collection for storing registered ops. The special 'get_op_catalog'
 /// op with OpId `0` is automatically added when the OpTable is created.
 pub struct OpTable(IndexMap<String, Rc<OpFn>>);
@@ -80,6 +317,10 @@ impl OpTable {
     op_id
   }
 
+  fn op_name(&self, op_id: OpId) -> &str {
+    self.0.get_index(op_id).unwrap().0
+  }
+
   pub fn route_op(
     op_id: OpId,
     state: Rc<RefCell<OpState>>,
@@ -98,7 +339,11 @@ impl OpTable {
         .get_index(op_id)
         .map(|(_, op_fn)| op_fn.clone());
       match op_fn {
-        Some(f) => (f)(state, bufs),
+        Some(f) => {
+          let start = Instant::now();
+          let op = (f)(state.clone(), bufs);
+          track_op(state, op_id, start, op)
+        }
         None => Op::NotFound,
       }
     }
@@ -114,6 +359,14 @@ impl Default for OpTable {
   }
 }
 
+#[test]
+fn op_call_site_is_scoped() {
+  let call_site = Some("file:///main.js:1:1".to_string());
+  let inner = with_op_call_site(&call_site, op_call_site);
+  assert_eq!(inner, call_site);
+  assert_eq!(op_call_site(), None);
+}
+
 #[test]
 fn op_table() {
   let state = Rc::new(RefCell::new(OpState::default()));
@@ -151,6 +404,98 @@ fn op_table() {
   )
 }
 
+#[test]
+fn op_metrics() {
+  let state = Rc::new(RefCell::new(OpState::default()));
+
+  let sync_id;
+  let async_id;
+  {
+    let op_table = &mut state.borrow_mut().op_table;
+    sync_id = op_table.register_op("sync", |_, _| Op::Sync(b"ok"[..].into()));
+    async_id = op_table.register_op("async", |_, _| {
+      Op::Async(futures::future::ready(b"ok"[..].into()).boxed_local())
+    });
+    op_table.register_op("unused", |_, _| Op::Sync(b"ok"[..].into()));
+  }
+
+  OpTable::route_op(sync_id, state.clone(), Default::default());
+  OpTable::route_op(sync_id, state.clone(), Default::default());
+  let fut = match OpTable::route_op(async_id, state.clone(), Default::default())
+  {
+    Op::Async(fut) => fut,
+    _ => panic!("unexpected `Op` variant"),
+  };
+  {
+    let state = state.borrow();
+    let metrics = state.op_metrics();
+    assert_eq!(metrics.len(), 2);
+    assert_eq!(metrics[0].0, "sync");
+    assert_eq!(metrics[0].1.ops_dispatched(), 2);
+    assert_eq!(metrics[0].1.ops_completed(), 2);
+    assert_eq!(metrics[0].1.latency.count(), 2);
+    assert_eq!(metrics[1].0, "async");
+    assert_eq!(metrics[1].1.ops_in_flight(), 1);
+    assert_eq!(metrics[1].1.latency.count(), 0);
+  }
+
+  futures::executor::block_on(fut);
+  let state = state.borrow();
+  let (_, metrics) = state.op_metrics()[1];
+  assert_eq!(metrics.ops_in_flight(), 0);
+  assert_eq!(metrics.ops_completed(), 1);
+  assert_eq!(metrics.latency.count(), 1);
+}
+
+#[test]
+fn pending_ops() {
+  let state = Rc::new(RefCell::new(OpState::default()));
+  let async_id = state.borrow_mut().op_table.register_op("async", |_, _| {
+    Op::AsyncUnref(futures::future::ready(b"ok"[..].into()).boxed_local())
+  });
+
+  let call_site = Some("file:///main.js:1:1".to_string());
+  let fut = with_op_call_site(&call_site, || {
+    match OpTable::route_op(async_id, state.clone(), Default::default()) {
+      Op::AsyncUnref(fut) => fut,
+      _ => panic!("unexpected `Op` variant"),
+    }
+  });
+  assert_eq!(
+    state.borrow().pending_ops(),
+    vec![PendingOp {
+      id: 0,
+      name: "async",
+      is_unref: true,
+      call_site: Some("file:///main.js:1:1"),
+    }]
+  );
+
+  futures::executor::block_on(fut);
+  assert!(state.borrow().pending_ops().is_empty());
+}
+
+#[test]
+fn dropped_async_op_is_not_pending() {
+  let state = Rc::new(RefCell::new(OpState::default()));
+  let async_id = state.borrow_mut().op_table.register_op("async", |_, _| {
+    Op::Async(futures::future::pending().boxed_local())
+  });
+  let fut = match OpTable::route_op(async_id, state.clone(), Default::default())
+  {
+    Op::Async(fut) => fut,
+    _ => panic!("unexpected `Op` variant"),
+  };
+  assert_eq!(state.borrow().pending_ops().len(), 1);
+
+  drop(fut);
+  let state = state.borrow();
+  assert!(state.pending_ops().is_empty());
+  let (_, metrics) = state.op_metrics()[0];
+  assert_eq!(metrics.ops_in_flight(), 0);
+  assert_eq!(metrics.latency.count(), 0);
+}
+
 pub fn json_op_sync<F>(op_fn: F) -> Box<OpFn>
 where
   F: Fn(&mut OpState, Value, &mut [ZeroCopyBuf]) -> Result<Value, AnyError>
@@ -221,16 +566,43 @@ fn json_serialize_op_result(
 }
 
 /// Return map of resources with id as key
-/// and string representaion as value.
+/// and string representaion as value, or the metadata of the resource if the
+/// `details` argument is set.
 ///
 /// This op must be wrapped in `json_op_sync`.
 pub fn op_resources(
   state: &mut OpState,
-  _args: Value,
+  args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<Value, AnyError> {
-  let serialized_resources = state.resource_table.entries();
-  Ok(json!(serialized_resources))
+  let details = args
+    .get("details")
+    .and_then(Value::as_bool)
+    .unwrap_or(false);
+  let resource_table = &state.resource_table;
+  if !details {
+    return Ok(json!(resource_table.entries()));
+  }
+  let resources: serde_json::Map<String, Value> = resource_table
+    .infos()
+    .into_iter()
+    .map(|(rid, info)| {
+      let created_at = info
+        .created_at
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+      let details = json!({
+        "name": info.name,
+        "createdAt": created_at,
+        "bytesRead": info.bytes_read,
+        "bytesWritten": info.bytes_written,
+        "callSite": info.call_site,
+      });
+      (rid.to_string(), details)
+    })
+    .collect();
+  Ok(Value::Object(resources))
 }
 
 /// Remove a resource from the resource table.
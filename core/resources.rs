@@ -6,81 +6,275 @@
 // Resources may or may not correspond to a real operating system file
 // descriptor (hence the different name).
 
+use crate::ops::op_call_site;
 use std::any::Any;
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::time::SystemTime;
 
 /// ResourceId is Deno's version of a file descriptor. ResourceId is also referred
 /// to as rid in the code base.
 pub type ResourceId = u32;
 
+/// A resource that is added to the table with `ResourceTable::add_resource()`.
+/// Other resources are added with a name and have no close hook.
+pub trait Resource: Any {
+  /// The kind of the resource, like "fsFile" or "tcpStream".
+  fn name(&self) -> Cow<str>;
+
+  /// Called when the resource is closed with `ResourceTable::close()`, right
+  /// before it is dropped. It isn't called when the resource is taken out of
+  /// the table with `ResourceTable::remove()`.
+  fn close(&mut self) {}
+}
+
+/// Metadata of an open resource.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceInfo {
+  pub name: String,
+  pub created_at: SystemTime,
+  /// The bytes read from and written to the resource, as recorded by the ops
+  /// with `ResourceTable::record_read()` and `record_write()`.
+  pub bytes_read: u64,
+  pub bytes_written: u64,
+  /// The location of the JavaScript code that created the resource, if it was
+  /// created by an op while `OpState::track_op_call_sites` was set.
+  pub call_site: Option<String>,
+}
+
+struct ResourceEntry {
+  info: ResourceInfo,
+  resource: Box<dyn Any>,
+  close_fn: Option<fn(&mut dyn Any)>,
+}
+
 /// These store Deno's file descriptors. These are not necessarily the operating
 /// system ones.
-type ResourceMap = HashMap<ResourceId, (String, Box<dyn Any>)>;
+type ResourceMap = HashMap<ResourceId, ResourceEntry>;
+
+/// The low bits of a generation-tagged resource id are the index of a slot,
+/// the high bits its generation, which is bumped every time the resource of
+/// the slot is closed. A slot is retired once its generation is exhausted, so
+/// an id is never handed out twice.
+const SLOT_BITS: u32 = 20;
+const SLOT_MASK: u32 = (1 << SLOT_BITS) - 1;
+const MAX_GENERATION: u32 = u32::MAX >> SLOT_BITS;
+
+/// Hands out generation-tagged resource ids. The ids are XORed with a random
+/// key, and each slot starts at a random generation, so they can't be guessed
+/// either.
+struct TaggedIds {
+  /// The generation of the current or next resource of each slot.
+  generations: Vec<u32>,
+  free_slots: Vec<u32>,
+  random_state: RandomState,
+  key: u32,
+}
+
+impl TaggedIds {
+  fn new() -> Self {
+    let random_state = RandomState::new();
+    let key = random_state.build_hasher().finish() as u32;
+    Self {
+      generations: Vec::new(),
+      free_slots: Vec::new(),
+      random_state,
+      key,
+    }
+  }
+
+  fn next(&mut self) -> ResourceId {
+    let slot = match self.free_slots.pop() {
+      Some(slot) => slot,
+      None => {
+        let slot = self.generations.len() as u32;
+        assert!(slot <= SLOT_MASK, "Too many open resources");
+        // Start in the lower half, so every slot is reused many times.
+        let mut hasher = self.random_state.build_hasher();
+        hasher.write_u32(slot);
+        self
+          .generations
+          .push(hasher.finish() as u32 % (MAX_GENERATION / 2));
+        slot
+      }
+    };
+    (self.generations[slot as usize] << SLOT_BITS | slot) ^ self.key
+  }
+
+  /// Frees the slot of `rid`, which must have been removed from the table.
+  fn release(&mut self, rid: ResourceId) {
+    let tagged = rid ^ self.key;
+    let slot = tagged & SLOT_MASK;
+    let generation = tagged >> SLOT_BITS;
+    match self.generations.get_mut(slot as usize) {
+      Some(current) if *current == generation => {
+        if generation < MAX_GENERATION {
+          *current += 1;
+          self.free_slots.push(slot);
+        }
+      }
+      _ => {}
+    }
+  }
+}
 
-#[derive(Default)]
 pub struct ResourceTable {
   map: ResourceMap,
   next_id: u32,
+  maybe_tagged_ids: Option<TaggedIds>,
+}
+
+impl Default for ResourceTable {
+  fn default() -> Self {
+    Self {
+      map: ResourceMap::new(),
+      next_id: 0,
+      maybe_tagged_ids: None,
+    }
+  }
 }
 
 impl ResourceTable {
+  /// Returns a table that hands out unpredictable, generation-tagged resource
+  /// ids, so that code can't guess the ids of resources it wasn't given, and
+  /// the id of a closed resource never refers to another one. Sequential ids
+  /// of the default table are only reused after 2^32 resources were added.
+  pub fn with_random_ids() -> Self {
+    Self {
+      maybe_tagged_ids: Some(TaggedIds::new()),
+      ..Default::default()
+    }
+  }
+
   pub fn has(&self, rid: ResourceId) -> bool {
     self.map.contains_key(&rid)
   }
 
   pub fn get<T: Any>(&self, rid: ResourceId) -> Option<&T> {
-    let (_, resource) = self.map.get(&rid)?;
-    resource.downcast_ref::<T>()
+    self.map.get(&rid)?.resource.downcast_ref::<T>()
   }
 
   pub fn get_mut<T: Any>(&mut self, rid: ResourceId) -> Option<&mut T> {
-    let (_, resource) = self.map.get_mut(&rid)?;
-    resource.downcast_mut::<T>()
+    self.map.get_mut(&rid)?.resource.downcast_mut::<T>()
   }
 
-  // TODO: resource id allocation should probably be randomized for security.
   fn next_rid(&mut self) -> ResourceId {
-    let next_rid = self.next_id;
-    self.next_id += 1;
-    next_rid as ResourceId
+    if let Some(tagged_ids) = &mut self.maybe_tagged_ids {
+      return tagged_ids.next();
+    }
+    loop {
+      let rid = self.next_id;
+      self.next_id = self.next_id.wrapping_add(1);
+      if !self.map.contains_key(&rid) {
+        return rid;
+      }
+    }
   }
 
-  pub fn add(&mut self, name: &str, resource: Box<dyn Any>) -> ResourceId {
+  fn remove_entry(&mut self, rid: ResourceId) -> Option<ResourceEntry> {
+    let entry = self.map.remove(&rid)?;
+    if let Some(tagged_ids) = &mut self.maybe_tagged_ids {
+      tagged_ids.release(rid);
+    }
+    Some(entry)
+  }
+
+  fn insert(
+    &mut self,
+    name: String,
+    resource: Box<dyn Any>,
+    close_fn: Option<fn(&mut dyn Any)>,
+  ) -> ResourceId {
     let rid = self.next_rid();
-    let r = self.map.insert(rid, (name.to_string(), resource));
-    assert!(r.is_none());
+    let info = ResourceInfo {
+      name,
+      created_at: SystemTime::now(),
+      bytes_read: 0,
+      bytes_written: 0,
+      call_site: op_call_site(),
+    };
+    self.map.insert(
+      rid,
+      ResourceEntry {
+        info,
+        resource,
+        close_fn,
+      },
+    );
     rid
   }
 
+  pub fn add(&mut self, name: &str, resource: Box<dyn Any>) -> ResourceId {
+    self.insert(name.to_string(), resource, None)
+  }
+
+  pub fn add_resource<R: Resource>(&mut self, resource: R) -> ResourceId {
+    let name = resource.name().into_owned();
+    let close_fn = |resource: &mut dyn Any| {
+      resource.downcast_mut::<R>().unwrap().close();
+    };
+    self.insert(name, Box::new(resource), Some(close_fn))
+  }
+
+  /// Returns the metadata of the resource.
+  pub fn info(&self, rid: ResourceId) -> Option<&ResourceInfo> {
+    self.map.get(&rid).map(|entry| &entry.info)
+  }
+
+  /// Returns the metadata of every open resource.
+  pub fn infos(&self) -> HashMap<ResourceId, &ResourceInfo> {
+    self
+      .map
+      .iter()
+      .map(|(rid, entry)| (*rid, &entry.info))
+      .collect()
+  }
+
+  pub fn record_read(&mut self, rid: ResourceId, nread: usize) {
+    if let Some(entry) = self.map.get_mut(&rid) {
+      entry.info.bytes_read += nread as u64;
+    }
+  }
+
+  pub fn record_write(&mut self, rid: ResourceId, nwritten: usize) {
+    if let Some(entry) = self.map.get_mut(&rid) {
+      entry.info.bytes_written += nwritten as u64;
+    }
+  }
+
   pub fn entries(&self) -> HashMap<ResourceId, String> {
     self
       .map
       .iter()
-      .map(|(key, (name, _resource))| (*key, name.clone()))
+      .map(|(key, entry)| (*key, entry.info.name.clone()))
       .collect()
   }
 
   // close(2) is done by dropping the value. Therefore we just need to remove
   // the resource from the resource table.
   pub fn close(&mut self, rid: ResourceId) -> Option<()> {
-    self.map.remove(&rid).map(|(_name, _resource)| ())
+    let mut entry = self.remove_entry(rid)?;
+    if let Some(close_fn) = entry.close_fn {
+      close_fn(entry.resource.as_mut());
+    }
+    Some(())
   }
 
   pub fn remove<T: Any>(&mut self, rid: ResourceId) -> Option<Box<T>> {
-    if let Some((_name, resource)) = self.map.remove(&rid) {
-      let res = match resource.downcast::<T>() {
-        Ok(res) => Some(res),
-        Err(_e) => None,
-      };
-      return res;
-    }
-    None
+    let entry = self.remove_entry(rid)?;
+    entry.resource.downcast::<T>().ok()
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use std::cell::Cell;
+  use std::collections::HashSet;
+  use std::rc::Rc;
 
   struct FakeResource {
     not_empty: u128,
@@ -122,6 +316,107 @@ mod tests {
     assert_eq!(resource.unwrap().not_empty, 7);
   }
 
+  #[test]
+  fn test_resource_call_site() {
+    let mut table = ResourceTable::default();
+    let rid1 = table.add("fake1", Box::new(FakeResource::new(1)));
+    let call_site = Some("file:///main.js:1:1".to_string());
+    let rid2 = crate::ops::with_op_call_site(&call_site, || {
+      table.add("fake2", Box::new(FakeResource::new(2)))
+    });
+    assert_eq!(table.info(rid1).unwrap().call_site, None);
+    assert_eq!(table.info(rid2).unwrap().call_site, call_site);
+  }
+
+  #[test]
+  fn test_resource_byte_counters() {
+    let mut table = ResourceTable::default();
+    let rid = table.add("fake", Box::new(FakeResource::new(1)));
+    table.record_read(rid, 10);
+    table.record_read(rid, 5);
+    table.record_write(rid, 3);
+    let info = table.info(rid).unwrap();
+    assert_eq!(info.name, "fake");
+    assert_eq!(info.bytes_read, 15);
+    assert_eq!(info.bytes_written, 3);
+    table.close(rid);
+    assert!(table.info(rid).is_none());
+  }
+
+  struct ClosableResource(Rc<Cell<bool>>);
+
+  impl Resource for ClosableResource {
+    fn name(&self) -> Cow<str> {
+      "closable".into()
+    }
+
+    fn close(&mut self) {
+      self.0.set(true);
+    }
+  }
+
+  #[test]
+  fn test_resource_close_hook() {
+    let mut table = ResourceTable::default();
+    let closed = Rc::new(Cell::new(false));
+    let rid1 = table.add_resource(ClosableResource(closed.clone()));
+    assert_eq!(table.info(rid1).unwrap().name, "closable");
+    assert!(table.get::<ClosableResource>(rid1).is_some());
+    table.close(rid1);
+    assert!(closed.get());
+
+    let closed = Rc::new(Cell::new(false));
+    let rid2 = table.add_resource(ClosableResource(closed.clone()));
+    assert!(table.remove::<ClosableResource>(rid2).is_some());
+    assert!(!closed.get());
+  }
+
+  #[test]
+  fn test_random_resource_ids() {
+    let mut table = ResourceTable::with_random_ids();
+    let rids: HashSet<ResourceId> = (0..100)
+      .map(|i| table.add("fake", Box::new(FakeResource::new(i))))
+      .collect();
+    assert_eq!(rids.len(), 100);
+    assert!(rids.iter().any(|rid| *rid >= 100));
+    for rid in rids {
+      assert!(table.get::<FakeResource>(rid).is_some());
+    }
+  }
+
+  #[test]
+  fn test_random_resource_ids_are_not_reused() {
+    let mut table = ResourceTable::with_random_ids();
+    let mut rids = HashSet::new();
+    for i in 0..1000 {
+      let rid = table.add("fake", Box::new(FakeResource::new(i)));
+      assert!(rids.insert(rid));
+      if i % 2 == 0 {
+        table.close(rid);
+      } else {
+        table.remove::<FakeResource>(rid);
+      }
+      assert!(table.get::<FakeResource>(rid).is_none());
+    }
+    // The slot of the closed resources was reused every time.
+    let tagged_ids = table.maybe_tagged_ids.as_ref().unwrap();
+    assert_eq!(tagged_ids.generations.len(), 1);
+  }
+
+  #[test]
+  fn test_tagged_ids_retire_exhausted_slots() {
+    let mut tagged_ids = TaggedIds::new();
+    let rid = tagged_ids.next();
+    tagged_ids.release(rid);
+    tagged_ids.generations[0] = MAX_GENERATION;
+    let last_rid = tagged_ids.next();
+    assert_ne!(rid, last_rid);
+    tagged_ids.release(last_rid);
+    assert!(tagged_ids.free_slots.is_empty());
+    // A new slot is used instead.
+    assert_eq!((tagged_ids.next() ^ tagged_ids.key) & SLOT_MASK, 1);
+  }
+
   #[test]
   fn test_remove_from_resource_table() {
     let mut table = ResourceTable::default();
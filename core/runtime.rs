@@ -4,12 +4,14 @@ use rusty_v8 as v8;
 
 use crate::bindings;
 use crate::error::attach_handle_to_error;
+use crate::error::generic_error;
 use crate::error::AnyError;
 use crate::error::ErrWithV8Handle;
 use crate::error::JsError;
 use crate::futures::FutureExt;
 use crate::module_specifier::ModuleSpecifier;
 use crate::modules::LoadState;
+use crate::modules::ModuleCode;
 use crate::modules::ModuleId;
 use crate::modules::ModuleLoadId;
 use crate::modules::ModuleLoader;
@@ -18,11 +20,13 @@ use crate::modules::Modules;
 use crate::modules::NoopModuleLoader;
 use crate::modules::PrepareLoadFuture;
 use crate::modules::RecursiveModuleLoad;
+use crate::modules::SyntheticExport;
 use crate::ops::*;
 use crate::shared_queue::SharedQueue;
 use crate::shared_queue::RECOMMENDED_SIZE;
 use crate::BufVec;
 use crate::OpState;
+use crate::ResourceTable;
 use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
 use futures::stream::StreamFuture;
@@ -99,6 +103,9 @@ pub(crate) struct JsRuntimeState {
   pub(crate) op_state: Rc<RefCell<OpState>>,
   loader: Rc<dyn ModuleLoader>,
   pub modules: Modules,
+  /// Exports of the synthetic modules that have not been evaluated yet.
+  pub(crate) synthetic_module_exports:
+    HashMap<ModuleId, Vec<(String, SyntheticExport)>>,
   pub(crate) dyn_import_map:
     HashMap<ModuleLoadId, v8::Global<v8::PromiseResolver>>,
   preparing_dyn_imports: FuturesUnordered<Pin<Box<PrepareLoadFuture>>>,
@@ -193,6 +200,10 @@ pub struct RuntimeOptions {
   /// Make sure to use [`add_near_heap_limit_callback`](#method.add_near_heap_limit_callback)
   /// to prevent v8 from crashing when reaching the upper limit.
   pub heap_limits: Option<HeapLimits>,
+
+  /// Hand out unpredictable, generation-tagged resource ids instead of
+  /// sequential ones. See `ResourceTable::with_random_ids()`.
+  pub random_resource_ids: bool,
 }
 
 impl JsRuntime {
@@ -258,7 +269,10 @@ impl JsRuntime {
     let js_error_create_fn = options
       .js_error_create_fn
       .unwrap_or_else(|| Box::new(JsError::create));
-    let op_state = OpState::default();
+    let mut op_state = OpState::default();
+    if options.random_resource_ids {
+      op_state.resource_table = ResourceTable::with_random_ids();
+    }
 
     isolate.set_slot(Rc::new(RefCell::new(JsRuntimeState {
       global_context: Some(global_context),
@@ -273,6 +287,7 @@ impl JsRuntime {
       op_state: Rc::new(RefCell::new(op_state)),
       have_unpolled_ops: Cell::new(false),
       modules: Modules::new(),
+      synthetic_module_exports: HashMap::new(),
       loader,
       dyn_import_map: HashMap::new(),
       preparing_dyn_imports: FuturesUnordered::new(),
@@ -320,7 +335,9 @@ impl JsRuntime {
   fn shared_init(&mut self) {
     if self.needs_init {
       self.needs_init = false;
-      self.execute("core.js", include_str!("core.js")).unwrap();
+      self
+        .execute("deno:core/core.js", include_str!("core.js"))
+        .unwrap();
     }
   }
 
@@ -645,6 +662,44 @@ impl JsRuntime {
     Ok(id)
   }
 
+  /// Low-level synthetic module creation. The exports are set when the
+  /// module is evaluated.
+  fn mod_new_synthetic(
+    &mut self,
+    main: bool,
+    name: &str,
+    exports: Vec<(String, SyntheticExport)>,
+  ) -> ModuleId {
+    let state_rc = Self::state(self.v8_isolate());
+    let context = self.global_context();
+    let scope = &mut v8::HandleScope::with_context(self.v8_isolate(), context);
+
+    let name_str = v8::String::new(scope, name).unwrap();
+    let export_names: Vec<v8::Local<v8::String>> = exports
+      .iter()
+      .map(|(export_name, _)| v8::String::new(scope, export_name).unwrap())
+      .collect();
+    let module = v8::Module::create_synthetic_module(
+      scope,
+      name_str,
+      &export_names,
+      bindings::synthetic_module_evaluation_steps,
+    );
+    let id = module.get_identity_hash();
+
+    let mut state = state_rc.borrow_mut();
+    state.synthetic_module_exports.insert(id, exports);
+    state.modules.register(
+      id,
+      name,
+      main,
+      v8::Global::<v8::Module>::new(scope, module),
+      vec![],
+    );
+
+    id
+  }
+
   /// Instantiates a ES module
   ///
   /// `AnyError` can be downcast to a type that exposes additional information
@@ -928,8 +983,7 @@ impl JsRuntime {
       module_url_found,
     } = info;
 
-    let is_main =
-      load.state == LoadState::LoadingRoot && !load.is_dynamic_import();
+    let is_main = load.state == LoadState::LoadingRoot && load.is_main();
     let referrer_specifier =
       ModuleSpecifier::resolve_url(&module_url_found).unwrap();
 
@@ -967,7 +1021,14 @@ impl JsRuntime {
         id
       }
       // Module not registered yet, do it now.
-      None => self.mod_new(is_main, &module_url_found, &code)?,
+      None => match code {
+        ModuleCode::Js(code) => {
+          self.mod_new(is_main, &module_url_found, &code)?
+        }
+        ModuleCode::Synthetic(exports) => {
+          self.mod_new_synthetic(is_main, &module_url_found, exports)
+        }
+      },
     };
 
     // Now we must iterate over all imports of the module and load them.
@@ -1011,19 +1072,73 @@ impl JsRuntime {
     specifier: &ModuleSpecifier,
     code: Option<String>,
   ) -> Result<ModuleId, AnyError> {
-    self.shared_init();
-    let loader = {
-      let state_rc = Self::state(self.v8_isolate());
-      let state = state_rc.borrow();
-      state.loader.clone()
-    };
-
     let load = RecursiveModuleLoad::main(
       self.op_state(),
       &specifier.to_string(),
       code,
-      loader,
+      self.loader(),
+    );
+    self.load(load).await
+  }
+
+  /// Asynchronously load specified side module and all of it's dependencies.
+  /// Unlike `JsRuntime::load_module`, any number of side modules can be
+  /// loaded, before or after the main module, and `import.meta.main` is
+  /// false in them.
+  ///
+  /// User must call `JsRuntime::mod_evaluate` with returned `ModuleId`
+  /// manually after load is finished.
+  pub async fn load_side_module(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    code: Option<String>,
+  ) -> Result<ModuleId, AnyError> {
+    let load = RecursiveModuleLoad::side(
+      self.op_state(),
+      &specifier.to_string(),
+      code,
+      self.loader(),
     );
+    self.load(load).await
+  }
+
+  /// Registers a synthetic module whose exports are provided by the embedder,
+  /// eg. a built-in `internal:` module, so that other modules can import it
+  /// without going through the `ModuleLoader`. The exports are set when the
+  /// module is evaluated, which happens the first time a module importing it
+  /// is evaluated, or when `JsRuntime::mod_evaluate` is called with returned
+  /// `ModuleId`.
+  pub fn register_synthetic_module(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    exports: Vec<(String, SyntheticExport)>,
+  ) -> Result<ModuleId, AnyError> {
+    let is_registered = {
+      let state_rc = Self::state(self.v8_isolate());
+      let state = state_rc.borrow();
+      state.modules.is_registered(specifier)
+    };
+    if is_registered {
+      return Err(generic_error(format!(
+        "Module \"{}\" is already registered",
+        specifier
+      )));
+    }
+    let id = self.mod_new_synthetic(false, &specifier.to_string(), exports);
+    self.mod_instantiate(id).map(|_| id)
+  }
+
+  fn loader(&mut self) -> Rc<dyn ModuleLoader> {
+    let state_rc = Self::state(self.v8_isolate());
+    let state = state_rc.borrow();
+    state.loader.clone()
+  }
+
+  async fn load(
+    &mut self,
+    load: RecursiveModuleLoad,
+  ) -> Result<ModuleId, AnyError> {
+    self.shared_init();
     let (_load_id, prepare_result) = load.prepare().await;
 
     let mut load = prepare_result?;
@@ -2089,9 +2204,9 @@ pub mod tests {
       let info = ModuleSource {
         module_url_specified: specifier.to_string(),
         module_url_found: specifier.to_string(),
-        code: "export function b() { return 'b' }".to_owned(),
+        code: ModuleCode::Js("export function b() { return 'b' }".to_owned()),
       };
-      async move { Ok(info) }.boxed()
+      async move { Ok(info) }.boxed_local()
     }
 
     fn prepare_load(
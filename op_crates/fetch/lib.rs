@@ -3,6 +3,7 @@
 #![deny(warnings)]
 
 use deno_core::error::bad_resource_id;
+use deno_core::error::custom_error;
 use deno_core::error::type_error;
 use deno_core::error::AnyError;
 use deno_core::futures;
@@ -27,6 +28,7 @@ use std::cell::RefCell;
 use std::convert::From;
 use std::fs::File;
 use std::io::Read;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::rc::Rc;
 
@@ -64,6 +66,21 @@ pub fn init(isolate: &mut JsRuntime) {
 pub trait FetchPermissions {
   fn check_net_url(&self, _url: &Url) -> Result<(), AnyError>;
   fn check_read(&self, _p: &PathBuf) -> Result<(), AnyError>;
+
+  /// Whether the address of a URL must be checked with `check_net_addr`. When
+  /// it must, `op_fetch` refuses URLs whose host is not an IP address.
+  fn checks_net_addrs(&self) -> bool {
+    false
+  }
+
+  /// Check the IP address of `url`, after `check_net_url`.
+  fn check_net_addr(
+    &self,
+    _url: &Url,
+    _addr: &SocketAddr,
+  ) -> Result<(), AnyError> {
+    Ok(())
+  }
 }
 
 /// For use with `op_fetch` when the user does not want permissions.
@@ -133,6 +150,25 @@ where
     let state_ = state.borrow();
     let permissions = state_.borrow::<FP>();
     permissions.check_net_url(&url_)?;
+    if permissions.checks_net_addrs() {
+      // reqwest resolves a host name itself when it connects, so the address
+      // it ends up using can't be checked here. Only IP addresses are allowed.
+      let ip = match url_.host() {
+        Some(url::Host::Ipv4(ip)) => ip.into(),
+        Some(url::Host::Ipv6(ip)) => ip.into(),
+        _ => {
+          return Err(custom_error(
+            "PermissionDenied",
+            format!(
+              "fetch can't connect to host name \"{}\" when --allow-net or --deny-net have IP address rules, use an IP address instead",
+              url_.host_str().unwrap_or("")
+            ),
+          ))
+        }
+      };
+      let port = url_.port_or_known_default().unwrap_or(0);
+      permissions.check_net_addr(&url_, &SocketAddr::new(ip, port))?;
+    }
   }
 
   let mut request = client.request(method, url_);